@@ -0,0 +1,131 @@
+//! This module contains [BoundedHashHeap], a hashheap that holds at most a
+//! fixed number of entries.  When a new key arrives while it's full, the
+//! [Overflow] policy given at creation decides whether the new pair is
+//! rejected or the entry with the lowest priority is evicted to make room,
+//! which keeps the best N pairs seen so far.  Both ends of the queue are
+//! reached through a [MinMaxHashHeap].
+
+use crate::MinMaxHashHeap;
+use core::hash::Hash;
+
+/// What a full [BoundedHashHeap] does with a new key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Overflow {
+  /// the new pair is rejected
+  #[default]
+  Reject,
+  /// The entry with the lowest priority is evicted, unless the new pair
+  /// has no higher priority than it, in which case the new pair is
+  /// rejected instead.
+  EvictWorst,
+}
+
+/// A hashheap with a maximum number of entries: see the
+/// [module documentation](crate::boundedhashheap).
+/// ```
+/// # use hashheap::*;
+///   // keep the 3 highest scores
+///   let mut top = BoundedHashHeap::<&str,u32>::new(3, true, Overflow::EvictWorst);
+///   for (name,score) in [("a",50),("b",90),("c",70),("d",60),("e",40)] {
+///     let _ = top.insert(name, score);
+///   }
+///   assert_eq!(top.len(), 3);
+///   assert_eq!(top.peek(), Some((&"b",&90)));
+///   assert_eq!(top.peek_worst(), Some((&"d",&60)));
+///   assert_eq!(top.insert("f", 10), Err(("f",10)));   // not good enough
+///   assert_eq!(top.insert("g", 80), Ok(Some(("d",60))));  // evicted
+/// ```
+#[derive(Clone, Debug)]
+pub struct BoundedHashHeap<KT,VT>
+{
+  heap : MinMaxHashHeap<KT,VT>,
+  maxlen : usize,
+  maxheap : bool,
+  overflow : Overflow,
+}
+impl<KT:Hash+Eq+Clone, VT:PartialOrd> BoundedHashHeap<KT,VT> {
+
+  /// creates an empty structure holding at most `maxlen` entries, which is
+  /// at least one; true = maxheap, false = minheap
+  pub fn new(maxlen:usize, maxheap:bool, overflow:Overflow) -> Self {
+    let maxlen = maxlen.max(1);
+    BoundedHashHeap { heap:MinMaxHashHeap::with_capacity(maxlen), maxlen, maxheap, overflow }
+  }
+
+  // true if a has lower priority than b
+  fn lower(&self, a:&VT, b:&VT) -> bool { if self.maxheap {a < b} else {b < a} }
+
+  /// Adds or changes a key-value pair.  Returns the pair that left the
+  /// structure, if any: the replaced pair of an existing key, or the
+  /// evicted pair.  If the structure is full and the new pair is rejected
+  /// by the [Overflow] policy, it's returned as an error.  O(log n)
+  pub fn insert(&mut self, key:KT, val:VT) -> Result<Option<(KT,VT)>,(KT,VT)> {
+    if self.heap.contains_key(&key) || self.heap.len() < self.maxlen {
+      return Ok(self.heap.insert(key,val));
+    }
+    match self.overflow {
+      Overflow::Reject => Err((key,val)),
+      Overflow::EvictWorst => {
+        match self.peek_worst() {
+          Some((_,w)) if self.lower(w,&val) => {
+            let evicted = self.pop_worst();
+            self.heap.push(key,val);
+            Ok(evicted)
+          },
+          _ => Err((key,val)),
+        }
+      },
+    }
+  }//insert
+
+  /// returns the value associated with the key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> { self.heap.get(key) }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// applies the closure to the value associated with the key and
+  /// repositions it.  Returns false if the key was not found.  O(log n)
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    self.heap.modify(key,f)
+  }
+
+  /// removes and returns the pair with the given key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> { self.heap.remove(key) }
+
+  /// returns the pair with the highest priority.  O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    if self.maxheap {self.heap.peek_max()} else {self.heap.peek_min()}
+  }
+
+  /// removes and returns the pair with the highest priority.  O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    if self.maxheap {self.heap.pop_max()} else {self.heap.pop_min()}
+  }
+
+  /// returns the pair with the lowest priority, which is the next to be
+  /// evicted.  O(1)
+  pub fn peek_worst(&self) -> Option<(&KT,&VT)> {
+    if self.maxheap {self.heap.peek_min()} else {self.heap.peek_max()}
+  }
+
+  /// removes and returns the pair with the lowest priority.  O(log n)
+  pub fn pop_worst(&mut self) -> Option<(KT,VT)> {
+    if self.maxheap {self.heap.pop_min()} else {self.heap.pop_max()}
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.is_empty() }
+
+  /// the maximum number of entries
+  pub fn max_len(&self) -> usize { self.maxlen }
+
+  /// returns true if the number of entries has reached the maximum
+  pub fn is_full(&self) -> bool { self.heap.len() >= self.maxlen }
+
+  /// iterates over the entries in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ { self.heap.iter() }
+}//impl BoundedHashHeap
@@ -0,0 +1,128 @@
+//! This module contains [BulkLoader], which builds a [HashHeap] from
+//! pairs produced by several threads.  Each thread obtains a [Stager] from
+//! the loader and stages pairs into a buffer of its own, which is handed
+//! over to the loader in one step, under a lock, whenever it fills up and
+//! when the stager is dropped.  [BulkLoader::finish] then hashes the keys
+//! of all the buffers in parallel and builds the HashHeap with a single
+//! O(n) *heapify* pass, instead of O(log n) per insertion.
+//!
+//! If the same key is staged more than once, only one of its values is
+//! kept, as with [HashHeap::from_pairs], but which one depends on the
+//! order in which the threads handed over their buffers.
+
+use crate::HashHeap;
+use core::hash::Hash;
+use std::sync::Mutex;
+
+/// The number of pairs a [Stager] buffers before handing them over
+pub const STAGER_BUFFER : usize = 4096;
+
+/// Builds a HashHeap from pairs staged by multiple threads: see the
+/// [module documentation](crate::bulkloader).
+/// ```
+/// # use hashheap::*;
+///   let loader = BulkLoader::<u32,u32>::new(false);
+///   std::thread::scope(|s| {
+///     for t in 0..4 {
+///       let mut stager = loader.stager();
+///       s.spawn(move || {
+///         for i in 0..1000 { stager.push(t*1000+i, (t*1000+i)%97); }
+///       });
+///     }
+///   });
+///   let mut hh = loader.finish();
+///   assert_eq!(hh.len(), 4000);
+///   assert_eq!(hh.pop().map(|p|p.1), Some(0));
+/// ```
+#[derive(Debug)]
+pub struct BulkLoader<KT,VT>
+{
+  staged : Mutex<Vec<Vec<(KT,VT)>>>,
+  heap : HashHeap<KT,VT>,  // empty, holds the hash state to be used
+}
+impl<KT:Hash+Eq+Send, VT:PartialOrd+Send> BulkLoader<KT,VT> {
+
+  /// creates a loader of a HashHeap; true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    Self::for_hashheap(HashHeap::with_capacity(0,maxheap))
+  }
+
+  /// creates a loader that builds the given HashHeap, keeping its hash
+  /// and comparison functions.  Any entries it contains are discarded.
+  pub fn for_hashheap(heap:HashHeap<KT,VT>) -> Self {
+    BulkLoader { staged:Mutex::new(Vec::new()), heap }
+  }
+
+  /// returns a stager to be moved into a producing thread
+  pub fn stager(&self) -> Stager<'_,KT,VT> {
+    Stager { loader:self, buffer:Vec::new() }
+  }
+
+  /// the number of pairs handed over by stagers so far
+  pub fn staged(&self) -> usize {
+    self.staged.lock().unwrap_or_else(|e|e.into_inner()).iter().map(|b|b.len()).sum()
+  }
+
+  /// Builds the HashHeap from the staged pairs.  The keys are hashed by
+  /// as many threads as are available, then the heap is built in O(n)
+  /// time.
+  pub fn finish(self) -> HashHeap<KT,VT> {
+    let BulkLoader { staged, mut heap } = self;
+    let buffers = staged.into_inner().unwrap_or_else(|e|e.into_inner());
+    let threads = std::thread::available_parallelism().map_or(1, |n|n.get());
+    let total:usize = buffers.iter().map(|b|b.len()).sum();
+    let per_thread = total.div_ceil(threads).max(STAGER_BUFFER);
+    // group the buffers into batches of about per_thread pairs
+    let mut batches:Vec<Vec<Vec<(KT,VT)>>> = vec![Vec::new()];
+    let mut batchsize = 0;
+    for b in buffers {
+      if batchsize >= per_thread { batches.push(Vec::new()); batchsize = 0; }
+      batchsize += b.len();
+      batches.last_mut().unwrap().push(b);
+    }
+    let hashed:Vec<Vec<(KT,VT,usize)>> = {
+      let hasher = heap.key_hasher();
+      let hasher = &hasher;
+      std::thread::scope(|s| {
+        let handles:Vec<_> = batches.into_iter().map(|batch| s.spawn(move || {
+          batch.into_iter().flatten().map(|(k,v)| { let h = hasher(&k); (k,v,h) })
+            .collect::<Vec<_>>()
+        })).collect();
+        handles.into_iter().map(|h|h.join().unwrap()).collect()
+      })
+    };
+    heap.heapify_hashed(hashed.into_iter().flatten().collect());
+    heap
+  }//finish
+}//impl BulkLoader
+
+// without bounds, so that a Stager can call it when dropped
+impl<KT,VT> BulkLoader<KT,VT> {
+  // takes over a full buffer
+  fn hand_over(&self, buffer:Vec<(KT,VT)>) {
+    if buffer.is_empty() { return; }
+    self.staged.lock().unwrap_or_else(|e|e.into_inner()).push(buffer);
+  }
+}
+
+/// A buffer of pairs staged by one thread for a [BulkLoader]
+pub struct Stager<'a,KT,VT>
+{
+  loader : &'a BulkLoader<KT,VT>,
+  buffer : Vec<(KT,VT)>,
+}
+impl<'a, KT:Hash+Eq+Send, VT:PartialOrd+Send> Stager<'a,KT,VT> {
+  /// stages a pair, handing over the buffer to the loader when it is full
+  pub fn push(&mut self, key:KT, val:VT) {
+    if self.buffer.capacity() == 0 { self.buffer.reserve_exact(STAGER_BUFFER); }
+    self.buffer.push((key,val));
+    if self.buffer.len() >= STAGER_BUFFER {
+      self.loader.hand_over(core::mem::take(&mut self.buffer));
+    }
+  }
+}
+impl<'a, KT, VT> Drop for Stager<'a,KT,VT> {
+  fn drop(&mut self) {
+    self.loader.hand_over(core::mem::take(&mut self.buffer));
+  }
+}
@@ -0,0 +1,226 @@
+//! Wrappers around [HashHeap] with the method names and signatures of
+//! the `priority-queue` and `keyed_priority_queue` crates, to ease
+//! migration and side-by-side benchmarking.  Only the common subset of
+//! each API is provided.  Like the originals, both are max-queues:
+//! the item with the greatest priority is popped first.  This module is
+//! not re-exported at the top level, so that its type names don't clash
+//! with those of the original crates; refer to it as `hashheap::compat`.
+//! ```
+//! use hashheap::compat::PriorityQueue;
+//! let mut pq = PriorityQueue::new();
+//! pq.push("apple", 3);
+//! pq.push("pear", 5);
+//! pq.change_priority(&"apple", 9);
+//! assert_eq!(pq.pop(), Some(("apple", 9)));
+//! ```
+
+use crate::HashHeap;
+use core::fmt;
+use core::hash::Hash;
+
+/// Version of `priority_queue::PriorityQueue` built on a [HashHeap]
+#[derive(Clone, Debug)]
+pub struct PriorityQueue<I,P>
+{
+  heap : HashHeap<I,P>,
+}
+impl<I:Hash+Eq, P:PartialOrd> PriorityQueue<I,P> {
+  /// creates an empty queue
+  pub fn new() -> Self { Self::with_capacity(0) }
+
+  /// creates an empty queue with the given capacity
+  pub fn with_capacity(capacity:usize) -> Self {
+    PriorityQueue { heap : HashHeap::with_capacity(capacity,true) }
+  }
+
+  /// Inserts the item, or changes its priority if it's already present,
+  /// returning the old priority.
+  pub fn push(&mut self, item:I, priority:P) -> Option<P> {
+    self.heap.insert(item,priority).map(|(_,p)|p)
+  }
+
+  /// Inserts the item, or raises its priority if the new priority is
+  /// greater.  Returns None if the item was inserted, the old priority if
+  /// it was replaced, and the given priority if the old one was kept.
+  pub fn push_increase(&mut self, item:I, priority:P) -> Option<P> {
+    match self.heap.get(&item) {
+      Some(p) if *p >= priority => Some(priority),
+      _ => self.push(item,priority),
+    }
+  }
+
+  /// Inserts the item, or lowers its priority if the new priority is
+  /// smaller.  Returns None if the item was inserted, the old priority if
+  /// it was replaced, and the given priority if the old one was kept.
+  pub fn push_decrease(&mut self, item:I, priority:P) -> Option<P> {
+    match self.heap.get(&item) {
+      Some(p) if *p <= priority => Some(priority),
+      _ => self.push(item,priority),
+    }
+  }
+
+  /// changes the priority of an item already present, returning the old
+  /// priority, or None if the item is not present
+  pub fn change_priority(&mut self, item:&I, priority:P) -> Option<P> {
+    let mut old = None;
+    self.heap.modify(item, |p| old = Some(core::mem::replace(p,priority)));
+    old
+  }
+
+  /// changes the priority of an item with a closure, returning false if
+  /// the item is not present
+  pub fn change_priority_by<F:FnOnce(&mut P)>(&mut self, item:&I, f:F) -> bool {
+    self.heap.modify(item,f).is_some()
+  }
+
+  /// the priority of the item
+  pub fn get_priority(&self, item:&I) -> Option<&P> { self.heap.get(item) }
+
+  /// the item and its priority
+  pub fn get(&self, item:&I) -> Option<(&I,&P)> {
+    match self.heap.findslot(item) {
+      (h,true) => {
+        let (ki,vi) = self.heap.kmap[&h];
+        self.heap.keys[ki].as_ref().map(|i|(i,&self.heap.vals[vi].0))
+      },
+      _ => None,
+    }
+  }
+
+  /// the item with the greatest priority
+  pub fn peek(&self) -> Option<(&I,&P)> { self.heap.peek() }
+
+  /// removes and returns the item with the greatest priority
+  pub fn pop(&mut self) -> Option<(I,P)> { self.heap.pop() }
+
+  /// removes and returns the item
+  pub fn remove(&mut self, item:&I) -> Option<(I,P)> { self.heap.remove(item) }
+
+  /// the number of items
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if the queue is empty
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// removes all items
+  pub fn clear(&mut self) { self.heap.clear(); }
+
+  /// the items in order of decreasing priority
+  pub fn into_sorted_vec(mut self) -> Vec<I> {
+    core::iter::from_fn(|| self.heap.pop().map(|(i,_)|i)).collect()
+  }
+
+  /// iterates over the items in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&I,&P)> + '_ { self.heap.iter() }
+
+  /// returns the underlying HashHeap
+  pub fn into_hashheap(self) -> HashHeap<I,P> { self.heap }
+}//impl PriorityQueue
+
+impl<I:Hash+Eq, P:PartialOrd> Default for PriorityQueue<I,P> {
+  fn default() -> Self { Self::new() }
+}
+
+impl<I:Hash+Eq, P:PartialOrd> FromIterator<(I,P)> for PriorityQueue<I,P> {
+  fn from_iter<T:IntoIterator<Item=(I,P)>>(iter:T) -> Self {
+    let mut pq = Self::new();
+    for (i,p) in iter { pq.push(i,p); }
+    pq
+  }
+}
+
+/// Error returned by [KeyedPriorityQueue::set_priority] when the key is
+/// not present
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SetPriorityNotFoundError;
+impl fmt::Display for SetPriorityNotFoundError {
+  fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "key not found in queue")
+  }
+}
+impl std::error::Error for SetPriorityNotFoundError {}
+
+/// Version of `keyed_priority_queue::KeyedPriorityQueue` built on a
+/// [HashHeap]
+/// ```
+/// # use hashheap::compat::*;
+///   let mut q = KeyedPriorityQueue::new();
+///   q.push('a', 1);
+///   q.push('b', 2);
+///   assert_eq!(q.set_priority(&'a', 5), Ok(1));
+///   assert_eq!(q.set_priority(&'z', 5), Err(SetPriorityNotFoundError));
+///   assert_eq!(q.pop(), Some(('a',5)));
+///   assert_eq!(q.remove(&'b'), Some(2));
+/// ```
+#[derive(Clone, Debug)]
+pub struct KeyedPriorityQueue<K,P>
+{
+  heap : HashHeap<K,P>,
+}
+impl<K:Hash+Eq, P:PartialOrd> KeyedPriorityQueue<K,P> {
+  /// creates an empty queue
+  pub fn new() -> Self { Self::with_capacity(0) }
+
+  /// creates an empty queue with the given capacity
+  pub fn with_capacity(capacity:usize) -> Self {
+    KeyedPriorityQueue { heap : HashHeap::with_capacity(capacity,true) }
+  }
+
+  /// inserts the key, or changes its priority if it's already present,
+  /// returning the old priority
+  pub fn push(&mut self, key:K, priority:P) -> Option<P> {
+    self.heap.insert(key,priority).map(|(_,p)|p)
+  }
+
+  /// the key with the greatest priority
+  pub fn peek(&self) -> Option<(&K,&P)> { self.heap.peek() }
+
+  /// removes and returns the key with the greatest priority
+  pub fn pop(&mut self) -> Option<(K,P)> { self.heap.pop() }
+
+  /// the priority of the key
+  pub fn get_priority(&self, key:&K) -> Option<&P> { self.heap.get(key) }
+
+  /// changes the priority of a key already present, returning the old
+  /// priority
+  pub fn set_priority(&mut self, key:&K, priority:P) -> Result<P,SetPriorityNotFoundError> {
+    let mut old = None;
+    self.heap.modify(key, |p| old = Some(core::mem::replace(p,priority)));
+    old.ok_or(SetPriorityNotFoundError)
+  }
+
+  /// removes the key, returning its priority
+  pub fn remove(&mut self, key:&K) -> Option<P> {
+    self.heap.remove(key).map(|(_,p)|p)
+  }
+
+  /// removes the key, returning it with its priority
+  pub fn remove_entry(&mut self, key:&K) -> Option<(K,P)> { self.heap.remove(key) }
+
+  /// the number of keys
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if the queue is empty
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// removes all keys
+  pub fn clear(&mut self) { self.heap.clear(); }
+
+  /// iterates over the keys and priorities in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&K,&P)> + '_ { self.heap.iter() }
+
+  /// returns the underlying HashHeap
+  pub fn into_hashheap(self) -> HashHeap<K,P> { self.heap }
+}//impl KeyedPriorityQueue
+
+impl<K:Hash+Eq, P:PartialOrd> Default for KeyedPriorityQueue<K,P> {
+  fn default() -> Self { Self::new() }
+}
+
+impl<K:Hash+Eq, P:PartialOrd> FromIterator<(K,P)> for KeyedPriorityQueue<K,P> {
+  fn from_iter<T:IntoIterator<Item=(K,P)>>(iter:T) -> Self {
+    let mut q = Self::new();
+    for (k,p) in iter { q.push(k,p); }
+    q
+  }
+}
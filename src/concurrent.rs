@@ -0,0 +1,133 @@
+//! This module contains [ShardedHashHeap], a keyed priority queue that
+//! can be shared by multiple threads.  The entries are partitioned among
+//! a number of *shards*, each a [HashHeap] behind its own [Mutex], by the
+//! hash of their keys.  Each worker thread has a home shard from which it
+//! pops the locally best entry, and steals from the other shards only when
+//! its own is empty.  The order in which entries are popped is therefore
+//! only an approximation of the global priority order, in exchange for
+//! much less contention than a single locked structure.  Operations on
+//! a key (insert, get, modify, remove) always go to the same shard, so
+//! keys remain unique across all shards.
+
+use crate::HashHeap;
+use core::hash::{BuildHasher, Hash};
+use std::collections::hash_map::RandomState;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+/// A concurrent, relaxed-order keyed priority queue: see the
+/// [module documentation](crate::concurrent).
+/// ```
+/// # use hashheap::*;
+///   let queue = ShardedHashHeap::<u32,u32>::new(4, false);
+///   for i in 0..100 { queue.insert(i, i%10); }
+///   let total:u32 = std::thread::scope(|s| {
+///     let handles:Vec<_> = (0..4).map(|w| {
+///       let q = &queue;
+///       s.spawn(move || {
+///         let mut sum = 0;
+///         while let Some((k,_)) = q.pop(w) { sum += k; }
+///         sum
+///       })
+///     }).collect();
+///     handles.into_iter().map(|h|h.join().unwrap()).sum()
+///   });
+///   assert_eq!(total, 4950);
+///   assert!(queue.is_empty());
+/// ```
+#[derive(Debug)]
+pub struct ShardedHashHeap<KT,VT>
+{
+  shards : Vec<Mutex<HashHeap<KT,VT>>>,
+  router : RandomState,
+}
+impl<KT:Hash+Eq, VT:PartialOrd> ShardedHashHeap<KT,VT> {
+
+  /// creates a structure with the given number of shards, which is
+  /// typically the number of worker threads (at least one shard is
+  /// created); true = maxheap, false = minheap
+  pub fn new(shards:usize, maxheap:bool) -> Self {
+    ShardedHashHeap {
+      shards : (0..shards.max(1)).map(|_|Mutex::new(HashHeap::with_capacity(0,maxheap))).collect(),
+      router : RandomState::new(),
+    }
+  }
+
+  /// the number of shards
+  pub fn shards(&self) -> usize { self.shards.len() }
+
+  // locks a shard, ignoring poisoning: the heap is consistent between
+  // operations even if a thread panicked while holding the lock
+  fn lock(&self, i:usize) -> MutexGuard<'_,HashHeap<KT,VT>> {
+    self.shards[i].lock().unwrap_or_else(|e|e.into_inner())
+  }
+
+  fn shard_of(&self, key:&KT) -> usize {
+    (self.router.hash_one(key) as usize) % self.shards.len()
+  }
+
+  /// Add or change a key-value pair, returning the replaced pair, if it
+  /// exists.  Only the shard of the key is locked.
+  pub fn insert(&self, key:KT, val:VT) -> Option<(KT,VT)> {
+    self.lock(self.shard_of(&key)).insert(key,val)
+  }
+
+  /// returns a copy of the value associated with the key
+  pub fn get(&self, key:&KT) -> Option<VT> where VT:Clone {
+    self.lock(self.shard_of(key)).get(key).cloned()
+  }
+
+  /// modifies the value associated with the key: see [HashHeap::modify]
+  pub fn modify<F:FnOnce(&mut VT)>(&self, key:&KT, f:F) -> bool {
+    self.lock(self.shard_of(key)).modify(key,f).is_some()
+  }
+
+  /// removes and returns the pair with the given key
+  pub fn remove(&self, key:&KT) -> Option<(KT,VT)> {
+    self.lock(self.shard_of(key)).remove(key)
+  }
+
+  /// Removes and returns the highest-priority entry of the worker's home
+  /// shard, which is `worker % shards()`.  If the home shard is empty,
+  /// the other shards are tried in turn, first without blocking on shards
+  /// locked by other threads.  None is returned only if all shards are
+  /// found empty.
+  pub fn pop(&self, worker:usize) -> Option<(KT,VT)> {
+    let n = self.shards.len();
+    let home = worker % n;
+    if let Some(p) = self.lock(home).pop() { return Some(p); }
+    let mut busy = Vec::new();
+    for j in 1..n {
+      let i = (home+j) % n;
+      match self.shards[i].try_lock() {
+        Ok(mut hp) => { if let Some(p) = hp.pop() { return Some(p); } },
+        Err(TryLockError::Poisoned(e)) => {
+          if let Some(p) = e.into_inner().pop() { return Some(p); }
+        },
+        Err(TryLockError::WouldBlock) => { busy.push(i); },
+      }
+    }//for
+    busy.into_iter().find_map(|i|self.lock(i).pop())
+  }//pop
+
+  /// the total number of entries.  Because other threads may be changing
+  /// the shards, the result may already be out of date.
+  pub fn len(&self) -> usize {
+    (0..self.shards.len()).map(|i|self.lock(i).len()).sum()
+  }
+
+  /// returns true if all shards were found empty
+  pub fn is_empty(&self) -> bool {
+    (0..self.shards.len()).all(|i|self.lock(i).len() == 0)
+  }
+
+  /// moves all entries into a single [HashHeap]
+  pub fn into_hashheap(self) -> HashHeap<KT,VT> {
+    let mut shards = self.shards.into_iter()
+      .map(|m|m.into_inner().unwrap_or_else(|e|e.into_inner()));
+    let mut hh = shards.next().unwrap();
+    for s in shards {
+      for (k,v) in s { hh.insert(k,v); }
+    }
+    hh
+  }
+}//impl ShardedHashHeap
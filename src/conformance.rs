@@ -0,0 +1,150 @@
+//! A battery of behavioral tests that any implementation of [PriorityMap]
+//! should pass, enabled by the `test-suite` feature.  Each check takes a
+//! closure that creates an empty structure, with keys of type u32 and
+//! values of type i32, and whether the structure is a maxheap.  The
+//! checks panic with a description of the first violation found.  They
+//! need room for 64 entries, so a fixed capacity must be at least 64.
+//! [run_all] runs every check:
+//! ```
+//! # use hashheap::*;
+//!   hashheap::conformance::run_all(|| HashHeap::<u32,i32>::new_minheap(), false);
+//!   hashheap::conformance::run_all(|| ConstHashHeap::<u32,i32,64>::new(true), true);
+//! ```
+//! The same checks can be run on a new backend from its own tests.
+
+use crate::PriorityMap;
+use std::collections::HashSet;
+
+const N:u32 = 64;
+
+// deterministic values with many duplicates
+fn value(i:u32) -> i32 { ((i.wrapping_mul(2654435761) >> 7) % 40) as i32 - 20 }
+
+// true if a should be popped no later than b
+fn before(a:i32, b:i32, maxheap:bool) -> bool { if maxheap {a >= b} else {a <= b} }
+
+fn fill<P:PriorityMap<u32,i32>>(pm:&mut P) {
+  for i in 0..N {
+    assert!(matches!(pm.insert(i,value(i)), Ok(None)), "insert of new key {} failed", i);
+  }
+}
+
+// pops everything, checking the order and that each key appears once
+fn drain_checked<P:PriorityMap<u32,i32>>(pm:&mut P, maxheap:bool) -> Vec<(u32,i32)> {
+  let mut popped:Vec<(u32,i32)> = Vec::new();
+  let n = pm.len();
+  while let Some((k,v)) = pm.pop() {
+    if let Some(&(_,prev)) = popped.last() {
+      assert!(before(prev,v,maxheap), "pop returned {} after {}", v, prev);
+    }
+    assert!(pm.get(&k).is_none(), "key {} still present after pop", k);
+    popped.push((k,v));
+  }
+  assert_eq!(popped.len(), n, "pop returned {} pairs, len was {}", popped.len(), n);
+  let keys:HashSet<u32> = popped.iter().map(|p|p.0).collect();
+  assert_eq!(keys.len(), n, "pop returned duplicate keys");
+  assert!(pm.is_empty() && pm.peek().is_none(), "not empty after popping everything");
+  popped
+}
+
+/// insertion of new keys and replacement of the values of existing keys
+pub fn check_insert_replace<P,F>(mut make:F, maxheap:bool)
+where P:PriorityMap<u32,i32>, F:FnMut() -> P
+{
+  let mut pm = make();
+  assert!(pm.is_empty() && pm.len() == 0 && pm.peek().is_none() && pm.pop().is_none());
+  fill(&mut pm);
+  assert_eq!(pm.len(), N as usize);
+  for i in 0..N {
+    assert_eq!(pm.get(&i), Some(&value(i)), "get({}) after insert", i);
+    assert!(pm.contains_key(&i));
+    let replaced = pm.insert(i, value(i)+100);
+    assert!(matches!(replaced, Ok(Some(v)) if v == value(i)), "replacing key {} returned wrong value", i);
+  }
+  assert_eq!(pm.len(), N as usize, "replacement changed len");
+  assert!(pm.get(&N).is_none() && !pm.contains_key(&N));
+  let top = *pm.peek().expect("peek on nonempty structure").1;
+  let best = (0..N).map(|i|value(i)+100);
+  let best = if maxheap {best.max()} else {best.min()};
+  assert_eq!(Some(top), best, "peek after replacements");
+}//check_insert_replace
+
+/// repositioning of entries after their values are modified
+pub fn check_reposition<P,F>(mut make:F, maxheap:bool)
+where P:PriorityMap<u32,i32>, F:FnMut() -> P
+{
+  let mut pm = make();
+  fill(&mut pm);
+  let (hi, lo) = if maxheap {(1000,-1000)} else {(-1000,1000)};
+  assert!(pm.modify(&17, &mut |v|*v = hi));
+  assert_eq!(pm.peek(), Some((&17,&hi)), "modified value not moved to top");
+  assert!(pm.modify(&17, &mut |v|*v = lo));
+  assert_ne!(pm.peek().map(|p|*p.0), Some(17), "modified value not moved down");
+  assert!(!pm.modify(&N, &mut |v|*v = hi), "modify of absent key returned true");
+  let mut model:Vec<i32> = (0..N).map(value).collect();
+  model[17] = lo;
+  for i in (0..N).step_by(3) {
+    let d = (i as i32 % 7) - 3;
+    assert!(pm.modify(&i, &mut |v|*v += d));
+    model[i as usize] += d;
+  }
+  for (k,v) in drain_checked(&mut pm, maxheap) {
+    assert_eq!(v, model[k as usize], "key {} popped with wrong value", k);
+  }
+}//check_reposition
+
+/// consistency after removals
+pub fn check_removal<P,F>(mut make:F, maxheap:bool)
+where P:PriorityMap<u32,i32>, F:FnMut() -> P
+{
+  let mut pm = make();
+  fill(&mut pm);
+  for i in (0..N).filter(|i|i%2==0) {
+    assert_eq!(pm.remove(&i), Some((i,value(i))), "remove({})", i);
+    assert_eq!(pm.remove(&i), None, "second remove({})", i);
+    assert!(!pm.contains_key(&i));
+  }
+  assert_eq!(pm.len(), (N/2) as usize);
+  for i in 0..N {
+    let expected = if i%2==0 {None} else {Some(value(i))};
+    assert_eq!(pm.get(&i).copied(), expected, "get({}) after removals", i);
+  }
+  for i in (0..N).filter(|i|i%2==0) {  // reinsert into vacated slots
+    assert!(matches!(pm.insert(i,value(i)), Ok(None)));
+  }
+  let popped = drain_checked(&mut pm, maxheap);
+  assert_eq!(popped.len(), N as usize);
+  fill(&mut pm);  // reuse after emptying
+  assert_eq!(pm.len(), N as usize);
+}//check_removal
+
+/// the iterator visits each pair exactly once
+pub fn check_iteration<P,F>(mut make:F, maxheap:bool)
+where P:PriorityMap<u32,i32>, F:FnMut() -> P
+{
+  let mut pm = make();
+  assert_eq!(pm.iter().count(), 0);
+  fill(&mut pm);
+  for i in (0..N).step_by(5) { pm.remove(&i); }
+  pm.modify(&1, &mut |v|*v -= 50);
+  let mut seen = HashSet::new();
+  for (k,v) in pm.iter() {
+    assert!(seen.insert(*k), "iterator returned key {} twice", k);
+    assert_eq!(pm.get(k), Some(v), "iterator returned wrong value for key {}", k);
+  }
+  assert_eq!(seen.len(), pm.len(), "iterator count differs from len");
+  let top = pm.peek().map(|(k,v)|(*k,*v)).unwrap();
+  for (_,v) in pm.iter() {
+    assert!(before(top.1,*v,maxheap), "peek {} is not the highest priority", top.1);
+  }
+}//check_iteration
+
+/// runs all of the checks of this module
+pub fn run_all<P,F>(mut make:F, maxheap:bool)
+where P:PriorityMap<u32,i32>, F:FnMut() -> P
+{
+  check_insert_replace(&mut make, maxheap);
+  check_reposition(&mut make, maxheap);
+  check_removal(&mut make, maxheap);
+  check_iteration(&mut make, maxheap);
+}
@@ -10,15 +10,20 @@
 //! the mapped value.  The values array contains entries of the form
 //! (value,ki) where ki is the index in the keys array of the corresponding
 //! key.  The keys array is treated as a closed hashmap (open addressing)
-//! with a linear probing rehash function.  The values array is treated
+//! with a linear probing rehash function.  Removals use *backward-shift
+//! deletion* instead of leaving tombstones: the keys that follow a removed
+//! key in its probe sequence are moved back, so that lookups stay as short
+//! as in a freshly built table however many keys are inserted and
+//! removed.  The values array is treated
 //! as a binary heap. Swapping values in the values array updates
 //! the corresponding information in the keys array using the ki index
-//! which it possesses.  The ki index does not change until the entire
-//! structure is resized.
+//! which it possesses.  The ki index of a key changes when the structure
+//! is resized or refreshed, and when the removal of another key in the
+//! same cluster shifts it back.
 //!
 //! The internal structure of the implementation allows for the following
 //! benefit.  The indices of keys in the internal hash array do not change
-//! unless removed.  Several functions including [ConstHashHeap::set_at],
+//! until a key near them is removed.  Several functions including [ConstHashHeap::set_at],
 //! [ConstHashHeap::and_generate] and [ConstHashHeap::modify_at]
 //! returns the internal index where the key was found or inserted.  This
 //! index can then be used by functions such as [ConstHashHeap::get_at]
@@ -46,9 +51,9 @@ use core::hash::{BuildHasher, Hash, Hasher};
 
 
 //global heap calculations
-fn left(i:usize) -> usize { 2*i+1 }
-fn right(i:usize) -> usize { 2*i+2 }
-fn parent(i:usize) -> usize { (i-1)/2 }
+pub(crate) fn left(i:usize) -> usize { 2*i+1 }
+pub(crate) fn right(i:usize) -> usize { 2*i+2 }
+pub(crate) fn parent(i:usize) -> usize { (i-1)/2 }
 
 fn optcmp<VT:PartialOrd>(a:&Option<(VT,usize)>, b:&Option<(VT,usize)>, neg:bool) -> bool
 {
@@ -59,20 +64,53 @@ fn optcmp<VT:PartialOrd>(a:&Option<(VT,usize)>, b:&Option<(VT,usize)>, neg:bool)
   }
 }
 
+/// Error returned when an operation would require more than the fixed
+/// capacity of a [ConstHashHeap].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapacityError {
+  /// the number of entries the receiving structure accepts: its capacity
+  /// CAP, or less if a [maximum load factor](ConstHashHeap::set_max_load)
+  /// is set
+  pub capacity : usize,
+  /// the number of entries that would have been required
+  pub required : usize,
+}
+impl Display for CapacityError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    write!(f,"capacity {} exceeded: {} entries required",self.capacity,self.required)
+  }
+}
+impl std::error::Error for CapacityError {}
+
 /// A version of hashheap map with const capacity: see [module documentation](crate::consthashheap) for overview.
-/// The default capacity of a ConstHashHeap is 1024.  Exact powers of
-/// two are recommended for other capacities.  Resizing is recommended
-/// when the [ConstHashHeap::load_factor] function returns a value greater 
-/// than 0.75.  
-#[derive(Clone, Debug)]
+/// The default capacity of a ConstHashHeap is 1024.  Any capacity may be
+/// used: hash values are reduced to the capacity with a bit mask when it's
+/// an exact power of two and with a multiply-shift otherwise, so neither
+/// requires a division.  Lookups slow down sharply as the table
+/// fills up, so a maximum load factor may be set with
+/// [ConstHashHeap::set_max_load]: insertions of new keys beyond it are
+/// rejected, and [ConstHashHeap::needs_resize] signals when the structure
+/// should be [resized](ConstHashHeap::resize).  Refreshing after many removals can be automated with
+/// [ConstHashHeap::set_refresh_threshold].
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
 pub struct ConstHashHeap<KT,VT, const CAPACITY:usize = 1024>
 {
    keys : [Option<(KT,usize)>;CAPACITY],
    vals : [Option<(VT,usize)>;CAPACITY],
    maxhashes : [usize;CAPACITY], // max number of hashes from start
+   fullhashes : [u64;CAPACITY],  // unreduced hash of key at each index
    size : usize,
    autostate: RandomState,
    lessthan : fn(&Option<(VT,usize)>,&Option<(VT,usize)>) -> bool,
+   maxheap : bool,
+   probesum : usize,  // sum of maxhashes
+   probed : usize,    // number of nonzero maxhashes
+   vacated : usize,   // removals since last refresh
+   refresh_threshold : f32,
+   auto_refresh : bool,
+   maxload : f32,
+   loadlimit : usize, // max number of entries allowed by maxload
 }
 impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
 
@@ -83,26 +121,42 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
       keys : [const { None }; CAP],
       vals : [const { None }; CAP], //std::array::from_fn(|_|None),
       maxhashes : [0;CAP],
+      fullhashes : [0;CAP],
       size : 0,
       autostate : RandomState::new(),
       lessthan : if maxheap{|a,b|optcmp(a,b,true)} else {|a,b|optcmp(a,b,false)},
+      maxheap,
+      probesum : 0,
+      probed : 0,
+      vacated : 0,
+      refresh_threshold : f32::INFINITY,
+      auto_refresh : false,
+      maxload : 1.0,
+      loadlimit : CAP,
     }
   }//new
 
-  fn hash(&self,key:&KT) -> usize {
-     let mut bs = self.autostate.build_hasher(); //rs.build_hasher();
-     key.hash(&mut bs);
-     (bs.finish() as usize) % CAP
+  // The full 64-bit hash of each key is kept in fullhashes, so that probe
+  // loops compare hashes before calling == on keys, and so that keys are
+  // never hashed again when they're moved by backshift or resize.
+  fn fullhash(&self,key:&KT) -> u64 {
+     self.autostate.hash_one(key)
   }
 
-  fn rehash(h:usize) -> usize { (h+1) % CAP }
+  fn hash(&self,key:&KT) -> usize { Self::reduce(self.fullhash(key)) }
 
-  fn borrow_hash(&self, key:&KT, rs:&RandomState) -> usize {
-     let mut bs = rs.build_hasher();
-     key.hash(&mut bs);
-     (bs.finish() as usize) % CAP
+  fn rehash(h:usize) -> usize { if h+1 < CAP {h+1} else {0} }
+
+  // maps a 64-bit hash into 0..CAP without division: a mask when CAP is
+  // a power of two, otherwise Lemire's multiply-shift range reduction.
+  // The branch is on a constant and is resolved at compile time.
+  #[inline]
+  fn reduce(h:u64) -> usize {
+    if CAP.is_power_of_two() { (h as usize) & (CAP-1) }
+    else { (((h as u128) * (CAP as u128)) >> 64) as usize }
   }
 
+  #[allow(clippy::option_map_unit_fn)]
   fn swap(&mut self, i:usize, k:usize) {
     self.vals.swap(i,k);
     if let Some((ival,ik)) = &mut self.vals[i] {
@@ -163,44 +217,40 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
   pub fn insert(&mut self, key:KT, val:VT) -> bool
   { 
     //if (self.size >= CAP) {return false;}
-    let h0 = self.hash(&key);
+    if self.auto_refresh && self.vacated >= CAP/8 && self.needs_refresh() {
+      self.refresh_in_place();
+    }
+    let hv = self.fullhash(&key);
+    let h0 = Self::reduce(hv);
     let mut h = h0;
     let mut hashes = 1;
-    let mut target_index = -1;
     let mut keyfoundloc = None;
     loop {
       match &self.keys[h] {
-        Some((k,vi)) if k==&key => {
+        Some((k,vi)) if self.fullhashes[h]==hv && k==&key => {
           keyfoundloc = Some(*vi);
           break;
         },
-        Some(_) => { h = Self::rehash(h); hashes+=1; },
-        None if hashes < self.maxhashes[h0] => {
-          if target_index == -1 { target_index = h as isize; }
-          h=Self::rehash(h);
-          hashes += 1;
-        },
-        None => {
+        Some(_) if hashes < CAP => { h = Self::rehash(h); hashes+=1; },
+        _ => {  // end of cluster (see backshift), or table is full
           keyfoundloc = Some(self.size);
           break;
         },
       }//match
     }// loop
-    match &keyfoundloc {  // reuse slot
-      Some(vi) if *vi==self.size && self.size >= CAP => {
+    match &keyfoundloc {
+      Some(vi) if *vi==self.size && self.size >= self.loadlimit => {
         return false;
       }
       Some(vi) if *vi == self.size => {
         self.size+=1;
-        if target_index>=0 {h = target_index as usize;}
       },
       _ => {},
     }//match
-    if hashes > self.maxhashes[h0] {
-      self.maxhashes[h0] = hashes;
-    }
+    self.set_maxhashes(h0, hashes);
     if let Some(vi) = keyfoundloc {
         self.keys[h] = Some((key,vi));
+        self.fullhashes[h] = hv;
         self.vals[vi] = Some((val,h));
         self.adjust(vi, vi+1<self.size);
     }
@@ -208,62 +258,91 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
   }//set
 
 
-  // also returns where modified/inserted in keys
-  fn find_and<F>(&mut self, key:KT, modifier:F)
-     -> (Option<VT>, Option<usize>) where F: FnOnce(Option<&VT>) -> VT
-  {
-    let mut valpos = None; // vi position
-    let h0 = self.hash(&key);
-    let mut h = h0;
+  // locates key for insertion: returns (h,hv,hashes,found) where h is the
+  // hash index of the key if found, or else of the slot where it should be
+  // inserted, hv the full hash and hashes the probe length.
+  fn probe(&self, key:&KT) -> (usize,u64,usize,bool) {
+    let hv = self.fullhash(key);
+    let mut h = Self::reduce(hv);
     let mut hashes = 1;
-    let mut reuse_index = -1;
     loop {
       match &self.keys[h] {
-        Some((k,vi)) if k==&key => {
-          valpos = Some(*vi);
-          break;
-        },
-        Some(_) => { h = Self::rehash(h); hashes+=1; },
-        None if hashes < self.maxhashes[h0] => {
-          if reuse_index == -1 { reuse_index = h as isize; }
-          h=Self::rehash(h);
-          hashes += 1;
-        },
-        None => {
-          valpos = Some(self.size);
-          break;
+        Some((k,vi)) if self.fullhashes[h]==hv && k==key => {
+          return (h,hv,hashes,true);
         },
+        Some(_) if hashes < CAP => { h = Self::rehash(h); hashes+=1; },
+        _ => { break; },
       }//match
     }// loop
-    match &valpos {  // reuse slot
-      Some(vi) if *vi==self.size && self.size >= CAP => {
-        return (None, None);
-      }
-      Some(vi) if *vi == self.size => {
-        self.size+=1;
-        if reuse_index>=0 {h = reuse_index as usize;}
-      },
-      _ => {},
-    }//match
-    if hashes > self.maxhashes[h0] {
-      self.maxhashes[h0] = hashes;
+    (h,hv,hashes,false)
+  }//probe
+
+  // inserts a new key at the slot found by probe, None if full
+  fn insert_probed(&mut self, (h,hv,hashes):(usize,u64,usize), key:KT, val:VT)
+    -> Option<usize>
+  {
+    if self.size >= self.loadlimit { return None; }
+    self.set_maxhashes(Self::reduce(hv), hashes);
+    self.fullhashes[h] = hv;
+    let vi = self.size;
+    self.size += 1;
+    self.keys[h] = Some((key,vi));
+    self.vals[vi] = Some((val,h));
+    self.swapup(vi);
+    Some(h)
+  }//insert_probed
+
+  // also returns where modified/inserted in keys
+  pub(crate) fn find_and<F>(&mut self, key:KT, modifier:F)
+     -> (Option<VT>, Option<usize>) where F: FnOnce(Option<&VT>) -> VT
+  {
+    let (h,hv,hashes,found) = self.probe(&key);
+    if !found {
+      // check the capacity before the modifier computes a value
+      if self.size >= self.loadlimit { return (None, None); }
+      return (None, self.insert_probed((h,hv,hashes),key,modifier(None)));
     }
     let mut swaptmp = None;
-    if let Some(vi) = valpos {
+    if let Some(vi) = self.keys[h].as_ref().map(|p|p.1) {
         self.keys[h] = Some((key,vi));
         std::mem::swap(&mut self.vals[vi], &mut swaptmp);
-        self.vals[vi] = Some((modifier(swaptmp.as_ref().map(|(v,_)|v)), h)); 
+        self.vals[vi] = Some((modifier(swaptmp.as_ref().map(|(v,_)|v)), h));
         self.adjust(vi, vi+1<self.size);
     }
     (swaptmp.map(|p|p.0), Some(h))
   }//find_and
 
+  /// returns an [CHHEntry] for the given key, which allows the value
+  /// associated with the key to be inserted or modified after a single
+  /// hash lookup.  The *hash index* of the entry is available for later
+  /// calls to functions such as [get_at](Self::get_at).
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut counts = ConstHashHeap::<&str,u32,16>::new(true);
+  ///   for w in ["a","b","a","c","a","b"] {
+  ///     counts.entry(w).and_modify(|c|*c+=1).or_insert(1);
+  ///   }
+  ///   assert_eq!(counts.peek(), Some((&"a",&3)));
+  ///   let hi = counts.entry("b").hash_index();
+  ///   assert_eq!(counts.get_at(hi.unwrap(), &"b"), Some(&2));
+  /// ```
+  pub fn entry<'a>(&'a mut self, key:KT) -> CHHEntry<'a,KT,VT,CAP> {
+    let (h,hv,hashes,found) = self.probe(&key);
+    if found {
+      CHHEntry::Occupied(CHHOccupiedEntry { chh:self, index:h })
+    }
+    else {
+      CHHEntry::Vacant(CHHVacantEntry { chh:self, key, probed:(h,hv,hashes) })
+    }
+  }//entry
+
   /// Inserts new key with value determined by the supplied closure,
   /// which is applied to the existing value associated with the key,
   /// if it exists.  The function returns the *hash index* of where
   /// the insertion occurred.  This index can be used by functions such
   /// as [modify_at](Self::modify_at) and [get_at](Self::get_at) for quicker hash lookup.
-  /// None is returned only if capacity was reached.
+  /// None is returned only if capacity was reached, in which case the
+  /// generator is not called.
     pub fn and_generate<F>(&mut self, key:KT, generator:F) -> Option<usize>
   where F: FnOnce(Option<&VT>) -> VT
   {  self.find_and(key,generator).1
@@ -294,6 +373,70 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
     self.getopt(Some(index), key)
   }
   
+  /// Version of [get_at](Self::get_at) without any checks: the key at
+  /// the hash index is not compared, and no bounds are checked.  O(1).
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut chh = ConstHashHeap::<&str,u32,16>::new(false);
+  ///   let hi = chh.set_at("a", 3).unwrap();
+  ///   chh.insert("b", 1);
+  ///   assert_eq!(unsafe { *chh.get_at_unchecked(hi) }, 3);
+  ///   unsafe { chh.modify_at_unchecked(hi, |v| *v = 0); }
+  ///   assert_eq!(chh.peek(), Some((&"a",&0)));
+  /// ```
+  ///
+  /// # Safety
+  /// The index must have been returned for a key that is still in the
+  /// structure, by a function such as [set_at](Self::set_at), and the
+  /// structure must not have been changed since then except by
+  /// [get_at](Self::get_at), [modify_at](Self::modify_at) and these
+  /// unchecked functions on that index.  Any other change may move keys
+  /// to different hash indices and invalidates the index, including
+  /// * removals: [remove](Self::remove), [remove_at](Self::remove_at),
+  ///   [pop](Self::pop), [pop_if](Self::pop_if), [pop_while](Self::pop_while),
+  ///   [drain](Self::drain), [drain_sorted](Self::drain_sorted) and
+  ///   [priority_stream](Self::priority_stream);
+  /// * insertions: [insert](Self::insert), [push](Self::push),
+  ///   [set_at](Self::set_at), [and_generate](Self::and_generate) and
+  ///   [entry](Self::entry), which may refresh the table when automatic
+  ///   refreshing is enabled by [set_refresh_threshold](Self::set_refresh_threshold);
+  /// * [refresh_in_place](Self::refresh_in_place) and [append](Self::append).
+  ///
+  /// Otherwise the behavior is undefined.  In debug builds an index that
+  /// is out of bounds or not occupied causes a panic.
+  pub unsafe fn get_at_unchecked(&self, index:usize) -> &VT {
+    debug_assert!(self.occupied_at(index), "stale hash index {}", index);
+    unsafe {
+      let vi = self.keys.get_unchecked(index).as_ref().unwrap_unchecked().1;
+      &self.vals.get_unchecked(vi).as_ref().unwrap_unchecked().0
+    }
+  }
+
+  /// Version of [modify_at](Self::modify_at) without any checks on the
+  /// index, which must satisfy the same conditions as for
+  /// [get_at_unchecked](Self::get_at_unchecked).  The entry is still
+  /// repositioned in the heap.  O(log n) plus the cost of the closure.
+  ///
+  /// # Safety
+  /// See [get_at_unchecked](Self::get_at_unchecked).
+  pub unsafe fn modify_at_unchecked<F:FnOnce(&mut VT)>(&mut self, index:usize, f:F) {
+    debug_assert!(self.occupied_at(index), "stale hash index {}", index);
+    let vi = unsafe {
+      let vi = self.keys.get_unchecked(index).as_ref().unwrap_unchecked().1;
+      f(&mut self.vals.get_unchecked_mut(vi).as_mut().unwrap_unchecked().0);
+      vi
+    };
+    self.adjust(vi, vi+1<self.size);
+  }
+
+  // true if the hash index holds a key whose value exists
+  fn occupied_at(&self, index:usize) -> bool {
+    match self.keys.get(index) {
+      Some(Some((_,vi))) => matches!(self.vals.get(*vi), Some(Some(_))),
+      _ => false,
+    }
+  }
+
   fn getopt(&self, iopt:Option<usize>, key:&KT) -> Option<&VT> {  
     let mut answer = None;
     match iopt {
@@ -308,16 +451,17 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
        _ => {},
     }//match
     // if did not return
-    let h0 = self.hash(&key);
+    let hv = self.fullhash(key);
+    let h0 = Self::reduce(hv);
     let mut h = h0;
     let mut hashes = 1;
     loop {
       match &self.keys[h] {
-        Some((k,vi)) if k==key => {
+        Some((k,vi)) if self.fullhashes[h]==hv && k==key => {
           answer = self.vals[*vi].as_ref().map(|p|&p.0);
           break;
         },
-        _ if hashes < self.maxhashes[h0] => {
+        Some(_) if hashes < self.maxhashes[h0] => {
           h=Self::rehash(h);
           hashes += 1;        
         }
@@ -346,6 +490,7 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
     self.modify_opt(Some(index),key,f)
   }
   
+  #[allow(clippy::option_map_unit_fn)]
   fn modify_opt<F>(&mut self, iopt:Option<usize>, key:&KT, f:F) -> Option<usize>
   where F:FnOnce(&mut VT)
   {
@@ -363,17 +508,18 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
       _ => {},
     }//match
     // if did not return  
-    let h0 = self.hash(&key);
+    let hv = self.fullhash(key);
+    let h0 = Self::reduce(hv);
     let mut h = h0;
     let mut hashes = 1;
     let mut valpos = None;
     loop {
       match &self.keys[h] {
-        Some((k,vi)) if k==key => {
+        Some((k,vi)) if self.fullhashes[h]==hv && k==key => {
           valpos = Some(*vi);
           break;
         },
-        _ if hashes < self.maxhashes[h0] => {
+        Some(_) if hashes < self.maxhashes[h0] => {
           h=Self::rehash(h);
           hashes += 1;        
         }
@@ -391,6 +537,7 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
 
 
   /// remove and return the key-value pair associated with the key.
+  /// Other keys that collided with it may move to different hash indices.
   /// O(log n)
   pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
     self.remove_opt(None,key)
@@ -420,16 +567,17 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
       _ => {},
     }//match
     if valpos.is_none() {
-      let h0 = self.hash(&key);
+      let hv = self.fullhash(key);
+      let h0 = Self::reduce(hv);
       h = h0;
       let mut hashes = 1;
       loop {
         match &self.keys[h] {
-          Some((k,vi)) if k==key => {
+          Some((k,vi)) if self.fullhashes[h]==hv && k==key => {
             valpos = Some(*vi);
             break;
           },
-          _ if hashes < self.maxhashes[h0] => {
+          Some(_) if hashes < self.maxhashes[h0] => {
             h=Self::rehash(h);
             hashes += 1;        
           }
@@ -438,41 +586,245 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
       }//loop
     } // quick lookup failed.
     
-    if let Some(vi) = valpos {
-       let mut ak = None;
-       let mut av = None;
-       core::mem::swap(&mut ak, &mut self.keys[h]);
-       core::mem::swap(&mut av, &mut self.vals[vi]);
-       answer = ak.zip(av).map(|(a,b)|(a.0,b.0));
-       // adjust heap;
-       if (vi+1 != self.size) {
-          self.swap(vi,self.size-1);
-          self.adjust(vi,true);
-       }
-       self.size -= 1; 
+    if valpos.is_some() {
+       answer = self.remove_index(h);
     }
     answer
   }//remove
 
+  // removes entry at hash index h, if any
+  fn remove_index(&mut self, h:usize) -> Option<(KT,VT)> {
+    let vi = self.keys[h].as_ref()?.1;
+    let mut ak = None;
+    let mut av = None;
+    core::mem::swap(&mut ak, &mut self.keys[h]);
+    core::mem::swap(&mut av, &mut self.vals[vi]);
+    // adjust heap;
+    if (vi+1 != self.size) {
+       self.swap(vi,self.size-1);
+       self.adjust(vi,true);
+    }
+    self.size -= 1;
+    self.vacated += 1;
+    self.backshift(h);
+    ak.zip(av).map(|(a,b)|(a.0,b.0))
+  }//remove_index
+
   /// remove and return the highest-priority key-value pair. O(log n).
   pub fn pop(&mut self) -> Option<(KT,VT)> {
     let mut answer = None;
     if self.size < 1 { return answer; }
-    if let Some((_,ki)) = &self.vals[0] {
+    if let Some(&(_,ki)) = self.vals[0].as_ref() {
        let mut ak = None;
        let mut av = None;
-       core::mem::swap(&mut ak, &mut self.keys[*ki]);
+       core::mem::swap(&mut ak, &mut self.keys[ki]);
        core::mem::swap(&mut av, &mut self.vals[0]);
        answer = ak.zip(av).map(|(a,b)|(a.0,b.0));
        self.size -= 1;
+       self.vacated += 1;
        if (self.size>0) {
             self.swap(0,self.size);
             self.swapdown(0);
        }
+       self.backshift(ki);
     }
     answer  
   }//pop
 
+  /// removes and returns the highest-priority key-value pair only if it
+  /// satisfies the given predicate.  None is returned if the structure is
+  /// empty or if the predicate is false on the top entry.  O(log n).
+  pub fn pop_if<F>(&mut self, pred:F) -> Option<(KT,VT)>
+  where F: FnOnce(&KT,&VT) -> bool
+  {
+    match self.peek() {
+      Some((k,v)) if pred(k,v) => self.pop(),
+      _ => None,
+    }
+  }//pop_if
+
+  /// returns an iterator that repeatedly pops the highest-priority
+  /// key-value pair while it satisfies the given predicate.  The iterator
+  /// stops at the first entry that fails the predicate, which remains in
+  /// the structure.  For example, to pop all events due by time 10:
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut events = ConstHashHeap::<&str,u32,16>::new(false);
+  ///   events.insert("c",12);
+  ///   events.insert("a",3);
+  ///   events.insert("b",10);
+  ///   let due:Vec<_> = events.pop_while(|_,t|*t<=10).collect();
+  ///   assert_eq!(due, vec![("a",3),("b",10)]);
+  ///   assert_eq!(events.size(), 1);
+  /// ```
+  pub fn pop_while<'a,F>(&'a mut self, pred:F) -> CHHPopWhile<'a,KT,VT,CAP,F>
+  where F: FnMut(&KT,&VT) -> bool
+  {
+    CHHPopWhile { chh:self, pred }
+  }//pop_while
+
+  /// returns an iterator that removes all entries in no particular order.
+  /// The structure keeps its arrays and hash state and can be reused
+  /// afterwards.  If the iterator is dropped before it's exhausted, the
+  /// remaining entries are still removed.  Draining the structure takes
+  /// O(n) time.
+  pub fn drain<'a>(&'a mut self) -> CHHDrain<'a,KT,VT,CAP> {
+    CHHDrain(self)
+  }
+
+  /// returns an iterator that removes all entries in order of priority,
+  /// as in [priority_stream](Self::priority_stream), except that any
+  /// entries remaining when the iterator is dropped are also removed.
+  /// Draining the structure takes O(n log n) time.
+  pub fn drain_sorted<'a>(&'a mut self) -> CHHDrainSorted<'a,KT,VT,CAP> {
+    CHHDrainSorted(self)
+  }
+
+  // removes the last entry of the heap array, which keeps the heap valid
+  fn pop_last(&mut self) -> Option<(KT,VT)> {
+    if self.size < 1 { return None; }
+    self.size -= 1;
+    self.vacated += 1;
+    let mut av = None;
+    core::mem::swap(&mut av, &mut self.vals[self.size]);
+    av.and_then(|(v,ki)| {
+      let mut ak = None;
+      core::mem::swap(&mut ak, &mut self.keys[ki]);
+      ak.map(|(k,_)|(k,v))
+    })
+  }//pop_last
+
+  // called once all entries are gone: no probe sequences need be kept
+  fn reset_probes(&mut self) {
+    if self.size == 0 {
+      self.maxhashes = [0;CAP];
+      self.probesum = 0;
+      self.probed = 0;
+      self.vacated = 0;
+    }
+  }
+
+  // raises the maximum probe length from hash index h0, if necessary,
+  // keeping the running totals for average_hashes
+  fn set_maxhashes(&mut self, h0:usize, hashes:usize) {
+    let old = self.maxhashes[h0];
+    if hashes > old {
+      if old == 0 { self.probed += 1; }
+      self.probesum += hashes - old;
+      self.maxhashes[h0] = hashes;
+    }
+  }
+
+  // Backward-shift deletion, called after the key at hash index hole is
+  // removed.  Each following key of the cluster (run of occupied slots)
+  // is moved back into the hole unless that would place it before its
+  // original hash index, so that no empty slot is ever left inside a
+  // probe sequence: lookups can stop at the first empty slot, and no
+  // tombstones accumulate.  The maxhashes of the cluster are then
+  // recomputed.  O(length of cluster).
+  fn backshift(&mut self, hole:usize) {
+    if self.size == 0 { self.reset_probes(); return; }
+    let mut start = hole;
+    loop {
+      let prev = if start>0 {start-1} else {CAP-1};
+      if prev == hole || self.keys[prev].is_none() { break; }
+      start = prev;
+    }
+    let mut hole = hole;
+    let mut j = Self::rehash(hole);
+    while let Some((k,vi)) = &self.keys[j] {
+      let home = Self::reduce(self.fullhashes[j]);
+      if Self::distance(home,hole) < Self::distance(home,j) {
+        let vi = *vi;
+        self.keys.swap(hole,j);
+        self.fullhashes[hole] = self.fullhashes[j];
+        if let Some(p) = self.vals[vi].as_mut() { p.1 = hole; }
+        hole = j;
+      }
+      j = Self::rehash(j);
+    }//while
+    self.recount(start, j);
+  }//backshift
+
+  // number of rehashes needed to get from hash index a to b
+  fn distance(a:usize, b:usize) -> usize { if b >= a {b-a} else {b+CAP-a} }
+
+  // recomputes maxhashes for the slots from start up to (excluding) end,
+  // which must span whole clusters, keeping the running totals
+  fn recount(&mut self, start:usize, end:usize) {
+    let mut h = start;
+    loop {
+      let old = self.maxhashes[h];
+      if old > 0 {
+        self.probed -= 1;
+        self.probesum -= old;
+        self.maxhashes[h] = 0;
+      }
+      h = Self::rehash(h);
+      if h == end { break; }
+    }
+    h = start;
+    loop {
+      if self.keys[h].is_some() {
+        let h0 = Self::reduce(self.fullhashes[h]);
+        self.set_maxhashes(h0, Self::distance(h0,h)+1);
+      }
+      h = Self::rehash(h);
+      if h == end { break; }
+    }
+  }//recount
+
+  /// Returns the average number of hashes (probes) needed to look up a
+  /// key, as also computed by [diagnostics](Self::diagnostics) in
+  /// O(capacity) time.  Here the number is maintained incrementally and
+  /// returned in O(1) time.  Because removals shift the remaining keys
+  /// of a cluster back towards their original hash indices, the number
+  /// does not grow with repeated insertions and removals.
+  pub fn average_hashes(&self) -> f32 {
+    if self.probed == 0 { 0.0 } else { self.probesum as f32 / self.probed as f32 }
+  }
+
+  /// Sets a threshold on the [average number of hashes](Self::average_hashes)
+  /// above which the structure is considered to need a refresh, as
+  /// reported by [needs_refresh](Self::needs_refresh).  If `auto` is true,
+  /// the structure is refreshed in place at the start of the next
+  /// [insert](Self::insert) that finds the threshold exceeded, but no more
+  /// than once per CAP/8 removals, so that the O(CAP) cost of refreshing is
+  /// amortized over the removals that made it necessary.  The default
+  /// threshold is infinite.
+  /// Since removals don't leave tombstones, a refresh is only useful in
+  /// unusual cases, such as after a change in the distribution of keys.
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut chh = ConstHashHeap::<u32,u32,64>::new(false);
+  ///   for i in 0..48 { chh.insert(i, i); }
+  ///   chh.set_refresh_threshold(0.0, true);
+  ///   assert!(!chh.needs_refresh());  // nothing removed yet
+  ///   for i in 0..8 { chh.remove(&i); }  // CAP/8 removals
+  ///   assert!(chh.needs_refresh());
+  ///   chh.insert(1,1);  // refreshes first
+  ///   assert!(!chh.needs_refresh());
+  ///   assert_eq!(chh.peek(), Some((&1,&1)));
+  /// ```
+  pub fn set_refresh_threshold(&mut self, average_hashes:f32, auto:bool) {
+    self.refresh_threshold = average_hashes;
+    self.auto_refresh = auto;
+  }
+
+  /// determines if the average number of hashes has exceeded the threshold
+  /// set by [set_refresh_threshold](Self::set_refresh_threshold) since keys
+  /// were last removed, so that [refresh](Self::refresh) would help.  O(1).
+  pub fn needs_refresh(&self) -> bool {
+    self.vacated > 0 && self.average_hashes() > self.refresh_threshold
+  }
+
+  /// Performs a [refresh](Self::refresh) without taking ownership of the
+  /// structure.  O(CAP).
+  pub fn refresh_in_place(&mut self) {
+    let old = core::mem::replace(self, ConstHashHeap::new(self.maxheap));
+    *self = old.refresh();
+  }
+
   /// returns reference to highest-priority key-value pair without
   /// removal.  This operation is O(1).
   pub fn peek(&self) -> Option<(&KT,&VT)> {
@@ -483,28 +835,224 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
     }
   }//peek
 
+  /// returns a guard giving mutable access to the value of the
+  /// highest-priority entry, or None if the structure is empty.  The
+  /// key cannot be changed.  When the guard is dropped, the modified
+  /// entry is moved down the heap to its proper position, which avoids
+  /// the cost of a remove followed by an insert.  This operation is O(1),
+  /// with an additional O(log n) when the guard is dropped.
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut hp = ConstHashHeap::<&str,i32,16>::new(true);
+  ///   hp.insert("a",5);
+  ///   hp.insert("b",3);
+  ///   if let Some(mut top) = hp.peek_mut() {
+  ///     assert_eq!(top.key(), &"a");
+  ///     *top = 1;
+  ///   }
+  ///   assert_eq!(hp.peek(), Some((&"b",&3)));
+  /// ```
+  pub fn peek_mut<'a>(&'a mut self) -> Option<CHHPeekMut<'a,KT,VT,CAP>> {
+    if self.size < 1 { None }
+    else { Some(CHHPeekMut{chh:self}) }
+  }//peek_mut
+
+  /// returns the key-value pair stored at the given position of the
+  /// internal heap (values) array, or None if the position is not less
+  /// than [size](Self::size).  Note that this index is not the *hash index*
+  /// taken by functions such as [get_at](Self::get_at).  Index 0 holds the
+  /// highest-priority entry, and the children of the entry at index i are
+  /// at 2i+1 and 2i+2.  Positions change when the structure is mutated.
+  /// O(1).
+  pub fn get_entry_at(&self, heap_index:usize) -> Option<(&KT,&VT)> {
+    if heap_index >= self.size { return None; }
+    self.vals[heap_index].as_ref().and_then(|(v,ki)|
+      self.keys[*ki].as_ref().map(|kp|(&kp.0,v)))
+  }
+
+  /// Fills the given buffer with the highest-priority entries, in order of
+  /// priority, without allocating memory.  If the buffer is longer than
+  /// [size](Self::size), the remaining slots are set to None.  Returns the
+  /// number of entries written.  Entries of equal priority may appear in
+  /// any order.  Since entries below the k-th best are never visited, this
+  /// is efficient for small k, requiring O(k^2) time in typical cases.
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut chh = ConstHashHeap::<u32,u32,64>::new(true);
+  ///   for i in 0..40 { chh.insert(i, (i*7)%40); }
+  ///   let mut top = [None; 3];
+  ///   assert_eq!(chh.peek_top_k_into(&mut top), 3);
+  ///   assert_eq!(top.map(|p|*p.unwrap().1), [39,38,37]);
+  /// ```
+  pub fn peek_top_k_into<'a>(&'a self, out:&mut [Option<(&'a KT,&'a VT)>]) -> usize {
+    for slot in out.iter_mut() { *slot = None; }
+    let mut filled = 0;
+    self.topk_visit(0, out, &mut filled);
+    filled
+  }
+
+  fn lower(&self, a:&VT, b:&VT) -> bool {
+    if self.maxheap { a < b } else { b < a }
+  }
+
+  // depth-first search that prunes subtrees not better than the worst entry
+  // kept, inserting each entry visited into the sorted buffer
+  fn topk_visit<'a>(&'a self, i:usize, out:&mut [Option<(&'a KT,&'a VT)>], filled:&mut usize) {
+    let (k,v) = match self.get_entry_at(i) {
+      Some(p) if !out.is_empty() => p,
+      _ => { return; },
+    };
+    if *filled == out.len() {
+      if out[*filled-1].is_some_and(|(_,w)|!self.lower(w,v)) { return; }
+    }
+    else { *filled += 1; }
+    let mut j = *filled - 1;
+    while j > 0 && out[j-1].is_some_and(|(_,w)|self.lower(w,v)) {
+      out[j] = out[j-1];
+      j -= 1;
+    }
+    out[j] = Some((k,v));
+    self.topk_visit(2*i+1, out, filled);
+    self.topk_visit(2*i+2, out, filled);
+  }//topk_visit
+
+  /// returns statistics about the shape of the heap in O(log n) time
+  pub fn shape_stats(&self) -> crate::ShapeStats {
+    crate::ShapeStats::for_size(self.size)
+  }
+
+  /// returns the level of the heap at which the entry with the given key
+  /// is currently found, with the top entry at level 0.
+  pub fn key_depth(&self, key:&KT) -> Option<usize> {
+    self.findkey(key).and_then(|h|self.keys[h].as_ref())
+      .map(|(_,vi)|crate::heap_depth(*vi))
+  }
+
+  // returns hash index of key, if found
+  fn findkey(&self, key:&KT) -> Option<usize> {
+    let hv = self.fullhash(key);
+    let h0 = Self::reduce(hv);
+    let mut h = h0;
+    let mut hashes = 1;
+    loop {
+      match &self.keys[h] {
+        Some((k,_)) if self.fullhashes[h]==hv && k==key => { return Some(h); },
+        Some(_) if hashes < self.maxhashes[h0] => {
+          h=Self::rehash(h);
+          hashes += 1;
+        }
+        _ => { return None; }
+      }//match
+    }//loop
+  }//findkey
+
   /// The load factor is the size divided by the capacity.  Resizing is
   /// recommended when this factor is greater than 0.75.
   pub fn load_factor(&self) -> f32 {
     (self.size as f32) / (CAP as f32)
   }
 
-  /// moves all entries to a ConstHashHeap of a new capacity.
+  /// Sets the maximum load factor, which must be greater than zero and at
+  /// most one.  The default is 1.0, which allows the table to fill up
+  /// completely.  Once the size reaches the maximum load factor times the
+  /// capacity (but at least one), insertions of new keys are rejected:
+  /// [insert](Self::insert) returns false, [set_at](Self::set_at) and
+  /// [and_generate](Self::and_generate) return None and
+  /// [append](Self::append) returns a [CapacityError].  Existing keys may
+  /// still be changed.  Returns false if the argument is invalid.  The
+  /// setting is kept by [resize](Self::resize).
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut chh = ConstHashHeap::<u32,u32,16>::new(false);
+  ///   assert!(chh.set_max_load(0.75));
+  ///   for i in 0..12 { assert!(chh.insert(i,i)); }
+  ///   assert!(chh.needs_resize());
+  ///   assert!(!chh.insert(12,12));      // rejected
+  ///   assert!(chh.insert(11,0));        // replacement is allowed
+  ///   let chh = chh.resize::<32>();
+  ///   assert!(!chh.needs_resize());
+  /// ```
+  pub fn set_max_load(&mut self, load:f32) -> bool {
+    if load.is_nan() || load <= 0.0 || load > 1.0 { return false; }
+    self.maxload = load;
+    self.loadlimit = Self::limit_for(load);
+    true
+  }
+
+  // max number of entries allowed at the given maximum load factor
+  fn limit_for(load:f32) -> usize {
+    if load >= 1.0 { CAP }
+    else { (((CAP as f64) * (load as f64)) as usize).clamp(1,CAP) }
+  }
+
+  /// returns the maximum load factor: see [set_max_load](Self::set_max_load)
+  pub fn max_load(&self) -> f32 { self.maxload }
+
+  /// returns true if the size has reached the limit set by the maximum
+  /// load factor, so that insertions of new keys will be rejected until
+  /// the structure is [resized](Self::resize) or entries are removed.
+  pub fn needs_resize(&self) -> bool { self.size >= self.loadlimit }
+
+  /// moves all entries from another ConstHashHeap, possibly of a different
+  /// capacity, into this structure.  Keys are rehashed using the hash
+  /// state of `self`.  If a key exists in both structures, the value from
+  /// `other` replaces the existing one.  If there is not enough capacity
+  /// for all the new keys, a [CapacityError] is returned and neither
+  /// structure is changed.  Otherwise `other` is left empty.  This
+  /// operation takes O(m log(n+m)) time where m is the size of `other`.
+  /// As with [Vec::append], `other` is borrowed rather than taken by value,
+  /// so that its entries are not lost when the error is returned.
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut hp1 = ConstHashHeap::<&str,u32,8>::new(false);
+  ///   let mut hp2 = ConstHashHeap::<&str,u32,4>::new(false);
+  ///   hp1.insert("a",4);
+  ///   hp2.insert("b",2);
+  ///   hp2.insert("a",1);
+  ///   assert!(hp1.append(&mut hp2).is_ok());
+  ///   assert_eq!(hp1.size(), 2);
+  ///   assert_eq!(hp1.peek(), Some((&"a",&1)));
+  ///   assert_eq!(hp2.size(), 0);
+  /// ```
+  pub fn append<const OTHER:usize>(&mut self, other:&mut ConstHashHeap<KT,VT,OTHER>)
+    -> Result<(),CapacityError>
+  {
+    let newkeys = other.iter().filter(|(k,_)|self.get(k).is_none()).count();
+    if self.size + newkeys > self.loadlimit {
+      return Err(CapacityError { capacity:self.loadlimit, required:self.size+newkeys });
+    }
+    for (k,v) in other.drain() {
+      self.insert(k,v);
+    }
+    Ok(())
+  }//append
+
+  /// moves all entries to a ConstHashHeap of a new capacity.  The
+  /// cached hash of each key is reused, so no key is hashed again.
+  /// Panics if the new capacity is smaller than the size.  The new
+  /// structure may exceed its [maximum load factor](Self::set_max_load),
+  /// in which case it rejects new keys.
+  #[allow(clippy::option_map_unit_fn, clippy::while_let_loop)]
   pub fn resize<const NEWCAP:usize>(mut self) -> ConstHashHeap<KT,VT,NEWCAP> {
-    let mut hp2 = ConstHashHeap::new(true);
-    hp2.lessthan = self.lessthan;
+    assert!(self.size <= NEWCAP,
+            "cannot resize ConstHashHeap of size {} to capacity {}", self.size, NEWCAP);
+    let mut hp2 = ConstHashHeap::new(self.maxheap);
     hp2.size = self.size;
+    hp2.refresh_threshold = self.refresh_threshold;
+    hp2.auto_refresh = self.auto_refresh;
+    hp2.set_max_load(self.maxload);
     for i in 0..self.size {
       let mut h = 0;
       if let Some((_,ki)) = &self.vals[i] {
+         let hv = self.fullhashes[*ki];
          self.keys[*ki].as_ref().map(|(key,vi)|{
-           let h0 = hp2.borrow_hash(key,&self.autostate);
+           let h0 = ConstHashHeap::<KT,VT,NEWCAP>::reduce(hv);
            h = h0;
            let mut hashes = 1;
            loop {
              match hp2.keys[h] {
                Some(_) => {
-                 h = (h+1) % NEWCAP;
+                 h = ConstHashHeap::<KT,VT,NEWCAP>::rehash(h);
                  hashes += 1;
                },
                None => {
@@ -512,9 +1060,10 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
                },
              }//match
            }//loop
-           hp2.maxhashes[h0] = hashes;
+           hp2.set_maxhashes(h0, hashes);
          });
          core::mem::swap(&mut hp2.keys[h],&mut self.keys[*ki]);
+         hp2.fullhashes[h] = hv;
          self.vals[i].as_mut().map(|p|{p.1 = h;});
       } // if-let
       core::mem::swap(&mut hp2.vals[i], &mut self.vals[i]);      
@@ -526,6 +1075,8 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
   /// moves all entries to a new ConstHashHeap of the same capacity. This
   /// operation may be called after a large number of key-value pairs
   /// had been removed, which should improve hash lookup performance.
+  /// See also [refresh_in_place](Self::refresh_in_place) and
+  /// [set_refresh_threshold](Self::set_refresh_threshold).
   pub fn refresh(mut self) -> Self {
     self.resize()
   }
@@ -539,6 +1090,27 @@ impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP> {
     }
   }//iter
 
+  /// returns an iterator over all entries in no particular order, each
+  /// with its *hash index*, so that the indices of many keys can be
+  /// collected in one pass for later calls to functions such as
+  /// [get_at](Self::get_at) and [modify_at](Self::modify_at).
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut chh = ConstHashHeap::<u32,u32,64>::new(true);
+  ///   for i in 0..20 { chh.insert(i, i*i); }
+  ///   let hints:Vec<(usize,u32)> = chh.iter_with_index().map(|(h,k,_)|(h,*k)).collect();
+  ///   for (h,k) in hints {
+  ///     assert_eq!(chh.modify_at(h, &k, |v| *v += 1), Some(h));
+  ///   }
+  ///   assert_eq!(chh.peek(), Some((&19,&362)));
+  /// ```
+  pub fn iter_with_index<'a>(&'a self) -> CHHIndexIter<'a,KT,VT,CAP> {
+    CHHIndexIter {
+      chh : self,
+      index : 0,
+    }
+  }//iter_with_index
+
   /// returns a consuming iterator over all entries in order of priority.
   /// This iterator is equivalent to repeatedly calling [pop](Self::pop), and
   /// will empty the structure of all entries.
@@ -558,6 +1130,79 @@ for ConstHashHeap<KT,VT,CAP>
     }
 } //impl Index
 
+/// Two ConstHashHeaps are equal if they contain the same key-value pairs,
+/// regardless of their capacities, of the order of the entries in the heap
+/// and of whether they are min or max heaps.  O(n)
+/// ```
+/// # use hashheap::*;
+///   let mut a = ConstHashHeap::<&str,u32,8>::new(true);
+///   let mut b = ConstHashHeap::<&str,u32,16>::new(true);
+///   a.insert("x",1); a.insert("y",2);
+///   b.insert("y",2); b.insert("x",1);
+///   assert!(a == b);
+///   b.modify(&"x", |v|*v=3);
+///   assert!(a != b);
+/// ```
+impl<KT: Hash + Eq, VT: PartialOrd, const CAP:usize, const CAP2:usize>
+PartialEq<ConstHashHeap<KT,VT,CAP2>> for ConstHashHeap<KT,VT,CAP>
+{
+  fn eq(&self, other:&ConstHashHeap<KT,VT,CAP2>) -> bool {
+    self.size == other.size()
+      && self.iter().all(|(k,v)| other.get(k) == Some(v))
+  }
+}
+impl<KT: Hash + Eq, VT: PartialOrd + Eq, const CAP:usize> Eq
+for ConstHashHeap<KT,VT,CAP> {}
+
+/// Shows the size, capacity and load factor of the structure, followed by
+/// its entries only, in heap order, so that the top entry comes first.
+/// ```
+/// # use hashheap::*;
+///   let mut chh = ConstHashHeap::<&str,u32,4>::new(true);
+///   chh.insert("a",1); chh.insert("b",2);
+///   assert_eq!(format!("{:?}",chh),
+///     r#"ConstHashHeap { maxheap: true, size: 2, capacity: 4, load_factor: 0.5, entries: {"b": 2, "a": 1} }"#);
+/// ```
+impl<KT:Debug, VT:Debug, const CAP:usize> Debug for ConstHashHeap<KT,VT,CAP>
+{
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_struct("ConstHashHeap")
+      .field("maxheap", &self.maxheap)
+      .field("size", &self.size)
+      .field("capacity", &CAP)
+      .field("load_factor", &((self.size as f32) / (CAP as f32)))
+      .field("entries", &CHHDebugEntries(self))
+      .finish()
+  }
+}
+
+// the live entries of a ConstHashHeap in heap order, for Debug
+struct CHHDebugEntries<'a,KT,VT,const CAP:usize>(&'a ConstHashHeap<KT,VT,CAP>);
+impl<'a,KT:Debug, VT:Debug, const CAP:usize> Debug for CHHDebugEntries<'a,KT,VT,CAP>
+{
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let chh = self.0;
+    f.debug_map().entries(chh.vals[..chh.size].iter().flatten().filter_map(|(v,ki)|
+        chh.keys[*ki].as_ref().map(|(k,_)|(k,v)))).finish()
+  }
+}
+
+/// A one-line summary of the structure, without its entries.
+/// ```
+/// # use hashheap::*;
+///   let mut chh = ConstHashHeap::<&str,u32,4>::new(false);
+///   chh.insert("a",1);
+///   assert_eq!(chh.to_string(), "ConstHashHeap (minheap): size 1, capacity 4, load factor 0.25");
+/// ```
+impl<KT, VT, const CAP:usize> Display for ConstHashHeap<KT,VT,CAP>
+{
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    write!(f, "ConstHashHeap ({}): size {}, capacity {}, load factor {}",
+      if self.maxheap {"maxheap"} else {"minheap"}, self.size, CAP,
+      (self.size as f32) / (CAP as f32))
+  }
+}
+
 impl<KT:Display+Debug+Hash+Eq, VT:Display+Debug+PartialOrd, const CAP:usize> ConstHashHeap<KT,VT,CAP>
 {
   /// For debugging and performance statistics.  The implementation uses a
@@ -571,6 +1216,7 @@ impl<KT:Display+Debug+Hash+Eq, VT:Display+Debug+PartialOrd, const CAP:usize> Con
   /// is not a constant-time operation and is in fact O(capacity).
   /// The boolean argument gives the
   /// option of printing the arrays underneath (not recommended).
+ #[allow(clippy::unwrap_or_default)]
  pub fn diagnostics(&self, print:bool) -> f32 {
 
    // compute average number of hashes from maxhashes
@@ -596,6 +1242,167 @@ impl<KT:Display+Debug+Hash+Eq, VT:Display+Debug+PartialOrd, const CAP:usize> Con
 }//diagnostics
 
 
+/////////////////// entry api
+
+/// A view into a single entry of a [ConstHashHeap], returned by
+/// [ConstHashHeap::entry].  Because values must be repositioned in the
+/// heap after they're changed, the operations that would give mutable
+/// references in a HashMap instead return the *hash index* of the entry.
+pub enum CHHEntry<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize>
+{
+  Occupied(CHHOccupiedEntry<'a,KT,VT,CAP>),
+  Vacant(CHHVacantEntry<'a,KT,VT,CAP>),
+}
+impl<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize> CHHEntry<'a,KT,VT,CAP>
+{
+  /// inserts the value if the entry is vacant.  Returns the hash index
+  /// of the entry, or None if the capacity has been reached.
+  pub fn or_insert(self, val:VT) -> Option<usize> {
+    self.or_insert_with(|| val)
+  }
+
+  /// inserts the value computed by the closure if the entry is vacant.
+  /// Returns the hash index of the entry, or None if the capacity has been
+  /// reached, in which case the closure is not called.
+  pub fn or_insert_with<F:FnOnce() -> VT>(self, f:F) -> Option<usize> {
+    match self {
+      CHHEntry::Occupied(e) => Some(e.index),
+      CHHEntry::Vacant(e) if e.chh.size >= e.chh.loadlimit => None,
+      CHHEntry::Vacant(e) => e.insert(f()),
+    }
+  }
+
+  /// modifies the value of an occupied entry, then adjusts its position
+  /// in the heap.  Vacant entries are unaffected.
+  pub fn and_modify<F:FnOnce(&mut VT)>(self, f:F) -> Self {
+    match self {
+      CHHEntry::Occupied(mut e) => { e.modify(f); CHHEntry::Occupied(e) },
+      e => e,
+    }
+  }
+
+  /// the key of this entry
+  pub fn key(&self) -> &KT {
+    match self {
+      CHHEntry::Occupied(e) => e.key(),
+      CHHEntry::Vacant(e) => e.key(),
+    }
+  }
+
+  /// the hash index of an occupied entry, None if vacant
+  pub fn hash_index(&self) -> Option<usize> {
+    match self {
+      CHHEntry::Occupied(e) => Some(e.index),
+      CHHEntry::Vacant(_) => None,
+    }
+  }
+}//impl CHHEntry
+
+/// An occupied entry of a [ConstHashHeap]
+pub struct CHHOccupiedEntry<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize>
+{
+  chh : &'a mut ConstHashHeap<KT,VT,CAP>,
+  index : usize,
+}
+impl<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize> CHHOccupiedEntry<'a,KT,VT,CAP>
+{
+  /// the key of this entry
+  pub fn key(&self) -> &KT {
+    &self.chh.keys[self.index].as_ref().unwrap().0
+  }
+  /// the value of this entry
+  pub fn get(&self) -> &VT {
+    let vi = self.chh.keys[self.index].as_ref().unwrap().1;
+    &self.chh.vals[vi].as_ref().unwrap().0
+  }
+  /// the hash index of this entry
+  pub fn hash_index(&self) -> usize { self.index }
+  /// modifies the value with the closure and adjusts its position in the
+  /// heap.  O(log n)
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, f:F) {
+    let vi = self.chh.keys[self.index].as_ref().unwrap().1;
+    if let Some(p) = self.chh.vals[vi].as_mut() { f(&mut p.0); }
+    self.chh.adjust(vi, vi+1<self.chh.size);
+  }
+  /// replaces the value, returning the old one.  O(log n)
+  pub fn insert(&mut self, val:VT) -> VT {
+    let mut val = val;
+    self.modify(|v|core::mem::swap(v,&mut val));
+    val
+  }
+  /// removes the entry from the structure.  O(log n)
+  pub fn remove(self) -> (KT,VT) {
+    self.chh.remove_index(self.index).unwrap()
+  }
+}//impl CHHOccupiedEntry
+
+/// A vacant entry of a [ConstHashHeap]
+pub struct CHHVacantEntry<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize>
+{
+  chh : &'a mut ConstHashHeap<KT,VT,CAP>,
+  key : KT,
+  probed : (usize,u64,usize),
+}
+impl<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize> CHHVacantEntry<'a,KT,VT,CAP>
+{
+  /// the key that would be inserted
+  pub fn key(&self) -> &KT { &self.key }
+  /// takes ownership of the key
+  pub fn into_key(self) -> KT { self.key }
+  /// inserts the value, returning the hash index of the new entry, or
+  /// None if capacity has been reached.  O(log n)
+  pub fn insert(self, val:VT) -> Option<usize> {
+    self.chh.insert_probed(self.probed, self.key, val)
+  }
+}//impl CHHVacantEntry
+
+/////////////////// peek_mut guard
+
+/// Guard returned by [ConstHashHeap::peek_mut].  Dereferences to the
+/// value of the highest-priority entry; the heap is adjusted when the
+/// guard is dropped.
+pub struct CHHPeekMut<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize>
+{
+  chh : &'a mut ConstHashHeap<KT,VT,CAP>,
+}
+impl<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize> CHHPeekMut<'a,KT,VT,CAP>
+{
+  /// the key of the top entry, which cannot be mutated
+  pub fn key(&self) -> &KT {
+    self.chh.vals[0].as_ref()
+      .and_then(|vp|self.chh.keys[vp.1].as_ref())
+      .map(|kp|&kp.0).unwrap()
+  }
+
+  /// removes and returns the top entry, consuming the guard
+  pub fn pop(mut self) -> (KT,VT) {
+    self.chh.pop().unwrap()
+  }
+}//impl CHHPeekMut
+
+impl<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize> core::ops::Deref
+for CHHPeekMut<'a,KT,VT,CAP>
+{
+  type Target = VT;
+  fn deref(&self) -> &VT {
+    &self.chh.vals[0].as_ref().unwrap().0
+  }
+}
+impl<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize> core::ops::DerefMut
+for CHHPeekMut<'a,KT,VT,CAP>
+{
+  fn deref_mut(&mut self) -> &mut VT {
+    &mut self.chh.vals[0].as_mut().unwrap().0
+  }
+}
+impl<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize> Drop
+for CHHPeekMut<'a,KT,VT,CAP>
+{
+  fn drop(&mut self) {
+    if self.chh.size > 1 { self.chh.swapdown(0); }
+  }
+}// drop adjusts heap
+
 /////////////////// iterators
 
 /// Iterator for the [ConstHashHeap::iter] function
@@ -618,7 +1425,40 @@ Iterator for CHHIter<'a,KT,VT,CAP> {
     }
     answer
   }//next
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let n = self.chh.size().saturating_sub(self.index);
+    (n, Some(n))
+  }
 }// CHHIter impl
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> ExactSizeIterator
+for CHHIter<'a,KT,VT,CAP> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> core::iter::FusedIterator
+for CHHIter<'a,KT,VT,CAP> {}
+
+/// Iterator for the [ConstHashHeap::iter_with_index] function
+pub struct CHHIndexIter<'a, KT,VT, const CAP:usize>
+{
+  chh : &'a ConstHashHeap<KT,VT,CAP>,
+  index : usize,
+}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize>
+Iterator for CHHIndexIter<'a,KT,VT,CAP> {
+  type Item = (usize, &'a KT, &'a VT);
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.index >= self.chh.size() {return None;}
+    self.index+=1;
+    let (val,ki) = self.chh.vals[self.index-1].as_ref()?;
+    self.chh.keys[*ki].as_ref().map(|(key,_)|(*ki,key,val))
+  }//next
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let n = self.chh.size().saturating_sub(self.index);
+    (n, Some(n))
+  }
+}// CHHIndexIter impl
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> ExactSizeIterator
+for CHHIndexIter<'a,KT,VT,CAP> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> core::iter::FusedIterator
+for CHHIndexIter<'a,KT,VT,CAP> {}
 
 impl<'a, KT: Hash + Eq, VT: PartialOrd, const CAP:usize> IntoIterator
 for &'a ConstHashHeap<KT,VT,CAP>
@@ -639,6 +1479,81 @@ for PriorityStream<'a,KT,VT,CAP>
   fn next(&mut self) -> Option<Self::Item> {
     self.0.pop()
   }
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.0.size(), Some(self.0.size()))
+  }
+}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> ExactSizeIterator
+for PriorityStream<'a,KT,VT,CAP> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> core::iter::FusedIterator
+for PriorityStream<'a,KT,VT,CAP> {}
+
+/// Iterator for the [ConstHashHeap::pop_while] function
+pub struct CHHPopWhile<'a,KT,VT,const CAP:usize,F>
+{
+  chh : &'a mut ConstHashHeap<KT,VT,CAP>,
+  pred : F,
+}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize, F> Iterator
+for CHHPopWhile<'a,KT,VT,CAP,F> where F: FnMut(&KT,&VT) -> bool
+{
+  type Item = (KT,VT);
+  fn next(&mut self) -> Option<Self::Item> {
+    let pred = &mut self.pred;
+    self.chh.pop_if(|k,v|pred(k,v))
+  }
+}
+
+/// Iterator for the [ConstHashHeap::drain] function
+pub struct CHHDrain<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize>(&'a mut ConstHashHeap<KT,VT,CAP>);
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> Iterator
+for CHHDrain<'a,KT,VT,CAP>
+{
+  type Item = (KT,VT);
+  fn next(&mut self) -> Option<Self::Item> {
+    self.0.pop_last()
+  }
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.0.size(), Some(self.0.size()))
+  }
+}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> ExactSizeIterator
+for CHHDrain<'a,KT,VT,CAP> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> core::iter::FusedIterator
+for CHHDrain<'a,KT,VT,CAP> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> Drop
+for CHHDrain<'a,KT,VT,CAP>
+{
+  fn drop(&mut self) {
+    while self.0.pop_last().is_some() {}
+    self.0.reset_probes();
+  }
+}
+
+/// Iterator for the [ConstHashHeap::drain_sorted] function
+pub struct CHHDrainSorted<'a,KT:Hash+Eq,VT:PartialOrd,const CAP:usize>(&'a mut ConstHashHeap<KT,VT,CAP>);
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> Iterator
+for CHHDrainSorted<'a,KT,VT,CAP>
+{
+  type Item = (KT,VT);
+  fn next(&mut self) -> Option<Self::Item> {
+    self.0.pop()
+  }
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.0.size(), Some(self.0.size()))
+  }
+}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> ExactSizeIterator
+for CHHDrainSorted<'a,KT,VT,CAP> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> core::iter::FusedIterator
+for CHHDrainSorted<'a,KT,VT,CAP> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd, const CAP:usize> Drop
+for CHHDrainSorted<'a,KT,VT,CAP>
+{
+  fn drop(&mut self) {
+    while self.0.pop_last().is_some() {}
+    self.0.reset_probes();
+  }
 }
 
 impl<'a, KT: Hash + Eq, VT: PartialOrd, const CAP:usize> IntoIterator
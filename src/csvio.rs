@@ -0,0 +1,141 @@
+//! Import and export of [HashHeap] contents as CSV or TSV text, enabled
+//! by the `csv` feature.  Keys and values are written with their
+//! [Display] implementations and read back with [FromStr].  The output
+//! starts with a comment line recording whether the structure is a
+//! maxheap or minheap, followed by a `key,value` header line and one line
+//! per entry, in no particular order.  Fields containing the delimiter,
+//! quotes or line breaks are quoted as in RFC 4180, as are fields that
+//! start with `#`, which would otherwise be read as comments, and fields
+//! with leading or trailing whitespace.
+
+use crate::HashHeap;
+use core::fmt::Display;
+use core::hash::Hash;
+use core::str::FromStr;
+use std::io::{self, BufRead, Write};
+
+pub(crate) fn quote(field:String, delim:char) -> String {
+  if field.contains(delim) || field.contains('"') || field.contains('\n')
+     || field.contains('\r') || field.starts_with('#') || field.trim() != field {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  }
+  else { field }
+}
+
+// splits a record into fields; returns None if a quoted field is not
+// terminated on this line
+pub(crate) fn split_record(line:&str, delim:char) -> Option<Vec<String>> {
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut chars = line.chars().peekable();
+  let mut quoted = false;
+  while let Some(c) = chars.next() {
+    if quoted {
+      if c == '"' {
+        if chars.peek() == Some(&'"') { field.push('"'); chars.next(); }
+        else { quoted = false; }
+      }
+      else { field.push(c); }
+    }
+    else if c == '"' { quoted = true; }
+    else if c == delim { fields.push(core::mem::take(&mut field)); }
+    else { field.push(c); }
+  }//while
+  if quoted { return None; }
+  fields.push(field);
+  Some(fields)
+}//split_record
+
+fn invalid(lineno:usize, msg:&str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", lineno, msg))
+}
+
+impl<KT:Hash+Eq+Display, VT:PartialOrd+Display> HashHeap<KT,VT> {
+  /// writes all entries as comma-separated values: see the
+  /// [csvio](crate::csvio) module.
+  pub fn to_csv<W:Write>(&self, writer:W) -> io::Result<()> {
+    self.to_delimited(writer, ',')
+  }
+
+  /// writes all entries as tab-separated values
+  pub fn to_tsv<W:Write>(&self, writer:W) -> io::Result<()> {
+    self.to_delimited(writer, '\t')
+  }
+
+  /// writes all entries, separating keys and values with the given
+  /// delimiter
+  pub fn to_delimited<W:Write>(&self, mut writer:W, delim:char) -> io::Result<()> {
+    let polarity = if self.is_max_hashheap() {"maxheap"} else {"minheap"};
+    writeln!(writer, "# hashheap {}", polarity)?;
+    writeln!(writer, "key{}value", delim)?;
+    for (k,v) in self.iter() {
+      writeln!(writer, "{}{}{}", quote(k.to_string(),delim), delim,
+               quote(v.to_string(),delim))?;
+    }
+    Ok(())
+  }//to_delimited
+}
+
+impl<KT:Hash+Eq+FromStr, VT:PartialOrd+FromStr> HashHeap<KT,VT> {
+  /// reads entries in the format written by [HashHeap::to_csv] and builds
+  /// a HashHeap from them.  The polarity of the result is determined by the
+  /// `maxheap` argument, not by the comment line.  Comment lines (starting
+  /// with an unquoted `#`), lines that are blank and contain no delimiter,
+  /// and a `key,value` header line are skipped.  If a key occurs more than once,
+  /// the last value is kept.
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut hh = HashHeap::<String,u32>::new_minheap();
+  ///   hh.insert("a,b".to_string(), 3);
+  ///   hh.insert("c".to_string(), 1);
+  ///   let mut out = Vec::new();
+  ///   hh.to_csv(&mut out).unwrap();
+  ///   let hh2 = HashHeap::<String,u32>::from_csv(&out[..], false).unwrap();
+  ///   assert_eq!(hh2.get(&"a,b".to_string()), Some(&3));
+  ///   assert_eq!(hh2.peek(), Some((&"c".to_string(),&1)));
+  /// ```
+  pub fn from_csv<R:BufRead>(reader:R, maxheap:bool) -> io::Result<Self> {
+    Self::from_delimited(reader, ',', maxheap)
+  }
+
+  /// reads entries in the format written by [HashHeap::to_tsv]
+  pub fn from_tsv<R:BufRead>(reader:R, maxheap:bool) -> io::Result<Self> {
+    Self::from_delimited(reader, '\t', maxheap)
+  }
+
+  /// reads entries separated by the given delimiter: see [HashHeap::from_csv]
+  pub fn from_delimited<R:BufRead>(reader:R, delim:char, maxheap:bool) -> io::Result<Self> {
+    let mut pairs = Vec::new();
+    let mut lines = reader.lines();
+    let mut lineno = 0;
+    let mut header = true;
+    while let Some(line) = lines.next() {
+      let mut line = line?;
+      lineno += 1;
+      if (line.trim().is_empty() && !line.contains(delim)) || line.starts_with('#') {
+        continue;
+      }
+      let mut fields = split_record(&line, delim);
+      while fields.is_none() {  // quoted field continues on next line
+        match lines.next() {
+          Some(more) => { line.push('\n'); line.push_str(&more?); lineno += 1; },
+          None => { return Err(invalid(lineno, "unterminated quoted field")); },
+        }
+        fields = split_record(&line, delim);
+      }
+      let fields = fields.unwrap();
+      if header && fields.len() == 2 && fields[0] == "key" && fields[1] == "value" {
+        header = false;
+        continue;
+      }
+      header = false;
+      if fields.len() != 2 { return Err(invalid(lineno, "expected two fields")); }
+      let k = fields[0].parse::<KT>().map_err(|_|invalid(lineno, "invalid key"))?;
+      let v = fields[1].parse::<VT>().map_err(|_|invalid(lineno, "invalid value"))?;
+      pairs.push((k,v));
+    }//while
+    let mut hh = HashHeap::with_capacity(pairs.len()+1, maxheap);
+    for (k,v) in pairs { hh.insert(k,v); }
+    Ok(hh)
+  }//from_delimited
+}
@@ -0,0 +1,136 @@
+//! This module contains [DagScheduler], a priority queue of tasks that
+//! may depend on other tasks.  A task becomes *ready* only when all of
+//! its dependencies have completed, and ready tasks are popped in order
+//! of priority.  The ready tasks are kept in a [HashHeap], so completing
+//! a task moves each dependent that it unlocks into the heap in O(log n)
+//! time, and the priority of a ready task can be changed in place.
+//!
+//! A dependency on a key that is not currently known to the scheduler
+//! (never added, or already completed) is considered satisfied.  Tasks
+//! that depend on each other in a cycle never become ready.
+
+use crate::HashHeap;
+use core::hash::Hash;
+use std::collections::{HashMap, HashSet};
+
+/// A dependency-aware ready queue: see the
+/// [module documentation](crate::dagscheduler).
+/// ```
+/// # use hashheap::*;
+///   let mut sched = DagScheduler::<&str,u32>::new(true); // maxheap
+///   sched.add("compile", 5, []);
+///   sched.add("test", 9, ["compile"]);
+///   sched.add("docs", 1, []);
+///   assert_eq!(sched.pop(), Some(("compile",5)));  // test not ready
+///   assert_eq!(sched.pop(), Some(("docs",1)));
+///   assert_eq!(sched.pop(), None);
+///   sched.complete(&"compile");
+///   assert_eq!(sched.pop(), Some(("test",9)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct DagScheduler<KT,VT>
+{
+  ready : HashHeap<KT,VT>,
+  waiting : HashMap<KT,(VT,usize)>,    // priority, unfinished dependencies
+  dependents : HashMap<KT,Vec<KT>>,    // key to tasks waiting on it
+  running : HashSet<KT>,               // popped but not completed
+}
+impl<KT:Hash+Eq+Clone, VT:PartialOrd> DagScheduler<KT,VT> {
+
+  /// creates an empty scheduler; true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    DagScheduler {
+      ready : HashHeap::with_capacity(0,maxheap),
+      waiting : HashMap::new(),
+      dependents : HashMap::new(),
+      running : HashSet::new(),
+    }
+  }
+
+  /// determines if the key is known to the scheduler, whether waiting,
+  /// ready or running
+  pub fn contains_key(&self, key:&KT) -> bool {
+    self.ready.contains_key(key) || self.waiting.contains_key(key)
+    || self.running.contains(key)
+  }
+
+  /// Adds a task with the given priority and dependencies.  Returns false,
+  /// without changing anything, if the key is already known.
+  pub fn add<I>(&mut self, key:KT, val:VT, deps:I) -> bool
+  where I: IntoIterator<Item=KT>
+  {
+    if self.contains_key(&key) { return false; }
+    let mut count = 0;
+    for d in deps {
+      if d != key && self.contains_key(&d) {
+        self.dependents.entry(d).or_default().push(key.clone());
+        count += 1;
+      }
+    }
+    if count == 0 { self.ready.insert(key,val); }
+    else { self.waiting.insert(key,(val,count)); }
+    true
+  }//add
+
+  /// removes and returns the ready task with the highest priority.  The
+  /// task is considered running until [Self::complete] is called.  O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    let (k,v) = self.ready.pop()?;
+    self.running.insert(k.clone());
+    Some((k,v))
+  }
+
+  /// returns the ready task with the highest priority.  O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    self.ready.peek()
+  }
+
+  /// Marks a running task as completed, which makes each task that
+  /// depends on it ready if it has no other unfinished dependencies.
+  /// Returns false if the task is not running.  O(d log n) where d is the
+  /// number of dependents.
+  pub fn complete(&mut self, key:&KT) -> bool {
+    if !self.running.remove(key) { return false; }
+    for d in self.dependents.remove(key).unwrap_or_default() {
+      let unlocked = match self.waiting.get_mut(&d) {
+        Some((_,count)) => { *count -= 1; *count == 0 },
+        None => false,
+      };
+      if unlocked {
+        if let Some((d,(v,_))) = self.waiting.remove_entry(&d) {
+          self.ready.insert(d,v);
+        }
+      }
+    }//for
+    true
+  }//complete
+
+  /// changes the priority of a task that has not yet been popped,
+  /// returning false if there's no such task.  O(log n)
+  pub fn set_priority(&mut self, key:&KT, val:VT) -> bool {
+    if let Some((v,_)) = self.waiting.get_mut(key) {
+      *v = val;
+      true
+    }
+    else { self.ready.modify(key,|v|*v=val).is_some() }
+  }
+
+  /// the priority of a task that has not yet been popped
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    self.ready.get(key).or_else(|| self.waiting.get(key).map(|p|&p.0))
+  }
+
+  /// the number of tasks ready to be popped
+  pub fn ready_len(&self) -> usize { self.ready.len() }
+
+  /// the number of tasks waiting on dependencies
+  pub fn waiting_len(&self) -> usize { self.waiting.len() }
+
+  /// the number of tasks popped but not completed
+  pub fn running_len(&self) -> usize { self.running.len() }
+
+  /// returns true if there are no tasks in any state
+  pub fn is_empty(&self) -> bool {
+    self.ready.len() == 0 && self.waiting.is_empty() && self.running.is_empty()
+  }
+}//impl DagScheduler
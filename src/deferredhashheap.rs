@@ -0,0 +1,177 @@
+//! This module contains [DeferredHashHeap], a [HashHeap] for
+//! construction-dominated workloads.  During an initial *loading phase*,
+//! new entries are only appended to the end of the heap array, without
+//! being sifted up.  The first operation that depends on the order of the
+//! heap, such as peek or pop, ends the loading phase by restoring the heap
+//! property in a single pass: by heapify, in O(n) time, if most entries
+//! are pending, and by sifting up each pending entry otherwise.
+//!
+//! After the loading phase, new entries may continue to be appended
+//! lazily, up to a *pending threshold* set with
+//! [DeferredHashHeap::set_threshold].  The threshold defaults to zero, in
+//! which case every later insertion is sifted up immediately, as in a
+//! HashHeap.  Lookups by key are never delayed, since the keys are always
+//! hashed as soon as they're inserted.
+
+use crate::HashHeap;
+use core::hash::Hash;
+
+/// A HashHeap with deferred heapify: see the
+/// [module documentation](crate::deferredhashheap).
+/// ```
+/// # use hashheap::*;
+///   let mut dh = DeferredHashHeap::<u32,u32>::new(true);
+///   for i in 0..1000 { dh.insert(i, (i*37)%1000); }  // no sifting
+///   assert_eq!(dh.pending(), 1000);
+///   assert_eq!(dh.get(&3), Some(&111));
+///   assert_eq!(dh.peek(), Some((&27,&999)));  // heapify happens here
+///   assert_eq!(dh.pending(), 0);
+///   let hh:HashHeap<u32,u32> = dh.into_hashheap();
+///   assert_eq!(hh.len(), 1000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DeferredHashHeap<KT,VT>
+{
+  heap : HashHeap<KT,VT>,
+  pending : usize,     // entries at end of heap array not yet sifted
+  threshold : usize,   // max pending after loading phase
+  loading : bool,
+}
+impl<KT:Hash+Eq, VT:PartialOrd> DeferredHashHeap<KT,VT> {
+
+  /// creates an empty structure in the loading phase; true = maxheap,
+  /// false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    Self::with_capacity(0, maxheap)
+  }
+
+  /// creates an empty structure with the given initial capacity
+  pub fn with_capacity(cap:usize, maxheap:bool) -> Self {
+    DeferredHashHeap {
+      heap : HashHeap::with_capacity(cap,maxheap),
+      pending : 0,
+      threshold : 0,
+      loading : true,
+    }
+  }
+
+  /// Sets the number of entries that may be appended without sifting after
+  /// the loading phase.  The pending entries are then sifted up together
+  /// when the threshold is exceeded or when the order of the heap is
+  /// needed.
+  pub fn set_threshold(&mut self, threshold:usize) {
+    self.threshold = threshold;
+    if !self.loading && self.pending > threshold { self.settle(); }
+  }
+
+  /// the number of entries that have not yet been placed in heap order
+  pub fn pending(&self) -> usize { self.pending }
+
+  /// returns true while the structure is in the loading phase
+  pub fn is_loading(&self) -> bool { self.loading }
+
+  /// Restores the heap property, which ends the loading phase, and returns
+  /// the underlying HashHeap for any operation not provided here.
+  pub fn settle(&mut self) -> &mut HashHeap<KT,VT> {
+    self.loading = false;
+    let n = self.heap.vals.len();
+    if self.pending > n - self.pending {
+      self.heap.reheap();
+    }
+    else {
+      for vi in n-self.pending .. n { self.heap.swapup(vi); }
+    }
+    self.pending = 0;
+    &mut self.heap
+  }//settle
+
+  // settles if there are more pending entries than allowed
+  fn check_pending(&mut self) {
+    if !self.loading && self.pending > self.threshold { self.settle(); }
+  }
+
+  /// Add or change a key-value pair, returning the replaced pair, if it
+  /// exists.  A new pair, or a replacement of a pending pair, is appended
+  /// without sifting.  Replacing the value of a pair already in heap order
+  /// first settles all pending entries.
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    let n = self.heap.vals.len();
+    match self.heap.findslot(&key) {
+      (h,true) => {
+        let (ki,vi) = self.heap.kmap[&h];
+        if vi >= n - self.pending {
+          let oldkey = self.heap.keys[ki].replace(key);
+          let oldval = core::mem::replace(&mut self.heap.vals[vi].0, val);
+          Some((oldkey.unwrap(), oldval))
+        }
+        else { self.settle().insert(key,val) }
+      },
+      _ => {
+        self.heap.append_unsorted(key,val);
+        self.pending += 1;
+        self.check_pending();
+        None
+      },
+    }//match
+  }//insert
+
+  /// Version of insert that does not replace an existing key, returning
+  /// false instead.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    let added = self.heap.append_unsorted(key,val);
+    if added {
+      self.pending += 1;
+      self.check_pending();
+    }
+    added
+  }
+
+  /// returns the value associated with the key without settling.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> { self.heap.get(key) }
+
+  /// determines if the key exists, without settling.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// the number of entries, including pending entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// returns the entry with the highest priority, after settling
+  pub fn peek(&mut self) -> Option<(&KT,&VT)> { self.settle().peek() }
+
+  /// removes and returns the entry with the highest priority, after
+  /// settling
+  pub fn pop(&mut self) -> Option<(KT,VT)> { self.settle().pop() }
+
+  /// modifies the value associated with the key, after settling: see
+  /// [HashHeap::modify]
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    self.settle().modify(key,f).is_some()
+  }
+
+  /// removes and returns the pair with the given key, after settling
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.settle().remove(key)
+  }
+
+  /// iterates over all entries in no particular order, without settling
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ { self.heap.iter() }
+
+  /// settles and returns the underlying HashHeap
+  pub fn into_hashheap(mut self) -> HashHeap<KT,VT> {
+    self.settle();
+    self.heap
+  }
+}//impl DeferredHashHeap
+
+impl<KT:Hash+Eq, VT:PartialOrd> From<DeferredHashHeap<KT,VT>> for HashHeap<KT,VT> {
+  fn from(dh:DeferredHashHeap<KT,VT>) -> Self { dh.into_hashheap() }
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd> Extend<(KT,VT)> for DeferredHashHeap<KT,VT> {
+  fn extend<I:IntoIterator<Item=(KT,VT)>>(&mut self, iter:I) {
+    for (k,v) in iter { self.insert(k,v); }
+  }
+}
@@ -0,0 +1,53 @@
+//! Export of the binary heap inside a [HashHeap] in the DOT language of
+//! [Graphviz](https://graphviz.org), enabled by the `dot` feature.  Each
+//! entry becomes a node labeled with its heap index, key and value, with
+//! an edge to each of its children, so that the result can be rendered
+//! with `dot -Tsvg` to see the shape of the heap.  This is meant for
+//! teaching and for checking the behavior of custom comparators.
+
+use crate::HashHeap;
+use core::fmt::Debug;
+use core::hash::Hash;
+use std::io::{self, Write};
+
+// escapes a label for a double-quoted DOT string
+fn escape(label:String) -> String {
+  label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl<KT:Hash+Eq+Debug, VT:Debug> HashHeap<KT,VT> {
+  /// returns the heap as a DOT digraph: see the [dot](crate::dot) module.
+  /// Keys and values are written with their [Debug] implementations.
+  /// ```
+  /// # use hashheap::*;
+  ///   let hh:HashHeap<&str,u32> = vec![("a",1),("b",3),("c",2)].into();
+  ///   let dot = hh.to_dot();
+  ///   assert!(dot.starts_with("digraph hashheap {"));
+  ///   assert!(dot.contains("n0 [label=\"0: \\\"b\\\" = 3\"];"));
+  ///   assert!(dot.contains("n0 -> n1;"));
+  ///   assert!(dot.contains("n0 -> n2;"));
+  /// ```
+  pub fn to_dot(&self) -> String {
+    let mut out = Vec::new();
+    self.write_dot(&mut out).expect("writing to a Vec cannot fail");
+    String::from_utf8(out).expect("labels are valid UTF-8")
+  }
+
+  /// writes the heap as a DOT digraph to the given writer
+  pub fn write_dot<W:Write>(&self, mut writer:W) -> io::Result<()> {
+    let polarity = if self.is_max_hashheap() {"maxheap"} else {"minheap"};
+    writeln!(writer, "digraph hashheap {{")?;
+    writeln!(writer, "  label=\"{} with {} entries\";", polarity, self.len())?;
+    writeln!(writer, "  node [shape=box];")?;
+    for (i,k,v) in self.iter_heap_order() {
+      let label = escape(format!("{}: {:?} = {:?}", i, k, v));
+      writeln!(writer, "  n{} [label=\"{}\"];", i, label)?;
+    }
+    for i in 0..self.len() {
+      for c in self.heap_children(i) {
+        writeln!(writer, "  n{} -> n{};", i, c)?;
+      }
+    }
+    writeln!(writer, "}}")
+  }//write_dot
+}
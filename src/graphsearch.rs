@@ -0,0 +1,112 @@
+//! Integration with the [petgraph](https://docs.rs/petgraph) crate,
+//! enabled by the `petgraph` feature.  [Frontier] adapts a min-[HashHeap]
+//! to serve as the open (tentative) set of shortest-path algorithms, where
+//! the cost of a node already in the frontier is lowered in place when a
+//! better path is found, instead of pushing duplicate entries.  The
+//! function [dijkstra_with_hashheap] implements Dijkstra's algorithm on
+//! any petgraph graph with this frontier, with the same signature as
+//! `petgraph::algo::dijkstra`.
+
+use crate::HashHeap;
+use core::hash::Hash;
+use petgraph::algo::Measure;
+use petgraph::visit::{EdgeRef, IntoEdges, VisitMap, Visitable};
+use std::collections::HashMap;
+
+/// The open set of a best-first graph search, keyed by node: see the
+/// [module documentation](crate::graphsearch).
+/// ```
+/// # use hashheap::*;
+///   let mut open = Frontier::<char,u32>::new();
+///   assert!(open.push_or_decrease('a', 9));
+///   assert!(open.push_or_decrease('b', 4));
+///   assert!(open.push_or_decrease('a', 3));   // better path to a
+///   assert!(!open.push_or_decrease('b', 6));  // not better
+///   assert_eq!(open.pop(), Some(('a',3)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Frontier<N,K>
+{
+  heap : HashHeap<N,K>,
+}
+impl<N:Hash+Eq, K:PartialOrd> Frontier<N,K> {
+  /// creates an empty frontier
+  pub fn new() -> Self {
+    Frontier { heap : HashHeap::with_capacity(0,false) }
+  }
+
+  /// Adds the node with the given cost, or lowers its cost if it's already
+  /// in the frontier with a higher cost.  Returns true if the frontier was
+  /// changed.  O(log n).
+  pub fn push_or_decrease(&mut self, node:N, cost:K) -> bool {
+    match self.heap.get(&node) {
+      Some(c) if *c <= cost => false,
+      Some(_) => self.heap.modify(&node, |c|*c=cost).is_some(),
+      None => self.heap.push(node,cost),
+    }
+  }
+
+  /// removes and returns the node with the lowest cost.  O(log n)
+  pub fn pop(&mut self) -> Option<(N,K)> { self.heap.pop() }
+
+  /// returns the node with the lowest cost.  O(1)
+  pub fn peek(&self) -> Option<(&N,&K)> { self.heap.peek() }
+
+  /// the current cost of the node, if it's in the frontier
+  pub fn cost(&self, node:&N) -> Option<&K> { self.heap.get(node) }
+
+  /// the number of nodes in the frontier
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if the frontier is empty
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// returns the underlying HashHeap
+  pub fn into_hashheap(self) -> HashHeap<N,K> { self.heap }
+}//impl Frontier
+
+impl<N:Hash+Eq, K:PartialOrd> Default for Frontier<N,K> {
+  fn default() -> Self { Self::new() }
+}
+
+/// Dijkstra's shortest path algorithm using a [Frontier].  Returns the
+/// cost of the shortest path from `start` to every node reachable from it,
+/// or only to the nodes settled before `goal` if a goal is given.  Edge
+/// costs must be non-negative.  With the HashHeap, each node is in the
+/// frontier at most once, so the running time is O((V + E) log V).
+/// ```
+/// # use hashheap::*;
+///   use petgraph::Graph;
+///   let mut g = Graph::<(),u32>::new();
+///   let (a,b,c,d) = (g.add_node(()), g.add_node(()), g.add_node(()), g.add_node(()));
+///   g.extend_with_edges([(a,b,7), (a,c,2), (c,b,3), (b,d,1)]);
+///   let dist = dijkstra_with_hashheap(&g, a, None, |e| *e.weight());
+///   assert_eq!(dist[&b], 5);
+///   assert_eq!(dist[&d], 6);
+///   assert_eq!(dist, petgraph::algo::dijkstra(&g, a, None, |e| *e.weight())
+///                      .into_iter().collect());
+/// ```
+pub fn dijkstra_with_hashheap<G,F,K>(graph:G, start:G::NodeId, goal:Option<G::NodeId>,
+                                     mut edge_cost:F) -> HashMap<G::NodeId,K>
+where G: IntoEdges + Visitable,
+      G::NodeId: Eq + Hash,
+      F: FnMut(G::EdgeRef) -> K,
+      K: Measure + Copy,
+{
+  let mut visited = graph.visit_map();
+  let mut scores = HashMap::new();
+  let mut open = Frontier::new();
+  scores.insert(start, K::default());
+  open.push_or_decrease(start, K::default());
+  while let Some((node,cost)) = open.pop() {
+    visited.visit(node);
+    if goal.as_ref() == Some(&node) { break; }
+    for edge in graph.edges(node) {
+      let next = edge.target();
+      if visited.is_visited(&next) { continue; }
+      let newcost = cost + edge_cost(edge);
+      if open.push_or_decrease(next, newcost) { scores.insert(next, newcost); }
+    }
+  }//while
+  scores
+}//dijkstra_with_hashheap
@@ -0,0 +1,410 @@
+//! This module contains [GrowableHashHeap], which uses the same internal
+//! layout as [ConstHashHeap](crate::ConstHashHeap) but stores its arrays
+//! in vectors that are reallocated automatically.  As in a ConstHashHeap,
+//! the keys are kept in a closed hash table (open addressing) with linear
+//! probing, and each entry records the index of its counterpart in the
+//! values array, which is treated as a binary heap.  No Rust HashMap is used.
+//!
+//! Whenever an insertion of a new key would cause the load factor of the
+//! hash table to exceed the maximum load factor (0.75 by default, see
+//! [GrowableHashHeap::set_max_load]), the capacity of the table is doubled
+//! and all keys are rehashed.  Rehashing takes O(n) time but, as with
+//! vectors, the cost of insertion remains amortized O(log n).  The
+//! capacity of the table is always a power of two.
+//!
+//! A ConstHashHeap can't simply be wrapped: its arrays are part of its
+//! type, so growing one means moving its entries to a structure of another
+//! type, as [resize](crate::ConstHashHeap::resize) does.  The same layout is
+//! therefore kept here in vectors, which also saves the `Option` around
+//! each value that a fixed array needs.  As in a ConstHashHeap, the full
+//! hash of every key is cached, so that probes compare hashes before keys
+//! and growing the table never hashes a key again.  The comparison
+//! function can be replaced by a closure, as in a [HashHeap](crate::HashHeap).
+
+use crate::consthashheap::{left, right, parent};
+use crate::{CmpFn, UserFn};
+use std::collections::hash_map::RandomState;
+use core::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+const DEFAULTCAP : usize = 16;
+const DEFAULTLOAD : f32 = 0.75;
+
+/// A hashheap map that grows automatically: see
+/// [module documentation](crate::growablehashheap) for overview.
+#[derive(Clone, Debug)]
+pub struct GrowableHashHeap<KT,VT>
+{
+   keys : Vec<Option<(KT,usize)>>,
+   vals : Vec<(VT,usize)>,
+   maxhashes : Vec<usize>, // max number of hashes from start
+   fullhashes : Vec<u64>,  // unreduced hash of key at each index
+   autostate : RandomState,
+   maxload : f32,
+   lessthan : CmpFn<VT>,
+   maxheap : bool,
+}
+impl<KT:Hash+Eq, VT:PartialOrd> GrowableHashHeap<KT,VT> {
+
+  /// creates a new GrowableHashHeap with a small default capacity.  The
+  /// boolean argument distinguishes maxheap and minheap, true = maxheap.
+  pub fn new(maxheap:bool) -> Self {
+    Self::with_capacity(DEFAULTCAP, maxheap)
+  }
+
+  /// creates a new GrowableHashHeap that can hold at least `cap` entries
+  /// before it needs to grow.
+  pub fn with_capacity(cap:usize, maxheap:bool) -> Self {
+    let tablecap = ((cap as f32 / DEFAULTLOAD) as usize + 1)
+                   .max(DEFAULTCAP).next_power_of_two();
+    GrowableHashHeap {
+      keys : (0..tablecap).map(|_|None).collect(),
+      vals : Vec::with_capacity(cap),
+      maxhashes : vec![0;tablecap],
+      fullhashes : vec![0;tablecap],
+      autostate : RandomState::new(),
+      maxload : DEFAULTLOAD,
+      lessthan : UserFn::Ptr(if maxheap {|a,b| a<b} else {|a,b| b<a}),
+      maxheap,
+    }
+  }//with_capacity
+
+  /// sets the maximum load factor, which must be strictly between zero
+  /// and one.  The default is 0.75.  The table grows when an insertion
+  /// would exceed this factor.  Returns false if the argument is invalid.
+  pub fn set_max_load(&mut self, load:f32) -> bool {
+    if load <= 0.0 || load >= 1.0 { return false; }
+    self.maxload = load;
+    true
+  }
+
+  /// Override the comparison function with a function cmp such that
+  /// `cmp(a,b)` is true means a is "less than" b, as in
+  /// [HashHeap::set_cmp](crate::HashHeap::set_cmp).  This operation is
+  /// only allowed when the size of the structure is no more than one.
+  /// Returns true on success.
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut ghh = GrowableHashHeap::<&str,(u32,u32)>::new(true);
+  ///   assert!(ghh.set_cmp(|a,b| a.1 < b.1));   // by second component
+  ///   ghh.insert("a",(9,1));
+  ///   ghh.insert("b",(1,5));
+  ///   assert_eq!(ghh.peek(), Some((&"b",&(1,5))));
+  /// ```
+  pub fn set_cmp(&mut self, cmp:fn(&VT,&VT) -> bool) -> bool {
+    if self.len() > 1 { return false; }
+    self.lessthan = UserFn::Ptr(cmp);
+    true
+  }
+
+  /// Version of [set_cmp](Self::set_cmp) that accepts a closure, as in
+  /// [HashHeap::set_cmp_with](crate::HashHeap::set_cmp_with).
+  pub fn set_cmp_with<F>(&mut self, cmp:F) -> bool
+  where F: Fn(&VT,&VT) -> bool + Send + Sync + 'static
+  {
+    if self.len() > 1 { return false; }
+    self.lessthan = UserFn::Closure(Arc::new(cmp));
+    true
+  }
+
+  fn fullhash(&self, key:&KT) -> u64 { self.autostate.hash_one(key) }
+
+  fn reduce(&self, hv:u64) -> usize { (hv as usize) & (self.keys.len()-1) }
+
+  fn rehash(&self, h:usize) -> usize { (h+1) & (self.keys.len()-1) }
+
+  // given the full hash hv of the key, returns Ok(h) if key found at hash
+  // index h, else Err((h0,h,hashes)), where h is the slot to insert into,
+  // h0 the original hash index, and hashes the number of hashes required
+  // to reach h.
+  fn probe(&self, key:&KT, hv:u64) -> Result<usize,(usize,usize,usize)> {
+    let h0 = self.reduce(hv);
+    let mut h = h0;
+    let mut hashes = 1;
+    let mut reuse = None;
+    loop {
+      match &self.keys[h] {
+        Some((k,_)) if self.fullhashes[h]==hv && k==key => { return Ok(h); },
+        Some(_) => {},
+        None => {
+          if reuse.is_none() { reuse = Some((h,hashes)); }
+        },
+      }//match
+      if reuse.is_some() && hashes >= self.maxhashes[h0] { break; }
+      h = self.rehash(h);
+      hashes += 1;
+    }//loop
+    let (h,hashes) = reuse.unwrap();
+    Err((h0,h,hashes))
+  }//probe
+
+  fn find(&self, key:&KT) -> Option<usize> {
+    let hv = self.fullhash(key);
+    let h0 = self.reduce(hv);
+    let mut h = h0;
+    let mut hashes = 1;
+    loop {
+      match &self.keys[h] {
+        Some((k,_)) if self.fullhashes[h]==hv && k==key => { return Some(h); },
+        _ if hashes < self.maxhashes[h0] => {
+          h = self.rehash(h);
+          hashes += 1;
+        },
+        _ => { return None; },
+      }//match
+    }//loop
+  }//find
+
+  fn swap(&mut self, i:usize, k:usize) {
+    self.vals.swap(i,k);
+    let (ik,kk) = (self.vals[i].1, self.vals[k].1);
+    if let Some(pair) = self.keys[ik].as_mut() { pair.1 = i; }
+    if let Some(pair) = self.keys[kk].as_mut() { pair.1 = k; }
+  }//swap
+
+  fn swapup(&mut self, mut i:usize) -> usize {
+    while i>0 && self.lessthan.call(&self.vals[parent(i)].0,&self.vals[i].0) {
+       self.swap(i,parent(i));
+       i = parent(i);
+    }
+    i
+  }//swapup
+
+  fn swapdown(&mut self, mut i:usize) -> usize {
+    let size = self.vals.len();
+    loop {
+      let lf = left(i);
+      let rt = right(i);
+      let mut si = i;
+      if lf<size && self.lessthan.call(&self.vals[si].0,&self.vals[lf].0) {
+        si = lf;
+      }
+      if rt<size && self.lessthan.call(&self.vals[si].0,&self.vals[rt].0) {
+        si = rt;
+      }
+      if si==i { break; }
+      self.swap(i,si);
+      i = si;
+    }//loop
+    i
+  }//swapdown
+
+  fn adjust(&mut self, i:usize) -> usize {
+    let k = self.swapup(i);
+    if k==i {self.swapdown(i)} else {k}
+  }
+
+  // move all keys to a new table of given capacity (a power of two),
+  // using their cached hashes
+  fn rebuild(&mut self, newcap:usize) {
+    let mut oldkeys = core::mem::replace(&mut self.keys, (0..newcap).map(|_|None).collect());
+    let oldhashes = core::mem::replace(&mut self.fullhashes, vec![0;newcap]);
+    self.maxhashes = vec![0;newcap];
+    for vi in 0..self.vals.len() {
+      let ki = self.vals[vi].1;
+      if let Some((key,_)) = oldkeys[ki].take() {
+        let hv = oldhashes[ki];
+        let h0 = self.reduce(hv);
+        let mut h = h0;
+        let mut hashes = 1;
+        while self.keys[h].is_some() {
+          h = self.rehash(h);
+          hashes += 1;
+        }
+        if hashes > self.maxhashes[h0] { self.maxhashes[h0] = hashes; }
+        self.keys[h] = Some((key,vi));
+        self.fullhashes[h] = hv;
+        self.vals[vi].1 = h;
+      }
+    }//for
+  }//rebuild
+
+  /// Inserts a new key-value pair, or changes the value associated with
+  /// an existing key, in which case the replaced pair is returned.  The
+  /// table is enlarged if necessary.  This operation takes amortized
+  /// O(log n) time.
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    let hv = self.fullhash(&key);
+    match self.probe(&key,hv) {
+      Ok(h) => {
+        let vi = self.keys[h].as_ref().unwrap().1;
+        let oldkey = self.keys[h].replace((key,vi));
+        let oldval = core::mem::replace(&mut self.vals[vi].0, val);
+        self.adjust(vi);
+        oldkey.map(|(k,_)|(k,oldval))
+      },
+      Err(slot) => {
+        self.insert_new(key,val,hv,slot);
+        None
+      },
+    }//match
+  }//insert
+
+  /// Version of insert that does not replace an existing key.  Instead,
+  /// it returns false if an equivalent key already exists.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    let hv = self.fullhash(&key);
+    match self.probe(&key,hv) {
+      Ok(_) => false,
+      Err(slot) => { self.insert_new(key,val,hv,slot); true },
+    }
+  }//push
+
+  // insert key known to be absent, with full hash hv, at the slot returned
+  // by probe, unless the table must grow first
+  fn insert_new(&mut self, key:KT, val:VT, hv:u64, slot:(usize,usize,usize)) {
+    let (mut h0, mut h, mut hashes) = slot;
+    if (self.vals.len()+1) as f32 > self.maxload * (self.keys.len() as f32) {
+      self.rebuild(self.keys.len()*2);
+      if let Err(newslot) = self.probe(&key,hv) { (h0,h,hashes) = newslot; }
+    }
+    if hashes > self.maxhashes[h0] { self.maxhashes[h0] = hashes; }
+    let vi = self.vals.len();
+    self.keys[h] = Some((key,vi));
+    self.fullhashes[h] = hv;
+    self.vals.push((val,h));
+    self.swapup(vi);
+  }//insert_new
+
+  /// returns reference to value associated with key, if it exists.
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    self.find(key).and_then(|h|self.keys[h].as_ref())
+      .map(|(_,vi)|&self.vals[*vi].0)
+  }
+
+  /// determines if the given key exists in the structure.
+  pub fn contains_key(&self, key:&KT) -> bool {
+    self.find(key).is_some()
+  }
+
+  /// modifies the value associated with the key, if it exists, using
+  /// the provided closure, then adjusts its position in the heap.  Returns
+  /// false if the key was not found.  O(log n) plus the cost of the closure.
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    if let Some(vi) = self.find(key).and_then(|h|self.keys[h].as_ref()).map(|p|p.1) {
+      f(&mut self.vals[vi].0);
+      self.adjust(vi);
+      true
+    }
+    else {false}
+  }//modify
+
+  /// remove and return the key-value pair associated with the key.
+  /// O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    let h = self.find(key)?;
+    let (k,vi) = self.keys[h].take()?;
+    let last = self.vals.len()-1;
+    if vi != last { self.swap(vi,last); }
+    let (v,_) = self.vals.pop()?;
+    if vi < self.vals.len() { self.adjust(vi); }
+    Some((k,v))
+  }//remove
+
+  /// remove and return the highest-priority key-value pair. O(log n).
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    if self.vals.is_empty() { return None; }
+    let last = self.vals.len()-1;
+    self.swap(0,last);
+    let (v,ki) = self.vals.pop()?;
+    if !self.vals.is_empty() { self.swapdown(0); }
+    self.keys[ki].take().map(|(k,_)|(k,v))
+  }//pop
+
+  /// returns reference to highest-priority key-value pair without
+  /// removal.  This operation is O(1).
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    self.vals.first().and_then(|(v,ki)|
+      self.keys[*ki].as_ref().map(|(k,_)|(k,v)))
+  }
+
+  /// The number of key-value pairs stored in the structure
+  pub fn len(&self) -> usize { self.vals.len() }
+
+  /// returns true if the structure contains no entries
+  pub fn is_empty(&self) -> bool { self.vals.is_empty() }
+
+  /// The current capacity of the hash table.  The structure will grow
+  /// before the number of entries exceeds the capacity times the maximum
+  /// load factor.
+  pub fn capacity(&self) -> usize { self.keys.len() }
+
+  /// The load factor is the size divided by the capacity.
+  pub fn load_factor(&self) -> f32 {
+    (self.vals.len() as f32) / (self.keys.len() as f32)
+  }
+
+  /// returns true if the structure is a maxheap and false if minheap
+  pub fn is_maxheap(&self) -> bool { self.maxheap }
+
+  /// rehashes all keys into a table of the same capacity, which
+  /// discards any unused probe sequences left by removed keys. O(n).
+  pub fn refresh(&mut self) {
+    self.rebuild(self.keys.len());
+  }
+
+  /// returns a non-consuming iterator over all entries in no particular
+  /// order.
+  pub fn iter<'a>(&'a self) -> GHHIter<'a,KT,VT> {
+    GHHIter { ghh:self, index:0 }
+  }
+
+  /// returns an iterator that removes entries in order of priority.
+  pub fn priority_stream<'a>(&'a mut self) -> GHHPriorityStream<'a,KT,VT> {
+    GHHPriorityStream(self)
+  }
+}// main impl
+
+/// indexed get, unwraps
+impl<KT: Hash + Eq, VT: PartialOrd> core::ops::Index<&KT> for GrowableHashHeap<KT,VT>
+{
+    type Output = VT;
+    fn index(&self, index: &KT) -> &Self::Output {
+        self.get(index).expect("key not found")
+    }
+} //impl Index
+
+/// Iterator for the [GrowableHashHeap::iter] function
+pub struct GHHIter<'a,KT,VT>
+{
+  ghh : &'a GrowableHashHeap<KT,VT>,
+  index : usize,
+}
+impl<'a,KT: Hash + Eq, VT: PartialOrd> Iterator for GHHIter<'a,KT,VT> {
+  type Item = (&'a KT, &'a VT);
+  fn next(&mut self) -> Option<Self::Item> {
+    let (v,ki) = self.ghh.vals.get(self.index)?;
+    self.index += 1;
+    self.ghh.keys[*ki].as_ref().map(|(k,_)|(k,v))
+  }
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let n = self.ghh.len().saturating_sub(self.index);
+    (n, Some(n))
+  }
+}
+impl<'a,KT: Hash + Eq, VT: PartialOrd> ExactSizeIterator for GHHIter<'a,KT,VT> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd> core::iter::FusedIterator for GHHIter<'a,KT,VT> {}
+
+impl<'a, KT: Hash + Eq, VT: PartialOrd> IntoIterator for &'a GrowableHashHeap<KT,VT>
+{
+  type Item = (&'a KT, &'a VT);
+  type IntoIter = GHHIter<'a,KT,VT>;
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+/// Iterator for the [GrowableHashHeap::priority_stream] function
+pub struct GHHPriorityStream<'a,KT,VT>(&'a mut GrowableHashHeap<KT,VT>);
+impl<'a,KT: Hash + Eq, VT: PartialOrd> Iterator for GHHPriorityStream<'a,KT,VT>
+{
+  type Item = (KT,VT);
+  fn next(&mut self) -> Option<Self::Item> {
+    self.0.pop()
+  }
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.0.len(), Some(self.0.len()))
+  }
+}
+impl<'a,KT: Hash + Eq, VT: PartialOrd> ExactSizeIterator for GHHPriorityStream<'a,KT,VT> {}
+impl<'a,KT: Hash + Eq, VT: PartialOrd> core::iter::FusedIterator for GHHPriorityStream<'a,KT,VT> {}
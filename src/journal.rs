@@ -0,0 +1,209 @@
+//! A write-ahead journal for [HashHeap], enabled by the `journal` feature.
+//! A [JournaledHashHeap] appends a record of every mutating operation to
+//! a [Write] sink *before* applying it, so that [recover] can rebuild the
+//! structure after a crash by replaying the journal.  Together with
+//! periodic [checkpoints](JournaledHashHeap::checkpoint), which start a new
+//! journal with a snapshot of the current entries, this bounds both the
+//! data lost in a crash and the length of the journal.
+//!
+//! The journal is text, one record per line, with keys and values written
+//! with their [Display] implementations, read back with [FromStr] and
+//! quoted as in the [csvio](crate::csvio) module:
+//! `I,key,value` records an insertion or a change of value, `R,key` a
+//! removal, including by pop, and `C` a clear.  Lines starting with `#`
+//! are comments.  A last line without a line break, which is what a crash
+//! in the middle of a write leaves behind, is ignored.  Records are
+//! written with a single call to `write_all`; whether they reach the disk
+//! depends on the sink, which the user should flush or sync as needed.
+
+use crate::csvio::{quote, split_record};
+use crate::HashHeap;
+use core::fmt::Display;
+use core::hash::Hash;
+use core::str::FromStr;
+use std::io::{self, BufRead, Write};
+
+fn invalid(lineno:usize, msg:&str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, format!("journal line {}: {}", lineno, msg))
+}
+
+/// A HashHeap whose mutations are recorded in a journal: see the
+/// [module documentation](crate::journal).
+/// ```
+/// # use hashheap::*;
+///   let mut jh = JournaledHashHeap::new(HashHeap::<String,u32>::new_minheap(), Vec::new()).unwrap();
+///   jh.insert("a".to_string(), 5).unwrap();
+///   jh.insert("b".to_string(), 3).unwrap();
+///   jh.modify(&"a".to_string(), |v| *v = 1).unwrap();
+///   jh.pop().unwrap();
+///   let (hh, log) = jh.into_parts();
+///   let recovered = recover::<String,u32,_>(&log[..], false).unwrap();
+///   assert_eq!(recovered.len(), 1);
+///   assert_eq!(recovered.peek(), hh.peek());
+/// ```
+#[derive(Debug)]
+pub struct JournaledHashHeap<KT,VT,W:Write>
+{
+  heap : HashHeap<KT,VT>,
+  sink : W,
+}
+impl<KT:Hash+Eq+Display, VT:PartialOrd+Display, W:Write> JournaledHashHeap<KT,VT,W> {
+
+  /// Starts journaling the given HashHeap to the sink, which begins with a
+  /// snapshot of the existing entries.
+  pub fn new(heap:HashHeap<KT,VT>, mut sink:W) -> io::Result<Self> {
+    Self::write_snapshot(&heap, &mut sink)?;
+    Ok(JournaledHashHeap { heap, sink })
+  }
+
+  fn write_snapshot(heap:&HashHeap<KT,VT>, sink:&mut W) -> io::Result<()> {
+    let polarity = if heap.is_max_hashheap() {"maxheap"} else {"minheap"};
+    let mut text = format!("# hashheap journal {}\nC\n", polarity);
+    for (k,v) in heap.iter() {
+      text.push_str(&Self::insert_record(k,v));
+    }
+    sink.write_all(text.as_bytes())
+  }
+
+  fn insert_record(key:&KT, val:&VT) -> String {
+    format!("I,{},{}\n", quote(key.to_string(),','), quote(val.to_string(),','))
+  }
+
+  fn remove_record(key:&KT) -> String {
+    format!("R,{}\n", quote(key.to_string(),','))
+  }
+
+  /// Starts a new journal in the given sink with a snapshot of the current
+  /// entries, and returns the old sink, which is no longer needed for
+  /// recovery once the new one has been made durable.
+  pub fn checkpoint(&mut self, mut sink:W) -> io::Result<W> {
+    Self::write_snapshot(&self.heap, &mut sink)?;
+    Ok(core::mem::replace(&mut self.sink, sink))
+  }
+
+  /// journals and performs [HashHeap::insert]
+  pub fn insert(&mut self, key:KT, val:VT) -> io::Result<Option<(KT,VT)>> {
+    self.sink.write_all(Self::insert_record(&key,&val).as_bytes())?;
+    Ok(self.heap.insert(key,val))
+  }
+
+  /// Journals and performs [HashHeap::push], which does not replace an
+  /// existing key.  Nothing is journaled if the key exists.
+  pub fn push(&mut self, key:KT, val:VT) -> io::Result<bool> {
+    if self.heap.contains_key(&key) { return Ok(false); }
+    self.sink.write_all(Self::insert_record(&key,&val).as_bytes())?;
+    Ok(self.heap.push(key,val))
+  }
+
+  /// Journals and performs a modification of the value associated with
+  /// the key.  The closure is applied to a copy of the value, so that the
+  /// new value can be journaled before the structure is changed.  Returns
+  /// false if the key was not found.
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> io::Result<bool>
+  where VT:Clone
+  {
+    let mut val = match self.heap.get(key) {
+      Some(v) => v.clone(),
+      None => { return Ok(false); },
+    };
+    f(&mut val);
+    self.sink.write_all(Self::insert_record(key,&val).as_bytes())?;
+    Ok(self.heap.modify(key, |v| *v = val).is_some())
+  }
+
+  /// journals and performs [HashHeap::remove]
+  pub fn remove(&mut self, key:&KT) -> io::Result<Option<(KT,VT)>> {
+    if !self.heap.contains_key(key) { return Ok(None); }
+    self.sink.write_all(Self::remove_record(key).as_bytes())?;
+    Ok(self.heap.remove(key))
+  }
+
+  /// Journals and performs [HashHeap::pop].  The removal of the popped key
+  /// is journaled, so that recovery does not depend on how ties between
+  /// values are broken.
+  pub fn pop(&mut self) -> io::Result<Option<(KT,VT)>> {
+    match self.heap.peek() {
+      Some((k,_)) => { self.sink.write_all(Self::remove_record(k).as_bytes())?; },
+      None => { return Ok(None); },
+    }
+    Ok(self.heap.pop())
+  }
+
+  /// journals and performs [HashHeap::clear]
+  pub fn clear(&mut self) -> io::Result<()> {
+    self.sink.write_all(b"C\n")?;
+    self.heap.clear();
+    Ok(())
+  }
+
+  /// flushes the sink
+  pub fn flush(&mut self) -> io::Result<()> { self.sink.flush() }
+
+  /// returns the value associated with the key
+  pub fn get(&self, key:&KT) -> Option<&VT> { self.heap.get(key) }
+
+  /// returns the entry with the highest priority
+  pub fn peek(&self) -> Option<(&KT,&VT)> { self.heap.peek() }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// the underlying HashHeap, for operations that don't change it
+  pub fn hashheap(&self) -> &HashHeap<KT,VT> { &self.heap }
+
+  /// a reference to the sink
+  pub fn sink(&self) -> &W { &self.sink }
+
+  /// stops journaling, returning the HashHeap and the sink
+  pub fn into_parts(self) -> (HashHeap<KT,VT>, W) { (self.heap, self.sink) }
+}//impl JournaledHashHeap
+
+/// Rebuilds a HashHeap by replaying a journal written by a
+/// [JournaledHashHeap].  As with [HashHeap::from_csv], whether the result
+/// is a maxheap is given by the `maxheap` argument.  An incomplete last
+/// line is ignored; any other malformed record is an error.
+/// ```
+/// # use hashheap::*;
+///   let log = "# hashheap journal minheap\nC\nI,a,5\nI,b,3\nR,b\nI,c,";
+///   let hh = recover::<String,u32,_>(log.as_bytes(), false).unwrap();
+///   assert_eq!(hh.len(), 1);
+///   assert_eq!(hh.get(&"a".to_string()), Some(&5));
+/// ```
+pub fn recover<KT,VT,R>(mut reader:R, maxheap:bool) -> io::Result<HashHeap<KT,VT>>
+where KT:Hash+Eq+FromStr, VT:PartialOrd+FromStr, R:BufRead
+{
+  let mut hh = HashHeap::with_capacity(0,maxheap);
+  let mut line = String::new();
+  let mut lineno = 0;
+  loop {
+    let continued = !line.is_empty();  // inside a quoted field
+    if reader.read_line(&mut line)? == 0 { break; }
+    if !line.ends_with('\n') { break; }   // torn write
+    lineno += 1;
+    // comments are skipped before splitting, so that quotes in them
+    // aren't taken to open a field
+    if !continued && (line.starts_with('#') || line.trim().is_empty()) {
+      line.clear();
+      continue;
+    }
+    let fields = match split_record(&line[..line.len()-1], ',') {
+      Some(f) => f,
+      None => { continue; },  // quoted field continues on next line
+    };
+    line.clear();
+    let key = |i:usize| fields[i].parse::<KT>().map_err(|_|invalid(lineno,"invalid key"));
+    match (fields[0].as_str(), fields.len()) {
+      ("I",3) => {
+        let v = fields[2].parse::<VT>().map_err(|_|invalid(lineno,"invalid value"))?;
+        hh.insert(key(1)?, v);
+      },
+      ("R",2) => { hh.remove(&key(1)?); },
+      ("C",1) => { hh.clear(); },
+      _ => { return Err(invalid(lineno,"unknown record")); },
+    }
+  }//loop
+  Ok(hh)
+}//recover
@@ -0,0 +1,96 @@
+//! This module contains [KeyCmpHashHeap], a [HashHeap] whose entries are
+//! ordered by a comparator that sees the keys as well as the values.  This
+//! allows ties between equal values to be broken by key, for example by
+//! job id, and priorities that are computed from both the key and the
+//! value.  The comparator of a HashHeap only sees values, and the heap
+//! would have to look up the key of every value it compares.  Instead,
+//! each value of a KeyCmpHashHeap is stored with a clone of its key, so
+//! keys should be cheap to clone, such as integers or `Rc<str>`.
+
+use crate::HashHeap;
+use core::cmp::Ordering;
+use core::hash::Hash;
+
+/// A HashHeap ordered by keys and values: see the
+/// [module documentation](crate::keycmphashheap).  The comparator returns
+/// the [Ordering] of two entries by priority, and the greatest entry is at
+/// the top.
+/// ```
+/// # use hashheap::*;
+///   // highest priority first, then lowest job id
+///   let mut jobs = KeyCmpHashHeap::<u32,u8>::new(|k1,p1,k2,p2| {
+///     p1.cmp(p2).then(k2.cmp(k1))
+///   });
+///   for (id,p) in [(7,1),(3,2),(5,1),(1,1)] { jobs.insert(id,p); }
+///   let order:Vec<_> = std::iter::from_fn(|| jobs.pop()).map(|p|p.0).collect();
+///   assert_eq!(order, vec![3,1,5,7]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KeyCmpHashHeap<KT,VT>
+{
+  heap : HashHeap<KT,(KT,VT)>,
+}
+impl<KT:Hash+Eq+Clone, VT> KeyCmpHashHeap<KT,VT> {
+
+  /// creates an empty structure ordered by the given comparator, which can
+  /// also be a closure
+  pub fn new<F>(cmp:F) -> Self
+  where F: Fn(&KT,&VT,&KT,&VT) -> Ordering + Send + Sync + 'static
+  {
+    let lessthan = move |a:&(KT,VT), b:&(KT,VT)| cmp(&a.0,&a.1,&b.0,&b.1) == Ordering::Less;
+    KeyCmpHashHeap { heap : HashHeap::with_comparator_closure(lessthan) }
+  }
+
+  /// Add or change a key-value pair, returning the replaced pair, if it
+  /// exists.  O(log n)
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    let kv = (key.clone(), val);
+    self.heap.insert(key,kv).map(|(k,(_,v))|(k,v))
+  }
+
+  /// Version of insert that does not replace an existing key, returning
+  /// false instead.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    if self.heap.contains_key(&key) { return false; }
+    let kv = (key.clone(), val);
+    self.heap.push(key,kv)
+  }
+
+  /// returns the value associated with the key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> { self.heap.get(key).map(|kv|&kv.1) }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// applies the closure to the value associated with the key and
+  /// repositions it.  Returns false if the key was not found.  O(log n)
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    self.heap.modify(key, |kv| f(&mut kv.1)).is_some()
+  }
+
+  /// removes and returns the pair with the given key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.heap.remove(key).map(|(k,(_,v))|(k,v))
+  }
+
+  /// returns the pair with the highest priority.  O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    self.heap.peek().map(|(k,kv)|(k,&kv.1))
+  }
+
+  /// removes and returns the pair with the highest priority.  O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    self.heap.pop().map(|(k,(_,v))|(k,v))
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.is_empty() }
+
+  /// iterates over the entries in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ {
+    self.heap.iter().map(|(k,kv)|(k,&kv.1))
+  }
+}//impl KeyCmpHashHeap
@@ -0,0 +1,81 @@
+// The key-index layer of a HashHeap: a map from hash indices to the
+// locations (ki,vi) of keys and values.  By default this is a std HashMap,
+// which hashes the hash index a second time.  With the `hashbrown` feature
+// it's a hashbrown::HashTable that stores (hash index, (ki,vi)) entries
+// directly and uses the hash index itself, after a single multiplication
+// to spread its bits, as the hash.  Both provide the same methods.
+
+
+#[cfg(not(feature = "hashbrown"))]
+pub(crate) type KMap = std::collections::HashMap<usize,(usize,usize)>;
+
+#[cfg(feature = "hashbrown")]
+pub(crate) use table::KMap;
+
+#[cfg(feature = "hashbrown")]
+mod table {
+  use hashbrown::HashTable;
+
+  #[derive(Clone, Debug, Default)]
+  pub(crate) struct KMap {
+    table : HashTable<(usize,(usize,usize))>,
+  }
+
+  // Fibonacci hashing: user-supplied hash functions may produce small
+  // consecutive indices, whose high bits must be spread for the SIMD tags
+  #[inline]
+  fn spread(h:usize) -> u64 { (h as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) }
+
+  impl KMap {
+    pub(crate) fn with_capacity(cap:usize) -> Self {
+      KMap { table : HashTable::with_capacity(cap) }
+    }
+
+    pub(crate) fn get(&self, h:&usize) -> Option<&(usize,usize)> {
+      self.table.find(spread(*h), |e| e.0 == *h).map(|e| &e.1)
+    }
+
+    pub(crate) fn get_mut(&mut self, h:&usize) -> Option<&mut (usize,usize)> {
+      self.table.find_mut(spread(*h), |e| e.0 == *h).map(|e| &mut e.1)
+    }
+
+    pub(crate) fn contains_key(&self, h:&usize) -> bool { self.get(h).is_some() }
+
+    pub(crate) fn insert(&mut self, h:usize, loc:(usize,usize)) -> Option<(usize,usize)> {
+      match self.get_mut(&h) {
+        Some(old) => Some(core::mem::replace(old, loc)),
+        None => {
+          self.table.insert_unique(spread(h), (h,loc), |e| spread(e.0));
+          None
+        },
+      }
+    }
+
+    pub(crate) fn remove(&mut self, h:&usize) -> Option<(usize,usize)> {
+      let entry = self.table.find_entry(spread(*h), |e| e.0 == *h).ok()?;
+      let ((_,loc),_) = entry.remove();
+      Some(loc)
+    }
+
+    pub(crate) fn reserve(&mut self, additional:usize) {
+      self.table.reserve(additional, |e| spread(e.0));
+    }
+
+    pub(crate) fn clear(&mut self) { self.table.clear(); }
+
+    pub(crate) fn len(&self) -> usize { self.table.len() }
+
+    pub(crate) fn capacity(&self) -> usize { self.table.capacity() }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+      self.table.shrink_to_fit(|e| spread(e.0));
+    }
+  }//impl KMap
+
+  impl core::ops::Index<&usize> for KMap {
+    type Output = (usize,usize);
+    fn index(&self, h:&usize) -> &(usize,usize) {
+      self.get(h).expect("hash index not found")
+    }
+  }
+}//mod table
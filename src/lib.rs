@@ -5,7 +5,7 @@
 //!
 
 //! In a HashHeap, however, values are paired with keys. The keys are
-//! hashable (`:Hash+Eq`) and the values are comparable (`:Ord`).
+//! hashable (`:Hash+Eq`) and the values are comparable (`:PartialOrd`).
 //! Conceptually, an internal HashMap maps keys to *indices* of where
 //! values are stored inside an internal vector. Heap operations that
 //! require values to be swapped must keep the hashmap consistent.
@@ -29,7 +29,8 @@
 //! Two versions of the data structure are provided.
 //! **Their documentation are found under structs
 //! [HashHeap] and [ConstHashHeap].**  The [consthashheap] module
-//! was added in Version 0.2.
+//! was added in Version 0.2.  A third version, [GrowableHashHeap], uses
+//! the same internal layout as [ConstHashHeap] but grows automatically.
 //!
 //! Because the mutation of values will require them to be repositioned in
 //! the heap, certain expected methods are not available, including `get_mut`
@@ -37,6 +38,30 @@
 //! allows the mutation of values with a closure, and will automatically
 //! adjust their positions afterwards.
 //!
+//! Since values only need to be `PartialOrd`, floating-point priorities
+//! such as `f64` costs can be used directly.  However, a value that is
+//! not comparable to other values, such as NaN, has no place in a heap:
+//! it is never moved up or down, and it may hide values of higher
+//! priority beneath it, so that [HashHeap::peek] and [HashHeap::pop] no
+//! longer return the highest priority.  No operation panics because of
+//! NaN, and the hash side of the structure is unaffected, so the entry can
+//! still be found, modified or removed by its key.  If NaN values can
+//! occur, a total order can be supplied with [HashHeap::with_comparator]:
+//! ```
+//!    # use hashheap::*;
+//!    let mut costs = HashHeap::<&str,f64>::with_comparator(|a,b| b.total_cmp(a).is_lt());
+//!    costs.insert("a", 2.5);
+//!    costs.insert("b", f64::NAN);  // largest in the total order
+//!    costs.insert("c", 0.5);
+//!    assert_eq!(costs.pop(), Some(("c",0.5)));
+//!    assert_eq!(costs.pop(), Some(("a",2.5)));
+//! ```
+//!
+//! With the optional `hashbrown` feature, the internal map from hash
+//! indices to key and value locations is a `hashbrown::HashTable` that
+//! uses the hash indices directly, instead of a std HashMap that hashes
+//! them again.  The public API is the same either way.
+//!
 //! Concerning the time complexity of operations, we consider looking up a
 //! hash table to be an O(1) operation, although theoretically it can be
 //! worst-case O(n) with concocted examples.  They rarely occur in practice.
@@ -103,18 +128,83 @@ So there are n-(n+1)/2 = non-leaves, not same as (n-1)/2, because of remainder
 #![allow(unused_assignments)]
 #![allow(unused_doc_comments)]
 #![allow(unused_imports)]
+use std::borrow::Borrow;
 use std::cell::{Ref, RefCell, RefMut};
 use std::cmp::Ord;
 use std::collections::hash_map::RandomState;
 use std::collections::{HashMap, HashSet};
 use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Arc;
 
+mod kmap;
+use kmap::KMap;
 pub mod consthashheap;
 pub use consthashheap::*;
+pub mod growablehashheap;
+pub use growablehashheap::*;
+pub mod prioritycache;
+pub use prioritycache::*;
+pub mod retryqueue;
+pub use retryqueue::*;
+pub mod rankedhashheap;
+pub use rankedhashheap::*;
+pub mod weakhashheap;
+pub use weakhashheap::*;
+pub mod dagscheduler;
+pub use dagscheduler::*;
+pub mod concurrent;
+pub use concurrent::*;
+pub mod deferredhashheap;
+pub use deferredhashheap::*;
+pub mod withmeta;
+pub use withmeta::*;
+pub mod timedhashheap;
+pub use timedhashheap::*;
+pub mod watchhashheap;
+pub use watchhashheap::*;
+pub mod segmentedhashheap;
+pub use segmentedhashheap::*;
+pub mod priorityset;
+pub use priorityset::*;
+pub mod stablehashheap;
+pub use stablehashheap::*;
+pub mod keycmphashheap;
+pub use keycmphashheap::*;
+pub mod typedhashheap;
+pub use typedhashheap::*;
+pub mod minmaxhashheap;
+pub use minmaxhashheap::*;
+pub mod boundedhashheap;
+pub use boundedhashheap::*;
+pub mod bulkloader;
+pub use bulkloader::*;
+pub mod prioritymap;
+pub use prioritymap::*;
+pub mod compat;
+#[cfg(feature = "csv")]
+pub mod csvio;
+#[cfg(feature = "journal")]
+pub mod journal;
+#[cfg(feature = "journal")]
+pub use journal::*;
+#[cfg(feature = "valindex")]
+pub mod valueindex;
+#[cfg(feature = "valindex")]
+pub use valueindex::*;
+#[cfg(feature = "dot")]
+pub mod dot;
+#[cfg(feature = "test-utils")]
+pub mod testutils;
+#[cfg(feature = "test-suite")]
+pub mod conformance;
+#[cfg(feature = "petgraph")]
+pub mod graphsearch;
+#[cfg(feature = "petgraph")]
+pub use graphsearch::*;
 
 const DEFAULTCAP: usize = 16;
 
-//// independent functions for heap indices:
+// independent functions for heap indices:
 fn left(i: usize) -> usize {
     2 * i + 1
 }
@@ -129,12 +219,230 @@ fn parent(i: usize) -> usize {
     }
 }
 
-fn derive_hash<T: Hash + Eq>(rs: &RandomState, key: &T) -> usize {
-    let mut bs = rs.build_hasher();
-    key.hash(&mut bs);
-    bs.finish() as usize
+// A user-supplied hash or rehash function: either a plain fn pointer,
+// called directly, or a closure shared between clones of a HashHeap
+pub(crate) enum UserFn<P, C: ?Sized> {
+    Ptr(P),
+    Closure(Arc<C>),
+}
+impl<P: Copy, C: ?Sized> Clone for UserFn<P, C> {
+    fn clone(&self) -> Self {
+        match self {
+            UserFn::Ptr(f) => UserFn::Ptr(*f),
+            UserFn::Closure(f) => UserFn::Closure(Arc::clone(f)),
+        }
+    }
+}
+impl<P, C: ?Sized> core::fmt::Debug for UserFn<P, C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            UserFn::Ptr(_) => f.write_str("fn"),
+            UserFn::Closure(_) => f.write_str("closure"),
+        }
+    }
+}
+type HashFn<KT> = UserFn<fn(&KT) -> usize, dyn Fn(&KT) -> usize + Send + Sync>;
+type RehashFn = UserFn<fn(usize, usize) -> usize, dyn Fn(usize, usize) -> usize + Send + Sync>;
+pub(crate) type CmpFn<VT> = UserFn<fn(&VT, &VT) -> bool, dyn Fn(&VT, &VT) -> bool + Send + Sync>;
+impl<VT> CmpFn<VT> {
+    #[inline]
+    pub(crate) fn call(&self, a: &VT, b: &VT) -> bool {
+        match self {
+            UserFn::Ptr(f) => f(a, b),
+            UserFn::Closure(f) => f(a, b),
+        }
+    }
+}
+
+fn derive_hash<T: Hash + ?Sized>(rs: &HashState, key: &T) -> usize {
+    rs.hash_one(key) as usize
 } // used by autohash
 
+// The state of the hasher of a HashHeap: either a RandomState, or a seed
+// that makes hash indices, and so the probe sequences, reproducible.
+// Seeded hashers are SipHash with fixed keys, whose results are only
+// guaranteed to be the same for the same version of Rust.
+#[derive(Clone, Debug)]
+enum HashState {
+    Random(RandomState),
+    Seeded(u64),
+}
+impl BuildHasher for HashState {
+    type Hasher = std::collections::hash_map::DefaultHasher;
+    fn build_hasher(&self) -> Self::Hasher {
+        match self {
+            HashState::Random(rs) => rs.build_hasher(),
+            HashState::Seeded(seed) => {
+                let mut h = std::collections::hash_map::DefaultHasher::new();
+                h.write_u64(*seed);
+                h
+            }
+        }
+    }
+} //impl HashState
+
+/// Sorts a vector of key-value pairs in order of priority using heapsort,
+/// without building the hash side of a [HashHeap].  If `maxheap` is
+/// true, the pairs are returned in decreasing order of value, otherwise in
+/// increasing order, which is the order in which they would be popped from
+/// a HashHeap.  Keys need not be hashable or distinct.  The sort is not
+/// stable and runs in O(n log n) time without additional allocation.
+/// ```
+/// # use hashheap::*;
+///   let sorted = sort_pairs_by_priority(vec![("a",2),("b",3),("c",1)], true);
+///   assert_eq!(sorted, vec![("b",3),("a",2),("c",1)]);
+/// ```
+pub fn sort_pairs_by_priority<KT, VT: PartialOrd>(
+    mut pairs: Vec<(KT, VT)>,
+    maxheap: bool,
+) -> Vec<(KT, VT)> {
+    // heap of lowest priority at top, so that the highest end up in front
+    let lessthan: fn(&VT, &VT) -> bool = if maxheap { |a, b| b < a } else { |a, b| a < b };
+    let n = pairs.len();
+    let mut i = n - n.div_ceil(2);
+    while i > 0 {
+        siftdown_pairs(&mut pairs, i - 1, n, lessthan);
+        i -= 1;
+    }
+    let mut end = n;
+    while end > 1 {
+        end -= 1;
+        pairs.swap(0, end);
+        siftdown_pairs(&mut pairs, 0, end, lessthan);
+    }
+    pairs
+} //sort_pairs_by_priority
+
+fn siftdown_pairs<KT, VT>(
+    v: &mut [(KT, VT)],
+    mut i: usize,
+    size: usize,
+    lessthan: fn(&VT, &VT) -> bool,
+) {
+    loop {
+        let (li, ri) = (left(i), right(i));
+        let mut sc = i;
+        if li < size && lessthan(&v[sc].1, &v[li].1) {
+            sc = li;
+        }
+        if ri < size && lessthan(&v[sc].1, &v[ri].1) {
+            sc = ri;
+        }
+        if sc == i {
+            break;
+        }
+        v.swap(i, sc);
+        i = sc;
+    }
+} //siftdown_pairs
+
+/// Statistics about the shape of the binary heap inside a hashheap,
+/// returned by [HashHeap::shape_stats] and [ConstHashHeap::shape_stats].
+/// Because a binary heap is always a complete binary tree, its shape
+/// depends only on the number of entries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShapeStats {
+    /// number of entries
+    pub len: usize,
+    /// number of levels of the tree (0 for an empty heap)
+    pub depth: usize,
+    /// number of entries without children
+    pub leaves: usize,
+    /// number of entries on each level, starting from the root
+    pub levels: Vec<usize>,
+}
+impl ShapeStats {
+    pub(crate) fn for_size(n: usize) -> ShapeStats {
+        let mut levels = Vec::new();
+        let (mut start, mut width) = (0, 1);
+        while start < n {
+            levels.push(width.min(n - start));
+            start += width;
+            width *= 2;
+        }
+        ShapeStats {
+            len: n,
+            depth: levels.len(),
+            leaves: n.div_ceil(2),
+            levels,
+        }
+    }
+} //impl ShapeStats
+
+/// Statistics about the probe sequences of a [HashHeap], returned by
+/// [HashHeap::probe_stats].  A key collides each time its probe sequence
+/// passes an index occupied by another key or a tombstone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProbeStats {
+    /// number of keys
+    pub keys: usize,
+    /// total number of collisions on the way to every key
+    pub collisions: usize,
+    /// largest number of collisions on the way to a single key
+    pub max_probe: usize,
+    /// number of tombstones left by removed keys
+    pub tombstones: usize,
+}
+impl ProbeStats {
+    /// the average number of hash indices probed to find a key, which is
+    /// 1.0 when there are no collisions, or 0.0 if there are no keys
+    pub fn average_probe(&self) -> f32 {
+        match self.keys {
+            0 => 0.0,
+            n => 1.0 + (self.collisions as f32) / (n as f32),
+        }
+    }
+} //impl ProbeStats
+
+/// The result of [HashHeap::modify]: whether the modified value moved up
+/// (towards the top) or down in the heap, or stayed where it was.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Moved {
+    Up,
+    Down,
+    None,
+}
+
+/// What [HashHeap::put] does when the key of the new pair already exists:
+/// see [HashHeap::set_insert_policy].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum InsertPolicy {
+    /// the new pair replaces the existing one, as with [HashHeap::insert]
+    #[default]
+    Replace,
+    /// the existing pair is kept, as with [HashHeap::push]
+    KeepExisting,
+    /// the pair with the higher priority is kept; on a tie the existing
+    /// pair is kept
+    KeepBest,
+    /// the new pair is returned as an error
+    Error,
+}
+
+/// Error returned by [HashHeap::try_insert] when the key already exists,
+/// giving back the key and value that were not inserted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OccupiedError<KT, VT> {
+    /// the key that was not inserted
+    pub key: KT,
+    /// the value that was not inserted
+    pub value: VT,
+}
+impl<KT: core::fmt::Debug, VT: core::fmt::Debug> core::fmt::Display for OccupiedError<KT, VT> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "key {:?} already exists, value {:?} not inserted",
+            self.key, self.value
+        )
+    }
+}
+impl<KT: core::fmt::Debug, VT: core::fmt::Debug> std::error::Error for OccupiedError<KT, VT> {}
+
+// depth of heap index i, with the root at depth 0
+pub(crate) fn heap_depth(i: usize) -> usize {
+    (usize::BITS - (i + 1).leading_zeros() - 1) as usize
+}
 
 //#[cfg(feature="serde")]
 //use serde::{Serialize, Deserialize};
@@ -142,38 +450,96 @@ fn derive_hash<T: Hash + Eq>(rs: &RandomState, key: &T) -> usize {
 #[derive(Clone, Debug)]
 pub struct HashHeap<KT, VT> {
     keys: Vec<Option<KT>>,  // None means once occupied
+    khash: Vec<usize>,      // original hash index of each key
     vals: Vec<(VT, usize)>, // with inverse hash index (for map)
-    userhash: Option<fn(&KT) -> usize>,
-    rehash: fn(usize, usize) -> usize, // hashi,collisions -> newhashi
-    kmap: HashMap<usize, (usize, usize)>, // hashindex to (ki,vi)
-    lessthan: fn(&VT, &VT) -> bool,
-    autostate: RandomState,
+    userhash: Option<HashFn<KT>>,
+    rehash: Option<RehashFn>, // hashi,collisions -> newhashi; None = linear
+    kmap: KMap, // hashindex to (ki,vi)
+    passes: HashMap<usize, usize>, // hashindex to number of keys probing past it
+    freekeys: Vec<usize>, // unused indices of keys
+    maintcursor: usize, // next heap index visited by maintain
+    lessthan: CmpFn<VT>,
+    autostate: HashState,
     minmax: bool, // record if it's min or max heap
+    policy: InsertPolicy, // used by put
 }
 impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
     /// creates a HashHeap with given capacity.  If the capacity is less than 1,
     /// it defaults to 16.  If the second argument is true, a maxheap is
     /// created; otherwise a minheap is created.
-    pub fn with_capacity(mut cap: usize, maxheap: bool) -> HashHeap<KT, VT> {
-        if cap < 1 {
-            cap = DEFAULTCAP;
-        }
-        let mut hh = HashHeap {
-            keys: Vec::with_capacity(cap),
-            vals: Vec::with_capacity(cap),
-            kmap: HashMap::with_capacity(cap),
-            userhash: None,
-            rehash: |h, c| h + c,
-            lessthan: |a, b| a < b,
-            autostate: RandomState::new(),
-            minmax: maxheap,
-        };
-        if !maxheap {
-            hh.lessthan = |a, b| b < a;
+    pub fn with_capacity(cap: usize, maxheap: bool) -> HashHeap<KT, VT> {
+        if maxheap {
+            Self::with_cmp(cap, true, UserFn::Ptr(|a, b| a < b))
+        } else {
+            Self::with_cmp(cap, false, UserFn::Ptr(|a, b| b < a))
         }
-        hh
     } //with_capacity
 
+    /// Converts the HashHeap into a min-hashheap ordered by [PartialOrd],
+    /// replacing any custom comparison function, and rebuilds the heap in
+    /// O(n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh:HashHeap<u32,u32> = (0..10).map(|i|(i,i)).collect();
+    ///   hh = hh.into_maxheap();
+    ///   assert!(hh.is_max_hashheap());
+    ///   assert_eq!(hh.pop(), Some((9,9)));
+    ///   hh = hh.into_minheap();
+    ///   assert_eq!(hh.pop(), Some((0,0)));
+    /// ```
+    pub fn into_minheap(mut self) -> HashHeap<KT, VT> {
+        self.minmax = false;
+        self.set_cmp_rebuild(|a, b| b < a);
+        self
+    }
+
+    /// Converts the HashHeap into a max-hashheap ordered by [PartialOrd],
+    /// replacing any custom comparison function, and rebuilds the heap in
+    /// O(n) time.
+    pub fn into_maxheap(mut self) -> HashHeap<KT, VT> {
+        self.minmax = true;
+        self.set_cmp_rebuild(|a, b| a < b);
+        self
+    }
+
+    /// Creates an empty HashHeap whose keys are hashed with the given
+    /// [RandomState] instead of a new one.  Structures created with clones
+    /// of the same state place the same keys at the same hash indices,
+    /// until they are [cleared](HashHeap::clear).  If the second argument
+    /// is true, a maxheap is created; otherwise a minheap is created.
+    pub fn with_state(state: RandomState, maxheap: bool) -> HashHeap<KT, VT> {
+        let mut hh = Self::with_capacity(0, maxheap);
+        hh.autostate = HashState::Random(state);
+        hh
+    }
+
+    /// Creates an empty HashHeap whose keys are hashed deterministically
+    /// from the given seed, so that hash indices and probe sequences are
+    /// the same in every run of a program, as needed to replay simulations
+    /// or compare with golden output.  The seed is kept by
+    /// [clear](HashHeap::clear).  Hash indices are only guaranteed to be
+    /// reproducible with the same version of Rust, and keys chosen by an
+    /// adversary who knows the seed can cause many collisions.
+    /// ```
+    /// # use hashheap::*;
+    ///   let build = || {
+    ///     let mut hh = HashHeap::<&str,u32>::with_seed(42, false);
+    ///     for (i,k) in ["x","y","z","w"].into_iter().enumerate() { hh.insert(k, i as u32 % 2); }
+    ///     hh
+    ///   };
+    ///   let (mut a, b) = (build(), build());
+    ///   assert_eq!(a.probe_stats(), b.probe_stats());
+    ///   assert!(a.iter().eq(b.iter()));   // same layout
+    ///   a.clear();
+    ///   a.insert("x", 0);
+    ///   assert_eq!(a.len(), 1);
+    /// ```
+    pub fn with_seed(seed: u64, maxheap: bool) -> HashHeap<KT, VT> {
+        let mut hh = Self::with_capacity(0, maxheap);
+        hh.autostate = HashState::Seeded(seed);
+        hh
+    }
+
     /// convenient way to create an empty min-hashheap with default capacity 16
     pub fn new_minheap() -> HashHeap<KT, VT> {
         Self::with_capacity(0, false)
@@ -193,15 +559,207 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
         hh
     } //from_pairs
 
+    /// Determines if the given value exists in the table.  This operation
+    /// **runs in O(n) time**.
+    pub fn contains_val(&self, val: &VT) -> bool {
+        // O(n)
+        self.valsearch(0, val)
+    }
+    fn valsearch(&self, root: usize, val: &VT) -> bool {
+        if root >= self.vals.len() {
+            false
+        } else if &self.vals[root].0 == val {
+            true
+        } else if self.lessthan.call(&self.vals[root].0, val) {
+            false
+        } else {
+            self.valsearch(left(root), val) || self.valsearch(right(root), val)
+        }
+    }
+
+    /// returns an iterator over all key-value pairs whose values are equal
+    /// to the given value, in no particular order.  The search prunes every
+    /// subtree of the heap whose root has lower priority than the value,
+    /// so it only visits entries with priority at least as high as the
+    /// given value (and their immediate children).
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut jobs = HashHeap::<&str,u32>::new_minheap();
+    ///   jobs.insert("a",0); jobs.insert("b",2); jobs.insert("c",0);
+    ///   jobs.insert("d",1);
+    ///   let mut zeros:Vec<_> = jobs.equal_range(&0).map(|(k,_)|*k).collect();
+    ///   zeros.sort();
+    ///   assert_eq!(zeros, vec!["a","c"]);
+    /// ```
+    pub fn equal_range<'a>(&'a self, val: &'a VT) -> EqualRange<'a, KT, VT> {
+        EqualRange {
+            hh: self,
+            val,
+            stack: if self.vals.is_empty() { vec![] } else { vec![0] },
+        }
+    } //equal_range
+
+    /// returns an iterator over all key-value pairs whose values lie in
+    /// the given range, in no particular order.  Like
+    /// [equal_range](Self::equal_range), the search prunes every subtree
+    /// whose root lies outside of the range and has lower priority than
+    /// the bound it lies beyond, since none of its descendants can be in
+    /// the range.  For example, the entries of a min-hashheap whose values
+    /// are below a bound are found without visiting the rest of the heap.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut jobs = HashHeap::<&str,u32>::new_minheap();
+    ///   for (j,deadline) in [("a",40),("b",10),("c",25),("d",90),("e",5)] {
+    ///     jobs.insert(j,deadline);
+    ///   }
+    ///   let mut due:Vec<_> = jobs.values_in_range(..30).map(|(k,_)|*k).collect();
+    ///   due.sort();
+    ///   assert_eq!(due, vec!["b","c","e"]);
+    ///   assert_eq!(jobs.values_in_range(20..=40).count(), 2);
+    ///   assert_eq!(jobs.len(), 5);
+    /// ```
+    pub fn values_in_range<R: core::ops::RangeBounds<VT>>(
+        &self,
+        range: R,
+    ) -> ValuesInRange<'_, KT, VT, R> {
+        ValuesInRange {
+            hh: self,
+            range,
+            stack: if self.vals.is_empty() { vec![] } else { vec![0] },
+        }
+    } //values_in_range
+
+    /// Counts the values that fall into each of the buckets delimited by
+    /// the given edges, which must be in increasing order.  The returned
+    /// vector has one more element than `edges`: element 0 counts the
+    /// values less than `edges[0]`, element i counts the values v with
+    /// `edges[i-1] <= v < edges[i]`, and the last element counts the values
+    /// at least as large as the last edge.  Buckets are always in increasing
+    /// order of value, whether this is a min or max hashheap.  Values that
+    /// are not comparable to the edges, such as NaN, are counted in the
+    /// first bucket.  This operation runs in O(n log b) time for b edges.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut jobs = HashHeap::<u32,u32>::new_maxheap();
+    ///   for (k,p) in [(1,3),(2,15),(3,7),(4,10),(5,22),(6,9)] { jobs.insert(k,p); }
+    ///   assert_eq!(jobs.priority_histogram(&[5,10,20]), vec![1,2,2,1]);
+    /// ```
+    pub fn priority_histogram(&self, edges: &[VT]) -> Vec<usize> {
+        let mut counts = vec![0; edges.len() + 1];
+        for (v, _) in self.vals.iter() {
+            counts[edges.partition_point(|e| e <= v)] += 1;
+        }
+        counts
+    } //priority_histogram
+} // impl ordered HashHeap
+
+impl<KT: Hash + Eq, VT> HashHeap<KT, VT> {
+    /// Creates an empty HashHeap ordered by the given function instead of
+    /// the [PartialOrd] trait, which values therefore need not implement.
+    /// `cmp(a,b)` is true when a has lower priority than b, so the value at
+    /// the top is the "largest" according to `cmp`.  Apart from
+    /// [contains_val](HashHeap::contains_val),
+    /// [equal_range](HashHeap::equal_range) and the histogram functions,
+    /// every operation is available with any type of value.
+    /// ```
+    /// # use hashheap::*;
+    ///   struct Job { name:&'static str, deadline:u32 }  // no PartialOrd
+    ///   let mut jobs = HashHeap::<u32,Job>::with_comparator(|a,b| a.deadline > b.deadline);
+    ///   jobs.insert(1, Job{name:"report", deadline:30});
+    ///   jobs.insert(2, Job{name:"backup", deadline:10});
+    ///   assert_eq!(jobs.pop().map(|(_,j)|j.name), Some("backup"));
+    /// ```
+    pub fn with_comparator(cmp: fn(&VT, &VT) -> bool) -> HashHeap<KT, VT> {
+        Self::with_cmp(0, true, UserFn::Ptr(cmp))
+    }
+
+    /// Version of [with_comparator](HashHeap::with_comparator) that accepts
+    /// a closure, which can capture data such as a table of weights or a
+    /// configuration.  The closure is shared by clones of the HashHeap, and
+    /// must be `Send` and `Sync` so that the HashHeap still is.  Calling it
+    /// is slightly slower than calling a plain function.
+    /// ```
+    /// # use hashheap::*;
+    ///   let weight = vec![5, 1, 3];        // weight of each kind of job
+    ///   let mut jobs = HashHeap::<&str,usize>::with_comparator_closure(
+    ///       move |a:&usize, b:&usize| weight[*a] < weight[*b]);
+    ///   jobs.insert("x", 1);
+    ///   jobs.insert("y", 0);
+    ///   jobs.insert("z", 2);
+    ///   assert_eq!(jobs.pop(), Some(("y",0)));   // heaviest first
+    ///   assert_eq!(jobs.pop(), Some(("z",2)));
+    /// ```
+    pub fn with_comparator_closure<F>(cmp: F) -> HashHeap<KT, VT>
+    where
+        F: Fn(&VT, &VT) -> bool + Send + Sync + 'static,
+    {
+        Self::with_cmp(0, true, UserFn::Closure(Arc::new(cmp)))
+    }
+
+    fn with_cmp(mut cap: usize, maxheap: bool, cmp: CmpFn<VT>) -> HashHeap<KT, VT> {
+        if cap < 1 {
+            cap = DEFAULTCAP;
+        }
+        HashHeap {
+            keys: Vec::with_capacity(cap),
+            khash: Vec::with_capacity(cap),
+            vals: Vec::with_capacity(cap),
+            kmap: KMap::with_capacity(cap),
+            passes: HashMap::new(),
+            freekeys: Vec::new(),
+            maintcursor: 0,
+            userhash: None,
+            rehash: None,
+            lessthan: cmp,
+            autostate: HashState::Random(RandomState::new()),
+            minmax: maxheap,
+            policy: InsertPolicy::Replace,
+        }
+    } //with_cmp
+
+    // an empty HashHeap with the same ordering, hash functions, hash state
+    // and insert policy
+    fn empty_like(&self, cap: usize) -> HashHeap<KT, VT> {
+        let mut hh = Self::with_cmp(cap, self.minmax, self.lessthan.clone());
+        hh.userhash = self.userhash.clone();
+        hh.rehash = self.rehash.clone();
+        hh.autostate = self.autostate.clone();
+        hh.policy = self.policy;
+        hh
+    } //empty_like
+
     /// This function allows the user to override the default hasher
     /// provided by the Hash trait with an arbitrary function.  The
     /// operation is only allowed while the HashHeap is empty.  Returns
-    /// true on success.
+    /// true on success.  See [HashHeap::set_hash_with] for closures.
+    #[allow(clippy::len_zero)]
     pub fn set_hash(&mut self, h: fn(&KT) -> usize) -> bool {
         if self.keys.len() > 0 {
             return false;
         }
-        self.userhash = Some(h);
+        self.userhash = Some(UserFn::Ptr(h));
+        true
+    }
+
+    /// Version of [HashHeap::set_hash] that accepts a closure, such as one
+    /// that captures a salt.  The closure is shared by clones of the
+    /// HashHeap.  Only allowed while the HashHeap is empty.
+    /// ```
+    /// # use hashheap::*;
+    ///   let salt = 0x9e37_79b9_usize;
+    ///   let mut hh = HashHeap::<usize,u32>::new_minheap();
+    ///   assert!(hh.set_hash_with(move |k| k.wrapping_mul(salt)));
+    ///   hh.insert(3,1);
+    ///   assert_eq!(hh.get(&3), Some(&1));
+    /// ```
+    pub fn set_hash_with<F>(&mut self, h: F) -> bool
+    where
+        F: Fn(&KT) -> usize + Send + Sync + 'static,
+    {
+        if !self.keys.is_empty() {
+            return false;
+        }
+        self.userhash = Some(UserFn::Closure(Arc::new(h)));
         true
     }
 
@@ -219,45 +777,123 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
     /// ```
     /// The calculated hash value does not index a vector but a rust HashMap with
     /// indices as keys, so there's no issue with out-of-bounds hash values.
+    #[allow(clippy::len_zero)]
     pub fn set_rehash(&mut self, rh: fn(usize, usize) -> usize) -> bool {
         if self.keys.len() > 0 {
             return false;
         }
-        self.rehash = rh;
+        self.rehash = Some(UserFn::Ptr(rh));
+        true
+    }
+
+    /// Version of [HashHeap::set_rehash] that accepts a closure, which is
+    /// shared by clones of the HashHeap.  Only allowed while the HashHeap
+    /// is empty.
+    /// ```
+    /// # use hashheap::*;
+    ///   let step = 7;
+    ///   let mut table = HashHeap::<&str,i32>::new_minheap();
+    ///   assert!(table.set_rehash_with(move |h,c| h + c*step));
+    /// ```
+    pub fn set_rehash_with<F>(&mut self, rh: F) -> bool
+    where
+        F: Fn(usize, usize) -> usize + Send + Sync + 'static,
+    {
+        if !self.keys.is_empty() {
+            return false;
+        }
+        self.rehash = Some(UserFn::Closure(Arc::new(rh)));
         true
     }
 
     /// Override the internal comparison function with a function cmp such
     /// that `cmp(a,b)` is true means a is "less than" b.  This operation
     /// is only allowed when the size of the HashHeap is no more than one.
-    /// Returns true on success.
+    /// Returns true on success.  See [HashHeap::set_cmp_with] for closures.
     pub fn set_cmp(&mut self, cmp: fn(&VT, &VT) -> bool) -> bool {
         if self.keys.len() > 1 {
             false
         } else {
-            self.lessthan = cmp;
+            self.lessthan = UserFn::Ptr(cmp);
             true
         }
     } //set_cmp
 
+    /// Version of [HashHeap::set_cmp] that accepts a closure: see
+    /// [HashHeap::with_comparator_closure].
+    pub fn set_cmp_with<F>(&mut self, cmp: F) -> bool
+    where
+        F: Fn(&VT, &VT) -> bool + Send + Sync + 'static,
+    {
+        if self.keys.len() > 1 {
+            return false;
+        }
+        self.lessthan = UserFn::Closure(Arc::new(cmp));
+        true
+    } //set_cmp_with
+
+    /// Replaces the comparison function, as [HashHeap::set_cmp] does, but
+    /// at any size: the heap is then rebuilt in O(n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<&str,i32>::new_maxheap();
+    ///   hh.insert("a", -5); hh.insert("b", 3); hh.insert("c", 1);
+    ///   assert_eq!(hh.peek(), Some((&"b",&3)));
+    ///   hh.set_cmp_rebuild(|a,b| a.abs() < b.abs());  // by magnitude
+    ///   assert_eq!(hh.peek(), Some((&"a",&-5)));
+    /// ```
+    pub fn set_cmp_rebuild(&mut self, cmp: fn(&VT, &VT) -> bool) {
+        self.lessthan = UserFn::Ptr(cmp);
+        self.reheap();
+    }
+
     fn autohash(&self, key: &KT) -> usize {
-        self.userhash
-            .map_or(derive_hash(&self.autostate, key),
-                    |f| { f(key) }
-             )
+        self.key_hasher()(key)
     } //autohash
 
+    // the hash function of autohash, which can be shared by threads
+    pub(crate) fn key_hasher(&self) -> impl Fn(&KT) -> usize + Sync + '_ {
+        let (state, userhash) = (&self.autostate, &self.userhash);
+        move |key| match userhash {
+            None => derive_hash(state, key),
+            Some(UserFn::Ptr(f)) => f(key),
+            Some(UserFn::Closure(f)) => f(key),
+        }
+    } //key_hasher
+
+    // index probed after the given number of collisions
+    fn rehash_index(&self, h0: usize, collisions: usize) -> usize {
+        match &self.rehash {
+            None => h0 + collisions,
+            Some(UserFn::Ptr(f)) => f(h0, collisions),
+            Some(UserFn::Closure(f)) => f(h0, collisions),
+        }
+    }
+
     // must return index of where key is found, or of an empty slot,
     // must rehash on collision
     fn findslot(&self, key: &KT) -> (usize, bool) {
-        let mut h = self.autohash(key);
-        let h0 = h;
+        let (h, found, _) = self.probe(key);
+        (h, found)
+    }
+
+    // version of findslot that also returns the original hash index
+    fn probe(&self, key: &KT) -> (usize, bool, usize) {
+        self.probe_from(key, self.autohash(key))
+    }
+
+    // probe given the original hash index h0 of the key.  The cached hash
+    // indices of keys are compared first, so that == is rarely called on
+    // keys that collide
+    #[allow(clippy::redundant_pattern_matching)]
+    fn probe_from(&self, key: &KT, h0: usize) -> (usize, bool, usize) {
+        let mut h = h0;
         let mut collisions = 0;
         let mut reuse = None;
         while let Some((ki, vi)) = self.kmap.get(&h) {
             match &self.keys[*ki] {
-                Some(key2) if key2 == key => {
-                    return (h, true);
+                Some(key2) if self.khash[*ki] == h0 && key2 == key => {
+                    return (h, true, h0);
                 }
                 None => {
                     // rehash, set reuse
@@ -266,20 +902,112 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
                     }
                     collisions += 1;
                     //self.tc+=1;
-                    h = (self.rehash)(h0, collisions);
+                    h = self.rehash_index(h0, collisions);
                 }
                 Some(_) => {
                     //rehash, includes case where key entry is None
                     collisions += 1;
                     //self.tc+=1;
-                    h = (self.rehash)(h0, collisions);
+                    h = self.rehash_index(h0, collisions);
                 }
             } //match
         } //while let
-        reuse.map_or((h, false), |g| (g, false))
-    } //findslot returns index for insert, and bool indicating exact key match
+        (reuse.unwrap_or(h), false, h0)
+    } //probe returns index for insert, and bool indicating exact key match
       //Here, index refers to index of kmap, not of heap vector
 
+    // version of findslot for a borrowed form of the key, which can only be
+    // hashed by the default hasher: with a custom hash function, the keys
+    // are searched in O(n) time instead
+    fn findslot_borrowed<Q>(&self, key: &Q) -> Option<usize>
+    where
+        KT: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.userhash.is_some() {
+            return self.vals.iter().map(|(_, h)| *h).find(|h| {
+                let ki = self.kmap[h].0;
+                self.keys[ki].as_ref().map(|k| k.borrow() == key) == Some(true)
+            });
+        }
+        let h0 = derive_hash(&self.autostate, key);
+        let (mut h, mut collisions) = (h0, 0);
+        while let Some((ki, _)) = self.kmap.get(&h) {
+            match &self.keys[*ki] {
+                Some(key2) if self.khash[*ki] == h0 && key2.borrow() == key => {
+                    return Some(h);
+                }
+                _ => {
+                    collisions += 1;
+                    h = self.rehash_index(h0, collisions);
+                }
+            }
+        } //while let
+        None
+    } //findslot_borrowed
+
+    // Tombstones: a removed key leaves its kmap entry behind (with the key
+    // set to None) only while the probe sequence of some other key passes
+    // through it, as counted in passes.  Entries are therefore removed as
+    // soon as they're no longer needed, so that heavy insertion and removal
+    // does not lengthen probe sequences permanently.  This works with any
+    // rehash function, which rules out the backward shifting used by
+    // ConstHashHeap.
+
+    // stores a new key at kmap index h returned by probe from h0, counting
+    // it in the passes of the earlier indices of its probe sequence, and
+    // returns the index of the key in keys.  The caller sets kmap[h].
+    fn link_key(&mut self, h: usize, h0: usize, key: KT) -> usize {
+        let (mut g, mut collisions) = (h0, 0);
+        while g != h {
+            *self.passes.entry(g).or_insert(0) += 1;
+            collisions += 1;
+            g = self.rehash_index(h0, collisions);
+        }
+        let ki = match self.kmap.get(&h) {
+            Some((ki, _)) => *ki, // reuse tombstone
+            None => self.freekeys.pop().unwrap_or_else(|| {
+                self.keys.push(None);
+                self.khash.push(0);
+                self.keys.len() - 1
+            }),
+        };
+        self.keys[ki] = Some(key);
+        self.khash[ki] = h0;
+        ki
+    } //link_key
+
+    // reverses link_key for the key just taken from kmap index h,
+    // removing the tombstones that are no longer passed through
+    fn unlink_key(&mut self, h: usize) {
+        let h0 = self.khash[self.kmap[&h].0];
+        let (mut g, mut collisions) = (h0, 0);
+        while g != h {
+            match self.passes.get_mut(&g) {
+                Some(c) if *c > 1 => *c -= 1,
+                _ => {
+                    self.passes.remove(&g);
+                    self.drop_tombstone(g);
+                }
+            }
+            collisions += 1;
+            g = self.rehash_index(h0, collisions);
+        }
+        self.drop_tombstone(h);
+    } //unlink_key
+
+    fn drop_tombstone(&mut self, h: usize) {
+        if self.passes.contains_key(&h) {
+            return;
+        }
+        if let Some(&(ki, _)) = self.kmap.get(&h) {
+            if self.keys[ki].is_none() {
+                self.kmap.remove(&h);
+                self.freekeys.push(ki);
+            }
+        }
+    } //drop_tombstone
+
     /// Add or change a key-value pair, returning the replaced pair, if
     /// it exists.  This operation runs in **average-case O(1) time and
     /// worst-case O(log n) time**.
@@ -287,7 +1015,19 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
     /// number of values on each higher level decreases geometrically, so that
     /// the average is bounded by a convergent infinite series.
     pub fn insert(&mut self, key: KT, val: VT) -> Option<(KT, VT)> {
-        let (h, exists) = self.findslot(&key);
+        let (h, exists, h0) = self.probe(&key);
+        self.insert_probed(h, exists, h0, key, val)
+    } //insert
+
+    // insert at the slot h returned by probe, without probing again
+    fn insert_probed(
+        &mut self,
+        h: usize,
+        exists: bool,
+        h0: usize,
+        key: KT,
+        val: VT,
+    ) -> Option<(KT, VT)> {
         if exists {
             let (ki, vi) = *self.kmap.get(&h).unwrap();
             let mut newkey = Some(key);
@@ -300,69 +1040,309 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
         //replace
         else {
             // assuming key is new
-            let kn = self.keys.len();
             let vn = self.vals.len();
-            self.keys.push(Some(key));
-            self.vals.push((val, h));
-            self.kmap.insert(h, (kn, vn));
+            self.append_at(h, h0, key, val);
             self.swapup(vn);
             None
         } //else
-    } //insert
+    } //insert_probed
+
+    /// Sets the [InsertPolicy] applied by [HashHeap::put] when a key
+    /// already exists.  The default is [InsertPolicy::Replace].
+    pub fn set_insert_policy(&mut self, policy: InsertPolicy) {
+        self.policy = policy;
+    }
+
+    /// returns the policy set by [HashHeap::set_insert_policy]
+    pub fn insert_policy(&self) -> InsertPolicy {
+        self.policy
+    }
+
+    /// Adds a key-value pair, applying the [InsertPolicy] of the
+    /// structure if the key already exists, so that the intended handling
+    /// of duplicate keys is declared once instead of by the choice
+    /// between [insert](HashHeap::insert) and [push](HashHeap::push) at each
+    /// call.  Returns `Ok(None)` if the key was new, `Ok(Some(pair))` with
+    /// the pair that was replaced or not kept, or, under
+    /// [InsertPolicy::Error], `Err(pair)` with the new pair, leaving the
+    /// structure unchanged.  O(log n)
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut dist = HashHeap::<char,u32>::new_minheap();
+    ///   dist.set_insert_policy(InsertPolicy::KeepBest);
+    ///   assert_eq!(dist.put('a', 7), Ok(None));
+    ///   assert_eq!(dist.put('a', 9), Ok(Some(('a',9))));  // not better
+    ///   assert_eq!(dist.put('a', 4), Ok(Some(('a',7))));  // relaxed
+    ///   dist.set_insert_policy(InsertPolicy::Error);
+    ///   assert_eq!(dist.put('a', 1), Err(('a',1)));
+    ///   assert_eq!(dist.get(&'a'), Some(&4));
+    /// ```
+    pub fn put(&mut self, key: KT, val: VT) -> Result<Option<(KT, VT)>, (KT, VT)> {
+        let (h, exists, h0) = self.probe(&key);
+        if !exists {
+            return Ok(self.insert_probed(h, exists, h0, key, val));
+        }
+        let vi = self.kmap[&h].1;
+        match self.policy {
+            InsertPolicy::Replace => Ok(self.insert_probed(h, exists, h0, key, val)),
+            InsertPolicy::KeepBest if self.lessthan.call(&self.vals[vi].0, &val) => {
+                Ok(self.insert_probed(h, exists, h0, key, val))
+            }
+            InsertPolicy::KeepExisting | InsertPolicy::KeepBest => Ok(Some((key, val))),
+            InsertPolicy::Error => Err((key, val)),
+        }
+    } //put
+
+    /// Moves all entries of another HashHeap into this one, leaving `other`
+    /// empty but with its capacity.  When a key exists in both, the pair
+    /// with the higher priority is kept, as with [InsertPolicy::KeepBest].
+    /// The new pairs are appended without ordering them and the heap is
+    /// rebuilt once, so this operation takes O(n+m) time rather than the
+    /// O(m log(n+m)) of m insertions.  Returns the pairs that were not
+    /// kept.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh1 = HashHeap::<&str,u32>::new_minheap();
+    ///   let mut hh2 = HashHeap::<&str,u32>::new_minheap();
+    ///   hh1.insert("a", 4); hh1.insert("b", 2);
+    ///   hh2.insert("a", 1); hh2.insert("b", 3); hh2.insert("c", 5);
+    ///   let dropped = hh1.append(&mut hh2);
+    ///   assert_eq!(dropped.len(), 2);
+    ///   assert_eq!(hh1.len(), 3);
+    ///   assert_eq!(hh1.pop(), Some(("a",1)));
+    ///   assert_eq!(hh1.pop(), Some(("b",2)));
+    ///   assert_eq!(hh2.len(), 0);
+    /// ```
+    pub fn append(&mut self, other: &mut HashHeap<KT, VT>) -> Vec<(KT, VT)> {
+        self.append_with(other, InsertPolicy::KeepBest)
+    }
+
+    /// Version of [append](Self::append) that resolves duplicate keys with
+    /// the given policy.  [InsertPolicy::Error] keeps the existing pair,
+    /// like [InsertPolicy::KeepExisting]; either way the pairs that were
+    /// not kept are returned.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh1 = HashHeap::<&str,u32>::new_maxheap();
+    ///   let mut hh2 = HashHeap::<&str,u32>::new_maxheap();
+    ///   hh1.insert("a", 4);
+    ///   hh2.insert("a", 1);
+    ///   let dropped = hh1.append_with(&mut hh2, InsertPolicy::Replace);
+    ///   assert_eq!(dropped, vec![("a",4)]);
+    ///   assert_eq!(hh1.peek(), Some((&"a",&1)));
+    /// ```
+    pub fn append_with(
+        &mut self,
+        other: &mut HashHeap<KT, VT>,
+        policy: InsertPolicy,
+    ) -> Vec<(KT, VT)> {
+        let mut dropped = Vec::new();
+        if other.vals.is_empty() {
+            return dropped;
+        }
+        self.reserve(other.len());
+        for (k, v) in other.drain() {
+            let h0 = self.autohash(&k);
+            match self.probe_from(&k, h0) {
+                (h, false, h0) => self.append_at(h, h0, k, v),
+                (h, true, _) => {
+                    let (ki, vi) = self.kmap[&h];
+                    let replace = match policy {
+                        InsertPolicy::Replace => true,
+                        InsertPolicy::KeepBest => self.lessthan.call(&self.vals[vi].0, &v),
+                        InsertPolicy::KeepExisting | InsertPolicy::Error => false,
+                    };
+                    if replace {
+                        let oldk = self.keys[ki].replace(k).unwrap();
+                        let oldv = core::mem::replace(&mut self.vals[vi].0, v);
+                        dropped.push((oldk, oldv));
+                    } else {
+                        dropped.push((k, v));
+                    }
+                }
+            } //match
+        }
+        self.reheap();
+        dropped
+    } //append_with
 
     /// Version of insert that does not replace existing key.
     /// Instead, it returns false if an equivalent key already exists.
     pub fn push(&mut self, key: KT, val: VT) -> bool {
-        let (h, exists) = self.findslot(&key);
-        if exists {
-            false
-        } else {
-            // assuming key is new
-            let kn = self.keys.len();
-            let vn = self.vals.len();
-            self.keys.push(Some(key));
-            self.vals.push((val, h));
-            self.kmap.insert(h, (kn, vn));
+        let vn = self.vals.len();
+        if self.append_unsorted(key, val) {
             self.swapup(vn);
             true
-        } //else
+        } else {
+            false
+        }
     } //push
 
-    /// This operation replaces the top (highest priority) entry
-    /// with given key and value, and returns the previous top entry.
-    /// However, if the given key already exists, it replaces the existing
-    /// key-value with the new ones before removing the top entry.  This
-    /// operation runs in O(log n) time.
-    pub fn top_swap(&mut self, key: KT, val: VT) -> Option<(KT, VT)> {
-        if self.vals.len() == 0 {
-            self.push(key, val);
-            return None;
-        }
-        let (h, exists) = self.findslot(&key);
+    /// Version of [push](Self::push) that returns the key and value in an
+    /// [OccupiedError] if the key already exists, instead of dropping them.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<String,Vec<u8>>::with_comparator(|a,b| a.len() < b.len());
+    ///   assert!(hh.try_insert("a".to_string(), vec![1]).is_ok());
+    ///   let err = hh.try_insert("a".to_string(), vec![2,3]).unwrap_err();
+    ///   assert_eq!((err.key.as_str(), err.value), ("a", vec![2,3]));
+    ///   assert_eq!(hh.get(&"a".to_string()), Some(&vec![1]));
+    /// ```
+    pub fn try_insert(&mut self, key: KT, val: VT) -> Result<(), OccupiedError<KT, VT>> {
+        let (h, exists, h0) = self.probe(&key);
         if exists {
-            // replace key,val then pop
-            let (ki, vi) = *self.kmap.get(&h).unwrap();
+            return Err(OccupiedError { key, value: val });
+        }
+        let vn = self.vals.len();
+        self.append_at(h, h0, key, val);
+        self.swapup(vn);
+        Ok(())
+    } //try_insert
+
+    /// Inserts the key with the value returned by the closure only if the
+    /// key is absent, so that an expensive value is not computed for an
+    /// existing key.  Returns true if the key was inserted.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<&str,u64>::new_minheap();
+    ///   let mut calls = 0;
+    ///   for _ in 0..3 {
+    ///     hh.insert_with("total", || { calls += 1; (1..=1000).sum() });
+    ///   }
+    ///   assert_eq!(calls, 1);
+    ///   assert_eq!(hh.get(&"total"), Some(&500500));
+    /// ```
+    pub fn insert_with<F>(&mut self, key: KT, valfun: F) -> bool
+    where
+        F: FnOnce() -> VT,
+    {
+        let (h, exists, h0) = self.probe(&key);
+        if exists {
+            return false;
+        }
+        let vn = self.vals.len();
+        self.append_at(h, h0, key, valfun());
+        self.swapup(vn);
+        true
+    } //insert_with
+
+    /// Inserts or changes the value associated with the key to the value
+    /// returned by the closure, which is given the existing value, if any,
+    /// as in [ConstHashHeap::and_generate].  Only the key is hashed, once.
+    /// Returns true if the key is new.  O(log n)
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut counts = HashHeap::<char,u32>::new_maxheap();
+    ///   for c in "abracadabra".chars() {
+    ///     counts.and_generate(c, |old| old.map_or(1, |n| n+1));
+    ///   }
+    ///   assert_eq!(counts.peek(), Some((&'a',&5)));
+    /// ```
+    pub fn and_generate<F>(&mut self, key: KT, generator: F) -> bool
+    where
+        F: FnOnce(Option<&VT>) -> VT,
+    {
+        let (h, exists, h0) = self.probe(&key);
+        if exists {
+            let (_, vi) = self.kmap[&h];
+            self.vals[vi].0 = generator(Some(&self.vals[vi].0));
+            self.reposition(vi);
+            false
+        } else {
+            let vn = self.vals.len();
+            self.append_at(h, h0, key, generator(None));
+            self.swapup(vn);
+            true
+        }
+    } //and_generate
+
+    // adds a new key-value pair at the end of the heap array without
+    // sifting it up; returns false if the key already exists
+    fn append_unsorted(&mut self, key: KT, val: VT) -> bool {
+        let (h, exists, h0) = self.probe(&key);
+        if exists {
+            false
+        } else {
+            self.append_at(h, h0, key, val);
+            true
+        } //else
+    } //append_unsorted
+
+    // appends a new key at kmap index h returned by probe
+    fn append_at(&mut self, h: usize, h0: usize, key: KT, val: VT) {
+        let vn = self.vals.len();
+        let kn = self.link_key(h, h0, key);
+        self.vals.push((val, h));
+        self.kmap.insert(h, (kn, vn));
+    } //append_at
+
+    /// This operation replaces the top (highest priority) entry
+    /// with given key and value, and returns the previous top entry.
+    /// However, if the given key already exists, it replaces the existing
+    /// key-value with the new ones before removing the top entry.  This
+    /// operation runs in O(log n) time.
+    #[allow(clippy::len_zero)]
+    pub fn top_swap(&mut self, key: KT, val: VT) -> Option<(KT, VT)> {
+        if self.vals.len() == 0 {
+            self.push(key, val);
+            return None;
+        }
+        let (h, exists) = self.findslot(&key);
+        if exists {
+            // replace key,val then pop
+            let (ki, vi) = *self.kmap.get(&h).unwrap();
             self.keys[ki] = Some(key);
             self.vals[vi] = (val, h);
             self.reposition(vi);
             return self.pop();
         }
-        // get info about top value
-        let (_, it) = &self.vals[0];
-        let (tki, tvi) = *self.kmap.get(it).unwrap();
+        // remove top key, then probe again since tombstones may be gone
+        let th = self.vals[0].1;
+        let (tki, tvi) = *self.kmap.get(&th).unwrap();
         assert!(tvi == 0);
-        let mut newkey = Some(key);
-        let mut newval = (val, h);
-        core::mem::swap(&mut newkey, &mut self.keys[tki]);
-        core::mem::swap(&mut newval, &mut self.vals[0]);
-        self.kmap.insert(h, (tki, 0));
+        let oldkey = self.keys[tki].take().unwrap();
+        self.unlink_key(th);
+        let (h, _, h0) = self.probe(&key);
+        let ki = self.link_key(h, h0, key);
+        self.kmap.insert(h, (ki, 0));
+        let (oldval, _) = core::mem::replace(&mut self.vals[0], (val, h));
         self.swapdown(0);
-        Some((newkey.unwrap(), newval.0))
+        Some((oldkey, oldval))
     } //swap
 
+    /// Inserts the given key-value pair and then removes and returns the
+    /// highest-priority pair, which may be the one just given.  If the new
+    /// value would be at the top of the heap, it's returned immediately
+    /// without changing the structure.  Otherwise it replaces the top entry
+    /// as in [Self::top_swap], saving the cost of the separate sift-up and
+    /// sift-down required by an [Self::insert] followed by a [Self::pop].
+    /// If the key already exists, its value is replaced before the top
+    /// entry is removed.  This operation runs in O(log n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<&str,u32>::new_minheap();
+    ///   hh.insert("a",4);
+    ///   hh.insert("b",6);
+    ///   assert_eq!(hh.pushpop("c",2), ("c",2));
+    ///   assert_eq!(hh.pushpop("d",5), ("a",4));
+    ///   assert_eq!(hh.len(), 2);
+    /// ```
+    pub fn pushpop(&mut self, key: KT, val: VT) -> (KT, VT) {
+        if !self.contains_key(&key) {
+            match self.peek() {
+                Some((_, tv)) if self.lessthan.call(&val, tv) => {}
+                _ => {
+                    return (key, val);
+                }
+            }
+        }
+        self.top_swap(key, val).unwrap()
+    } //pushpop
+
     /// Returns the key-value pair with the highest priority value (smallest
     /// or largest depending on minheap or maxheap).  This operation runs in
     /// O(1) time
+    #[allow(clippy::len_zero)]
     pub fn peek(&self) -> Option<(&KT, &VT)> {
         if self.vals.len() == 0 {
             return None;
@@ -372,6 +1352,83 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
         Some((self.keys[k].as_ref().unwrap(), v))
     } //peek
 
+    /// Returns the key-value pair stored at the given position of the
+    /// internal heap array, or None if the index is not less than
+    /// [Self::len].  The entry at index 0 is the one returned by
+    /// [Self::peek], and the children of the entry at index i are at
+    /// indices 2i+1 and 2i+2.  Positions change whenever the structure is
+    /// mutated.  This operation runs in O(1) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let hh = HashHeap::from_pairs(vec![("a",3),("b",1),("c",2)], true);
+    ///   let top = hh.get_entry_at(0);
+    ///   assert_eq!(top, hh.peek());
+    ///   for i in 1..hh.len() {
+    ///     assert!(hh.get_entry_at(i).unwrap().1 <= top.unwrap().1);
+    ///   }
+    ///   assert!(hh.get_entry_at(3).is_none());
+    /// ```
+    pub fn get_entry_at(&self, heap_index: usize) -> Option<(&KT, &VT)> {
+        let (v, hv) = self.vals.get(heap_index)?;
+        let (ki, _) = self.kmap[hv];
+        self.keys[ki].as_ref().map(|k| (k, v))
+    } //get_entry_at
+
+    /// Fills the given buffer with the highest-priority entries, in order of
+    /// priority, without allocating memory: suitable for code that needs the
+    /// top few entries repeatedly.  If the buffer is longer than
+    /// [Self::len], the remaining slots are set to None.  Returns the number
+    /// of entries written.  Entries of equal priority may appear in any
+    /// order.  Subtrees of the heap below the k-th best entry are never
+    /// visited, so this requires O(k^2) time in typical cases.
+    /// ```
+    /// # use hashheap::*;
+    ///   let hh:HashHeap<u32,u32> = (0..20).map(|i|(i,(i*3)%20)).collect();
+    ///   let mut top = [None; 4];
+    ///   assert_eq!(hh.peek_top_k_into(&mut top), 4);
+    ///   assert_eq!(top.map(|p|*p.unwrap().1), [0,1,2,3]); // minheap
+    ///   let mut all = [None; 30];
+    ///   assert_eq!(hh.peek_top_k_into(&mut all), 20);
+    ///   assert!(all[20].is_none());
+    /// ```
+    pub fn peek_top_k_into<'a>(&'a self, out: &mut [Option<(&'a KT, &'a VT)>]) -> usize {
+        for slot in out.iter_mut() {
+            *slot = None;
+        }
+        let mut filled = 0;
+        self.topk_visit(0, out, &mut filled);
+        filled
+    } //peek_top_k_into
+
+    // depth-first search that prunes subtrees not better than the worst
+    // entry kept, inserting each entry visited into the sorted buffer
+    fn topk_visit<'a>(
+        &'a self,
+        i: usize,
+        out: &mut [Option<(&'a KT, &'a VT)>],
+        filled: &mut usize,
+    ) {
+        let (k, v) = match self.get_entry_at(i) {
+            Some(p) if !out.is_empty() => p,
+            _ => return,
+        };
+        if *filled == out.len() {
+            if out[*filled - 1].is_some_and(|(_, w)| !self.lessthan.call(w, v)) {
+                return;
+            }
+        } else {
+            *filled += 1;
+        }
+        let mut j = *filled - 1;
+        while j > 0 && out[j - 1].is_some_and(|(_, w)| self.lessthan.call(w, v)) {
+            out[j] = out[j - 1];
+            j -= 1;
+        }
+        out[j] = Some((k, v));
+        self.topk_visit(left(i), out, filled);
+        self.topk_visit(right(i), out, filled);
+    } //topk_visit
+
     /// Removes and returns the key-value pair with highest priority value
     /// (smallest or largest depending on minheap or maxheap).  This operation
     /// runs in O(log n) time
@@ -385,11 +1442,231 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
         let (V, iv) = self.vals.pop().unwrap();
         let (ki, vi) = *self.kmap.get(&iv).unwrap();
         core::mem::swap(&mut self.keys[ki], &mut Kopt);
-        // entry persist in kmap for rehashing
+        let K = Kopt.unwrap();
+        self.unlink_key(iv);
         self.swapdown(0);
-        Some((Kopt.unwrap(), V))
+        Some((K, V))
     } //pop
 
+    /// Returns an iterator that pops entries while the top entry has
+    /// strictly higher priority than the given bound, according to the
+    /// ordering of this HashHeap, and stops at the first entry that does
+    /// not, which remains in the structure.  For example, with a min-heap
+    /// of scheduled times, it releases everything scheduled before a given
+    /// time.  O(log n) per entry returned.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut due = HashHeap::<&str,u32>::new_minheap();
+    ///   due.insert("a", 30); due.insert("b", 10); due.insert("c", 20);
+    ///   let now = 20;
+    ///   let released:Vec<_> = due.drain_better_than(&now).collect();
+    ///   assert_eq!(released, vec![("b",10)]);
+    ///   assert_eq!(due.len(), 2);
+    /// ```
+    pub fn drain_better_than<'a>(
+        &'a mut self,
+        bound: &'a VT,
+    ) -> impl Iterator<Item = (KT, VT)> + 'a {
+        core::iter::from_fn(move || match self.peek() {
+            Some((_, v)) if self.lessthan.call(bound, v) => self.pop(),
+            _ => None,
+        })
+    } //drain_better_than
+
+    /// Removes and returns the highest-priority key-value pair only if it
+    /// satisfies the given predicate, which is given references to the top
+    /// key and value.  None is returned if the structure is empty or if the
+    /// predicate is false on the top entry.  O(log n)
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut deadlines = HashHeap::<String,u32>::new_minheap();
+    ///   deadlines.insert("report".to_string(), 9);
+    ///   let now = 8;
+    ///   assert_eq!(deadlines.pop_if(|_,d| *d <= now), None);
+    ///   assert_eq!(deadlines.pop_if(|_,d| *d <= now+1), Some(("report".to_string(),9)));
+    /// ```
+    pub fn pop_if<F>(&mut self, pred: F) -> Option<(KT, VT)>
+    where
+        F: FnOnce(&KT, &VT) -> bool,
+    {
+        match self.peek() {
+            Some((k, v)) if pred(k, v) => self.pop(),
+            _ => None,
+        }
+    } //pop_if
+
+    /// Returns an iterator that repeatedly pops the highest-priority
+    /// key-value pair while it satisfies the given predicate.  The iterator
+    /// stops at the first entry that fails the predicate, which remains in
+    /// the structure.  O(log n) per entry returned.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut events = HashHeap::<&str,u32>::new_minheap();
+    ///   events.insert("c", 12);
+    ///   events.insert("a", 3);
+    ///   events.insert("b", 10);
+    ///   let due:Vec<_> = events.pop_while(|_,t| *t <= 10).collect();
+    ///   assert_eq!(due, vec![("a",3),("b",10)]);
+    ///   assert_eq!(events.len(), 1);
+    /// ```
+    pub fn pop_while<'a, F>(&'a mut self, mut pred: F) -> impl Iterator<Item = (KT, VT)> + 'a
+    where
+        F: FnMut(&KT, &VT) -> bool + 'a,
+    {
+        core::iter::from_fn(move || self.pop_if(&mut pred))
+    } //pop_while
+
+    /// Removes all entries with strictly higher priority than the given
+    /// threshold, according to the ordering of this HashHeap, and returns
+    /// them in a new HashHeap with the same ordering, hash functions and
+    /// hash state.  The entries equal to or below the threshold remain.
+    /// Both heaps are rebuilt with a single O(n) pass, instead of popping
+    /// the better entries one at a time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<u32,u32>::new_minheap();
+    ///   for i in 0..10 { hh.insert(i, 10*i); }
+    ///   let early = hh.split_off(&40);
+    ///   assert_eq!(early.len(), 4);              // 0, 10, 20, 30
+    ///   assert_eq!(early.peek(), Some((&0,&0)));
+    ///   assert_eq!(hh.len(), 6);
+    ///   assert_eq!(hh.peek(), Some((&4,&40)));
+    /// ```
+    pub fn split_off(&mut self, threshold: &VT) -> HashHeap<KT, VT> {
+        let lessthan = self.lessthan.clone();
+        let better: Vec<(KT, VT)> = self
+            .extract_if(|_, v| lessthan.call(threshold, v))
+            .collect();
+        let mut hh = self.empty_like(better.len());
+        hh.heapify(better);
+        hh
+    } //split_off
+
+    /// Returns an iterator that removes and yields, in no particular order,
+    /// only the key-value pairs for which the predicate returns true,
+    /// leaving the others in place.  Entries are examined lazily: those not
+    /// reached before the iterator is dropped are neither tested nor
+    /// removed.  The heap property is restored with a single O(n) pass when
+    /// the iterator is dropped, if anything was removed, so the iterator
+    /// should not be leaked with [core::mem::forget].
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<u32,u32>::new_minheap();
+    ///   for i in 0..10 { hh.insert(i, 100-i); }
+    ///   let mut odd:Vec<_> = hh.extract_if(|k,_| k%2==1).collect();
+    ///   odd.sort();
+    ///   assert_eq!(odd, vec![(1,99),(3,97),(5,95),(7,93),(9,91)]);
+    ///   assert_eq!(hh.len(), 5);
+    ///   assert_eq!(hh.pop(), Some((8,92)));
+    /// ```
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, KT, VT, F>
+    where
+        F: FnMut(&KT, &VT) -> bool,
+    {
+        ExtractIf {
+            hh: self,
+            pred,
+            index: 0,
+            removed: false,
+        }
+    } //extract_if
+
+    /// Keeps only the key-value pairs for which the predicate returns true,
+    /// removing the others.  The heap property is restored with a single
+    /// O(n) pass afterwards instead of one O(log n) removal per entry, so
+    /// the whole operation takes O(n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut jobs = HashHeap::<u32,u32>::new_minheap();
+    ///   for i in 0..10 { jobs.insert(i, 20-i); }   // key i due at 20-i
+    ///   let now = 15;
+    ///   jobs.retain(|_,due| *due > now);            // expire stale jobs
+    ///   assert_eq!(jobs.len(), 5);
+    ///   assert_eq!(jobs.pop(), Some((4,16)));
+    /// ```
+    pub fn retain<F>(&mut self, mut pred: F)
+    where
+        F: FnMut(&KT, &VT) -> bool,
+    {
+        self.extract_if(|k, v| !pred(k, v)).for_each(drop);
+    } //retain
+
+    /// Returns an iterator that removes all entries in no particular order,
+    /// as [std::collections::BinaryHeap::drain] does.  Unlike the consuming
+    /// [IntoIter], the HashHeap keeps its allocated capacity, hash state
+    /// and ordering and can be reused afterwards.  If the iterator is
+    /// dropped before it's exhausted, the remaining entries are still
+    /// removed.  Draining the structure takes O(n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<u32,u32>::new_maxheap();
+    ///   for i in 0..10 { hh.insert(i, i*i); }
+    ///   let total:u32 = hh.drain().map(|(k,_)|k).sum();
+    ///   assert_eq!(total, 45);
+    ///   assert_eq!(hh.len(), 0);
+    ///   hh.insert(1, 1);
+    ///   assert_eq!(hh.peek(), Some((&1,&1)));
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, KT, VT> {
+        Drain(self)
+    }
+
+    /// Returns an iterator that removes all entries in order of priority,
+    /// as in [priority_stream](Self::priority_stream), except that any
+    /// entries remaining when the iterator is dropped are also removed.
+    /// The HashHeap keeps its capacity.  Draining the structure takes
+    /// O(n log n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<&str,u32>::new_minheap();
+    ///   hh.insert("a", 3); hh.insert("b", 1); hh.insert("c", 2);
+    ///   let first2:Vec<_> = hh.drain_sorted().take(2).collect();
+    ///   assert_eq!(first2, vec![("b",1),("c",2)]);
+    ///   assert_eq!(hh.len(), 0);
+    /// ```
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, KT, VT> {
+        DrainSorted(self)
+    }
+
+    /// Consumes the structure and returns an iterator over its keys in
+    /// no particular order.  This takes O(n) time in total: the heap is
+    /// not consulted.
+    /// ```
+    /// # use hashheap::*;
+    ///   let hh:HashHeap<String,u32> = vec![("x".to_string(),1),("y".to_string(),2)].into();
+    ///   let mut keys:Vec<String> = hh.into_keys().collect();
+    ///   keys.sort();
+    ///   assert_eq!(keys, vec!["x","y"]);
+    /// ```
+    pub fn into_keys(self) -> IntoKeys<KT> {
+        IntoKeys {
+            remaining: self.vals.len(),
+            keys: self.keys.into_iter(),
+        }
+    }
+
+    /// Consumes the structure and returns an iterator over its values in
+    /// order of priority, like the [consuming iterator](IntoIter), which
+    /// takes O(n log n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let hh:HashHeap<&str,u32> = vec![("a",3),("b",1),("c",2)].into();
+    ///   let vals:Vec<u32> = hh.into_values().collect();
+    ///   assert_eq!(vals, vec![3,2,1]);   // max-hashheap
+    /// ```
+    pub fn into_values(self) -> IntoValues<KT, VT> {
+        IntoValues(self.into_iter())
+    }
+
+    // removes the last entry of the heap, which keeps the heap valid
+    fn pop_last(&mut self) -> Option<(KT, VT)> {
+        let (V, h) = self.vals.pop()?;
+        let (ki, _) = self.kmap[&h];
+        let K = self.keys[ki].take().unwrap();
+        self.unlink_key(h);
+        Some((K, V))
+    } //pop_last
+
     /// returns the value associated with the given key, if it exists.  
     /// Indexed access is also available, but will panic if the key is not found.
     /// This operation runs in O(1) time.
@@ -407,42 +1684,418 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
         }
     } //get
 
+    /// Returns the position of the key's entry inside the binary heap,
+    /// where 0 is the top and the children of i are at 2i+1 and 2i+2.
+    /// This is the cheap, O(1) estimate of how soon an entry will be
+    /// popped: its depth, `log2(i+1)`, is at most the number of pops before
+    /// it reaches the top.  See [rank](Self::rank) for the exact position.
+    pub fn heap_index(&self, key: &KT) -> Option<usize> {
+        match self.findslot(key) {
+            (h, true) => Some(self.kmap[&h].1),
+            _ => None,
+        }
+    } //heap_index
+
+    /// Returns the number of entries with strictly higher priority than
+    /// the key's, which is the number of pops before it would reach the
+    /// top, not counting ties.  The search prunes every subtree whose root
+    /// does not have higher priority, so it visits only those entries and
+    /// their immediate children: this operation runs in O(r) time for rank
+    /// r, and O(n) in the worst case.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut jobs = HashHeap::<&str,u32>::new_minheap();
+    ///   for (j,p) in [("a",4),("b",1),("c",3),("d",3),("e",9)] { jobs.insert(j,p); }
+    ///   assert_eq!(jobs.rank(&"b"), Some(0));
+    ///   assert_eq!(jobs.rank(&"c"), Some(1));
+    ///   assert_eq!(jobs.rank(&"d"), Some(1));
+    ///   assert_eq!(jobs.rank(&"e"), Some(4));
+    ///   assert_eq!(jobs.heap_index(&"b"), Some(0));
+    ///   assert_eq!(jobs.rank(&"z"), None);
+    /// ```
+    pub fn rank(&self, key: &KT) -> Option<usize> {
+        let val = &self.vals[self.heap_index(key)?].0;
+        let mut count = 0;
+        let mut stack = if self.vals.is_empty() { vec![] } else { vec![0] };
+        while let Some(i) = stack.pop() {
+            if !self.lessthan.call(val, &self.vals[i].0) {
+                continue; // prune subtree
+            }
+            count += 1;
+            for c in [left(i), right(i)] {
+                if c < self.vals.len() {
+                    stack.push(c);
+                }
+            }
+        }
+        Some(count)
+    } //rank
+
+    /// Returns an iterator over the entries in the order of the array that
+    /// stores the binary heap, each with its [heap index](Self::heap_index),
+    /// starting with the top entry at index 0.  Together with
+    /// [heap_parent](Self::heap_parent) and [heap_children](Self::heap_children)
+    /// this exposes the shape of the heap, for example to draw it.
+    /// ```
+    /// # use hashheap::*;
+    ///   let hh:HashHeap<&str,u32> = vec![("a",1),("b",5),("c",3),("d",4)].into();
+    ///   for (i,k,v) in hh.iter_heap_order() {
+    ///     assert_eq!(hh.heap_index(k), Some(i));
+    ///     if let Some(p) = hh.heap_parent(i) {
+    ///       assert!(hh.heap_children(p).any(|c| c == i));
+    ///     }
+    ///   }
+    ///   assert_eq!(hh.iter_heap_order().next(), Some((0,&"b",&5)));
+    ///   assert_eq!(hh.heap_children(1).collect::<Vec<_>>(), vec![3]);
+    /// ```
+    pub fn iter_heap_order(&self) -> impl ExactSizeIterator<Item = (usize, &KT, &VT)> + '_ {
+        self.iter().enumerate().map(|(i, (k, v))| (i, k, v))
+    }
+
+    /// Returns the heap index of the parent of the entry at heap index i,
+    /// or None for the top entry or if there is no entry at i.
+    pub fn heap_parent(&self, i: usize) -> Option<usize> {
+        if i > 0 && i < self.vals.len() {
+            Some(parent(i))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the heap indices of the (at most two) children of the entry
+    /// at heap index i.
+    pub fn heap_children(&self, i: usize) -> impl Iterator<Item = usize> {
+        let (n, i) = (self.vals.len(), i.min(self.vals.len()));
+        [left(i), right(i)].into_iter().filter(move |c| *c < n)
+    }
+
     /// This operation applies the mutating closure to the value associated
     /// with the key, if it exists.  It then adjusts the position of the
-    /// value inside the heap.  It returns true on success and false if
-    /// the key was not found. This operation runs in O(log n) time in addition
-    /// to the cost of calling the closure.
-    pub fn modify<F>(&mut self, key: &KT, mapfun: F) -> bool
+    /// value inside the heap, and returns whether it moved [up](Moved::Up)
+    /// or [down](Moved::Down), or [not at all](Moved::None) because its
+    /// order relative to its parent and children is unchanged.  None is
+    /// returned if the key was not found.  This operation runs in O(log n)
+    /// time in addition to the cost of calling the closure, but only O(1)
+    /// time if the value does not move.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<&str,u32>::new_minheap();
+    ///   hh.insert("a", 10);
+    ///   hh.insert("b", 20);
+    ///   hh.insert("c", 30);
+    ///   assert_eq!(hh.modify(&"c", |v| *v = 31), Some(Moved::None));
+    ///   assert_eq!(hh.modify(&"c", |v| *v = 5), Some(Moved::Up));
+    ///   assert_eq!(hh.modify(&"c", |v| *v = 50), Some(Moved::Down));
+    ///   assert_eq!(hh.modify(&"d", |v| *v = 50), None);
+    /// ```
+    pub fn modify<F>(&mut self, key: &KT, mapfun: F) -> Option<Moved>
     where
         F: FnOnce(&mut VT),
     {
         if let (h, true) = self.findslot(key) {
-            let (_, vi) = self.kmap[&h];
-            mapfun(&mut self.vals[vi].0);
+            Some(self.modify_slot(h, mapfun))
+        } else {
+            None
+        }
+    } //modify
+
+    /// Version of [Self::modify] that also returns a reference to the new
+    /// value, so that the caller can tell whether a change, such as a
+    /// relaxation, reached the top of the heap without another lookup.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<&str,u32>::new_minheap();
+    ///   hh.insert("a", 10); hh.insert("b", 20);
+    ///   let (moved, v) = hh.modify_verbose(&"b", |v| *v = 5).unwrap();
+    ///   assert_eq!((moved, *v), (Moved::Up, 5));
+    ///   assert_eq!(hh.peek(), Some((&"b",&5)));
+    /// ```
+    pub fn modify_verbose<F>(&mut self, key: &KT, mapfun: F) -> Option<(Moved, &VT)>
+    where
+        F: FnOnce(&mut VT),
+    {
+        if let (h, true) = self.findslot(key) {
+            let moved = self.modify_slot(h, mapfun);
+            Some((moved, &self.vals[self.kmap[&h].1].0))
+        } else {
+            None
+        }
+    } //modify_verbose
+
+    // modifies the value of the key at kmap index h, which must be occupied
+    fn modify_slot<F>(&mut self, h: usize, mapfun: F) -> Moved
+    where
+        F: FnOnce(&mut VT),
+    {
+        let (_, vi) = self.kmap[&h];
+        mapfun(&mut self.vals[vi].0);
+        let ni = self.reposition(vi);
+        if ni < vi {
+            Moved::Up
+        } else if ni > vi {
+            Moved::Down
+        } else {
+            Moved::None
+        }
+    } //modify_slot
+
+    /// Version of [Self::modify] in which the closure is also given a
+    /// reference to the key, for when the new value depends on the key.
+    /// Returns false if the key was not found.  O(log n) in addition to
+    /// the cost of calling the closure.
+    pub fn modify_with_key<F>(&mut self, key: &KT, mapfun: F) -> bool
+    where
+        F: FnOnce(&KT, &mut VT),
+    {
+        if let (h, true) = self.findslot(key) {
+            let (ki, vi) = self.kmap[&h];
+            if let Some(k) = &self.keys[ki] {
+                mapfun(k, &mut self.vals[vi].0);
+            }
             self.reposition(vi);
             true
         } else {
             false
         }
-    } //modify
+    } //modify_with_key
+
+    /// Conditional version of [Self::modify] in the style of
+    /// compare-and-set.  The closure is given the current value and returns
+    /// either a new value, which replaces it, or None, in which case the
+    /// entry and its position in the heap are left untouched.  Returns
+    /// `Some(true)` if a new value was installed, `Some(false)` if the
+    /// closure returned None, and None if the key was not found.  O(log n)
+    /// if a new value was installed, O(1) otherwise, in addition to the cost
+    /// of calling the closure.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<&str,u32>::new_minheap();
+    ///   hh.insert("a", 5);
+    ///   let lower = |v:&u32| if 3 < *v { Some(3) } else { None };
+    ///   assert_eq!(hh.modify_if(&"a", lower), Some(true));
+    ///   assert_eq!(hh.modify_if(&"a", lower), Some(false));
+    ///   assert_eq!(hh.modify_if(&"b", lower), None);
+    ///   assert_eq!(hh.get(&"a"), Some(&3));
+    /// ```
+    pub fn modify_if<F>(&mut self, key: &KT, mapfun: F) -> Option<bool>
+    where
+        F: FnOnce(&VT) -> Option<VT>,
+    {
+        if let (h, true) = self.findslot(key) {
+            let (_, vi) = self.kmap[&h];
+            match mapfun(&self.vals[vi].0) {
+                Some(newval) => {
+                    self.vals[vi].0 = newval;
+                    self.reposition(vi);
+                    Some(true)
+                }
+                None => Some(false),
+            }
+        } else {
+            None
+        }
+    } //modify_if
+
+    /// Replaces the value associated with the key only if the new value
+    /// has strictly higher priority, that is, if it's smaller in a
+    /// min-hashheap or larger in a max-hashheap; this is the *decrease-key*
+    /// operation of Dijkstra's and similar algorithms.  Returns true if the
+    /// value was replaced, and false if it was not or if the key was not
+    /// found.  Only one hash lookup is performed, and since the value can
+    /// only move up, this operation runs in O(log n) time, or O(1) if the
+    /// value was not replaced.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut dist = HashHeap::<char,u32>::new_minheap();
+    ///   dist.insert('a', 10);
+    ///   dist.insert('b', 6);
+    ///   assert!(dist.modify_if_improves(&'a', 4));
+    ///   assert!(!dist.modify_if_improves(&'a', 8));
+    ///   assert!(!dist.modify_if_improves(&'z', 1));
+    ///   assert_eq!(dist.peek(), Some((&'a',&4)));
+    /// ```
+    pub fn modify_if_improves(&mut self, key: &KT, newval: VT) -> bool {
+        if let (h, true) = self.findslot(key) {
+            let (_, vi) = self.kmap[&h];
+            if self.lessthan.call(&self.vals[vi].0, &newval) {
+                self.vals[vi].0 = newval;
+                self.swapup(vi);
+                return true;
+            }
+        }
+        false
+    } //modify_if_improves
+
+    /// Replaces the value associated with an existing key, returning the
+    /// old value, and repositions it in the heap.  Unlike
+    /// [insert](HashHeap::insert), the key is borrowed and a key that
+    /// doesn't exist is not inserted: None is returned instead.  O(log n)
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<String,u32>::new_maxheap();
+    ///   hh.insert("a".to_string(), 1);
+    ///   let a = "a".to_string();
+    ///   assert_eq!(hh.replace(&a, 5), Some(1));
+    ///   assert_eq!(hh.replace(&"b".to_string(), 2), None);
+    ///   assert_eq!(hh.len(), 1);
+    ///   assert_eq!(hh.get(&a), Some(&5));
+    /// ```
+    pub fn replace(&mut self, key: &KT, val: VT) -> Option<VT> {
+        if let (h, true) = self.findslot(key) {
+            let (_, vi) = self.kmap[&h];
+            let old = core::mem::replace(&mut self.vals[vi].0, val);
+            self.reposition(vi);
+            Some(old)
+        } else {
+            None
+        }
+    } //replace
+
+    /// Applies a batch of updates with "keep the better value" semantics,
+    /// as in the relaxation step of Dijkstra's algorithm: each new key is
+    /// inserted, and the value of an existing key is replaced only if the
+    /// new value has higher priority.  Returns the number of entries
+    /// inserted or improved.  Each update is sifted immediately while the
+    /// batch is small, but once the number of changes exceeds n/log(n) the
+    /// rest are applied without sifting and the heap is rebuilt with a
+    /// single O(n) *heapify*, so a batch of m updates takes
+    /// O(min(m log n, m + n)) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut open = HashHeap::<char,u32>::new_minheap();
+    ///   open.insert('b', 7);
+    ///   open.insert('c', 2);
+    ///   let changed = open.relax_batch([('b',4), ('c',5), ('d',3)]);
+    ///   assert_eq!(changed, 2);  // c kept its lower cost
+    ///   assert_eq!(open.pop(), Some(('c',2)));
+    ///   assert_eq!(open.pop(), Some(('d',3)));
+    /// ```
+    pub fn relax_batch<I>(&mut self, updates: I) -> usize
+    where
+        I: IntoIterator<Item = (KT, VT)>,
+    {
+        let n = self.vals.len().max(2);
+        let limit = n / (usize::BITS - n.leading_zeros()) as usize;
+        let mut changed = 0;
+        for (key, val) in updates {
+            let sift = changed < limit;
+            match self.findslot(&key) {
+                (h, true) => {
+                    let (_, vi) = self.kmap[&h];
+                    if self.lessthan.call(&self.vals[vi].0, &val) {
+                        self.vals[vi].0 = val;
+                        if sift {
+                            self.swapup(vi);
+                        }
+                        changed += 1;
+                    }
+                }
+                _ => {
+                    let vn = self.vals.len();
+                    self.append_unsorted(key, val);
+                    if sift {
+                        self.swapup(vn);
+                    }
+                    changed += 1;
+                }
+            } //match
+        } //for
+        if changed > limit {
+            self.reheap();
+        }
+        changed
+    } //relax_batch
+
+    /// Applies the closure to the values of the given keys, skipping keys
+    /// that are not found, and returns the number of values modified.  As
+    /// in [relax_batch](Self::relax_batch), each value is repositioned
+    /// immediately while the batch is small, but once more than n/log(n)
+    /// values have changed the rest are left in place and the heap is
+    /// rebuilt with a single O(n) *heapify* at the end.  The keys may be
+    /// given by value or by reference.  See [modify_all](Self::modify_all)
+    /// to modify every value.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut jobs = HashHeap::<u32,u32>::new_maxheap();
+    ///   for i in 0..100 { jobs.insert(i, 10); }
+    ///   let aged = jobs.modify_many((0..100).filter(|i| i%3==0), |p| *p += 1);
+    ///   assert_eq!(aged, 34);
+    ///   assert_eq!(jobs.peek().map(|p|*p.1), Some(11));
+    ///   assert_eq!(jobs.modify_many(&[5, 500], |p| *p = 20), 1);
+    ///   assert_eq!(jobs.pop(), Some((5,20)));
+    /// ```
+    pub fn modify_many<I, Q, F>(&mut self, keys: I, mut mapfun: F) -> usize
+    where
+        I: IntoIterator<Item = Q>,
+        Q: Borrow<KT>,
+        F: FnMut(&mut VT),
+    {
+        let n = self.vals.len().max(2);
+        let limit = n / (usize::BITS - n.leading_zeros()) as usize;
+        let mut changed = 0;
+        for key in keys {
+            if let (h, true) = self.findslot(key.borrow()) {
+                let (_, vi) = self.kmap[&h];
+                mapfun(&mut self.vals[vi].0);
+                if changed < limit {
+                    self.reposition(vi);
+                }
+                changed += 1;
+            }
+        } //for
+        if changed > limit {
+            self.reheap();
+        }
+        changed
+    } //modify_many
+
+    /// Applies the closure to every key-value pair, allowing the values to
+    /// be changed, then restores the heap property with a single O(n)
+    /// *heapify* pass instead of repositioning each value.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<u32,u32>::new_maxheap();
+    ///   for i in 1..10 { hh.insert(i,i); }
+    ///   hh.modify_all(|k,v| *v = 100 - k*k);
+    ///   assert_eq!(hh.peek(), Some((&1,&99)));
+    /// ```
+    pub fn modify_all<F>(&mut self, mut mapfun: F)
+    where
+        F: FnMut(&KT, &mut VT),
+    {
+        for (v, h) in self.vals.iter_mut() {
+            let (ki, _) = self.kmap[h];
+            if let Some(k) = &self.keys[ki] {
+                mapfun(k, v);
+            }
+        }
+        self.reheap();
+    } //modify_all
 
     /// Removes and returns the key-value pair with the given key reference, if it
     /// exists.  This operation runs in O(log n) time.
     pub fn remove(&mut self, key: &KT) -> Option<(KT, VT)> {
         if let (h, true) = self.findslot(key) {
-            let (ki, vi) = self.kmap[&h];
-            self.heapswap(vi, self.vals.len() - 1);
-            let (V, _) = self.vals.pop().unwrap();
-            //if vi < self.vals.len() {self.reposition(vi);}  //vi was not popped
-            self.reposition(vi);
-            let mut K = None;
-            core::mem::swap(&mut K, &mut self.keys[ki]);
-            Some((K.unwrap(), V))
+            Some(self.remove_slot(h))
         } else {
             None
         }
     } //remove
 
+    // removes entry at kmap index h, which must be occupied
+    fn remove_slot(&mut self, h: usize) -> (KT, VT) {
+        let (ki, vi) = self.kmap[&h];
+        self.heapswap(vi, self.vals.len() - 1);
+        let (V, _) = self.vals.pop().unwrap();
+        //if vi < self.vals.len() {self.reposition(vi);}  //vi was not popped
+        self.reposition(vi);
+        let mut K = None;
+        core::mem::swap(&mut K, &mut self.keys[ki]);
+        let K = K.unwrap();
+        self.unlink_key(h);
+        (K, V)
+    } //remove_slot
+
     /// Determines if the given key exists in the HashHeap. This is an
     /// O(1) operation.
     pub fn contains_key(&self, key: &KT) -> bool {
@@ -450,31 +2103,94 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
         self.findslot(key).1
     }
 
-    /// Determines if the given value exists in the table.  This operation
-    /// **runs in O(n) time**.
-    pub fn contains_val(&self, val: &VT) -> bool {
-        // O(n)
-        self.valsearch(0, val)
+    /// Determines if every key of this HashHeap is also a key of the
+    /// other, regardless of values.  This operation runs in O(n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let small:HashHeap<u32,u32> = (0..3).map(|i|(i,i)).collect();
+    ///   let big:HashHeap<u32,&str> = (0..5).map(|i|(i,"x")).collect();
+    ///   assert!(small.is_subset(&big));
+    ///   assert!(big.is_superset(&small));
+    ///   assert!(!big.is_subset(&small));
+    /// ```
+    pub fn is_subset<V2>(&self, other: &HashHeap<KT, V2>) -> bool {
+        self.len() <= other.len() && self.keys().all(|k| other.contains_key(k))
     }
-    fn valsearch(&self, root: usize, val: &VT) -> bool {
-        if root >= self.vals.len() {
-            false
-        } else if &self.vals[root].0 == val {
-            true
-        } else if (self.lessthan)(&self.vals[root].0, val) {
-            false
-        } else {
-            self.valsearch(left(root), val) || self.valsearch(right(root), val)
-        }
+
+    /// Determines if every key of the other HashHeap is also a key of this
+    /// one: see [is_subset](Self::is_subset).
+    pub fn is_superset<V2>(&self, other: &HashHeap<KT, V2>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Version of [get](Self::get) that looks up a key by any borrowed form
+    /// of it, such as a `&str` for a `String` key, without constructing a
+    /// key.  As with [HashMap::get], the [Hash] and [Eq] implementations of
+    /// the borrowed form must agree with those of the key.  Keys are
+    /// hashed by the default hasher: if a custom hash function was set
+    /// with [set_hash](Self::set_hash), which takes only `&KT`, this and
+    /// the other `_borrowed` lookups search all keys in O(n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<String,u32>::new_minheap();
+    ///   hh.insert("a".to_string(), 3);
+    ///   hh.insert("b".to_string(), 2);
+    ///   assert_eq!(hh.get_borrowed("a"), Some(&3));
+    ///   assert!(hh.contains_key_borrowed("b"));
+    ///   hh.modify_borrowed("a", |v| *v = 1);
+    ///   assert_eq!(hh.remove_borrowed("a"), Some(("a".to_string(), 1)));
+    ///   assert!(!hh.contains_key_borrowed("a"));
+    /// ```
+    pub fn get_borrowed<Q>(&self, key: &Q) -> Option<&VT>
+    where
+        KT: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let h = self.findslot_borrowed(key)?;
+        Some(&self.vals[self.kmap[&h].1].0)
+    }
+
+    /// version of [contains_key](Self::contains_key) for a borrowed form of
+    /// the key: see [get_borrowed](Self::get_borrowed)
+    pub fn contains_key_borrowed<Q>(&self, key: &Q) -> bool
+    where
+        KT: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.findslot_borrowed(key).is_some()
     }
 
+    /// version of [remove](Self::remove) for a borrowed form of the key:
+    /// see [get_borrowed](Self::get_borrowed)
+    pub fn remove_borrowed<Q>(&mut self, key: &Q) -> Option<(KT, VT)>
+    where
+        KT: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let h = self.findslot_borrowed(key)?;
+        Some(self.remove_slot(h))
+    }
+
+    /// version of [modify](Self::modify) for a borrowed form of the key:
+    /// see [get_borrowed](Self::get_borrowed)
+    pub fn modify_borrowed<Q, F>(&mut self, key: &Q, mapfun: F) -> Option<Moved>
+    where
+        KT: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&mut VT),
+    {
+        let h = self.findslot_borrowed(key)?;
+        Some(self.modify_slot(h, mapfun))
+    } //modify_borrowed
+
+
     // treat as maxheap
     fn swapup(&mut self, mut i: usize) -> usize {
         if i >= self.vals.len() {
             return i;
         }
         let mut p = parent(i);
-        while i > 0 && (self.lessthan)(&self.vals[p].0, &self.vals[i].0) {
+        while i > 0 && self.lessthan.call(&self.vals[p].0, &self.vals[i].0) {
             self.heapswap(i, p);
             i = p;
             p = parent(i);
@@ -482,6 +2198,7 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
         i
     } //swapup returns final position of ith val
 
+    #[allow(clippy::manual_div_ceil)]
     fn swapdown(&mut self, mut i: usize) -> usize {
         let size = self.vals.len();
         let nonleaves = size - ((size + 1) / 2);
@@ -491,12 +2208,12 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
             sc = usize::MAX;
             let li = left(i);
             let ri = right(i);
-            if li < size && (self.lessthan)(&self.vals[i].0, &self.vals[li].0) {
+            if li < size && self.lessthan.call(&self.vals[i].0, &self.vals[li].0) {
                 sc = li;
             }
             if ri < size
-                && (self.lessthan)(&self.vals[i].0, &self.vals[ri].0)
-                && (self.lessthan)(&self.vals[li].0, &self.vals[ri].0)
+                && self.lessthan.call(&self.vals[i].0, &self.vals[ri].0)
+                && self.lessthan.call(&self.vals[li].0, &self.vals[ri].0)
             {
                 sc = ri;
             }
@@ -518,6 +2235,7 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
     } //reposition
 
     // swap values at indices i, j in vals, re-associate
+    #[allow(clippy::option_map_unit_fn)]
     fn heapswap(&mut self, i: usize, j: usize) {
         if i == j {
             return;
@@ -535,34 +2253,154 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
     } // swap values in vals, re-associate
 
     fn heapify(&mut self, vkv: Vec<(KT, VT)>) {
-        if self.keys.len() > 0 {
-            self.keys.clear();
-            self.vals.clear();
-            self.kmap.clear();
-        }
-        let vn = vkv.len();
-        let nonleafs = vn - (vn + 1) / 2;
-        let mut vi = 0;
+        self.clear_tables();
         for (k, v) in vkv {
-            let (kh, _) = self.findslot(&k);
-            self.keys.push(Some(k));
-            self.vals.push((v, kh));
-            self.kmap.insert(kh, (vi, vi));
-            vi += 1;
-        } //for
-        vi = nonleafs;
+            let h0 = self.autohash(&k);
+            self.heap_append(k, v, h0);
+        }
+        self.reheap();
+    } //heapify
+
+    // version of heapify for keys whose original hash indices were
+    // computed in advance, possibly in parallel: see BulkLoader
+    pub(crate) fn heapify_hashed(&mut self, items: Vec<(KT, VT, usize)>) {
+        self.clear_tables();
+        for (k, v, h0) in items {
+            self.heap_append(k, v, h0);
+        }
+        self.reheap();
+    } //heapify_hashed
+
+    // appends a pair at the end of vals, without restoring the heap
+    fn heap_append(&mut self, k: KT, v: VT, h0: usize) {
+        match self.probe_from(&k, h0) {
+            (kh, true, _) => {
+                // duplicate key: later value replaces earlier one
+                let vi = self.kmap[&kh].1;
+                self.vals[vi].0 = v;
+            }
+            (kh, false, h0) => self.append_at(kh, h0, k, v),
+        }
+    } //heap_append
+
+    fn clear_tables(&mut self) {
+        self.keys.clear();
+        self.khash.clear();
+        self.vals.clear();
+        self.kmap.clear();
+        self.passes.clear();
+        self.freekeys.clear();
+    }
+
+    // restores heap property of vals in place, O(n)
+    fn reheap(&mut self) {
+        let vn = self.vals.len();
+        let mut vi = vn - vn.div_ceil(2);
         while vi > 0 {
-            // heapify loop
             self.swapdown(vi - 1);
             vi -= 1;
-        } //while
-    } //heapify
+        }
+    } //reheap
 
     /// returns the number of key-value pairs in the HashHeap in constant time.
     pub fn len(&self) -> usize {
         self.vals.len()
     }
 
+    /// returns true if the HashHeap contains no key-value pairs
+    pub fn is_empty(&self) -> bool {
+        self.vals.is_empty()
+    }
+
+    /// returns the number of key-value pairs that the HashHeap can hold
+    /// without reallocating any of its internal tables.  Tombstones left
+    /// by removed keys count against this capacity.
+    pub fn capacity(&self) -> usize {
+        self.vals
+            .capacity()
+            .min(self.keys.capacity())
+            .min(self.kmap.capacity())
+    }
+
+    /// returns the number of tombstones: entries of the key table left by
+    /// removed keys that are still needed by the probe sequences of other
+    /// keys.
+    pub fn tombstones(&self) -> usize {
+        self.kmap.len() - self.vals.len()
+    }
+
+    /// The load factor is the number of entries in the key table, including
+    /// [tombstones](Self::tombstones), divided by its capacity.  It is 0 if
+    /// nothing has been allocated.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<u32,u32>::with_capacity(100, true);
+    ///   assert!(hh.is_empty());
+    ///   assert!(hh.capacity() >= 100);
+    ///   for i in 0..50 { hh.insert(i,i); }
+    ///   assert!(!hh.is_empty());
+    ///   assert!(hh.load_factor() > 0.0 && hh.load_factor() <= 1.0);
+    ///   assert_eq!(hh.tombstones(), 0);   // no removals yet
+    /// ```
+    pub fn load_factor(&self) -> f32 {
+        match self.kmap.capacity() {
+            0 => 0.0,
+            cap => (self.kmap.len() as f32) / (cap as f32),
+        }
+    }
+
+    /// Checks the internal consistency of the structure in O(n) time,
+    /// returning a description of the first problem found.  It verifies
+    /// the heap property, that every value is linked to a live key, and
+    /// that every live key is found again by probing from its hash index.
+    /// A HashHeap can only become inconsistent through a custom hash or
+    /// [rehash](Self::set_rehash) function that is not deterministic, or
+    /// a comparison function that is not a strict order, so this is meant
+    /// for testing such functions.  Probing is bounded by the size of the
+    /// key table, so a rehash function that cycles is reported instead of
+    /// looping forever.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut table = HashHeap::<u32,u32>::new_minheap();
+    ///   table.set_rehash(|h,c| h + c*c);
+    ///   for i in 0..100 { table.insert(i%7 + 7*(i%3), i); }
+    ///   table.remove(&8);
+    ///   assert_eq!(table.debug_validate(), Ok(()));
+    /// ```
+    pub fn debug_validate(&self) -> Result<(), String> {
+        let vn = self.vals.len();
+        for i in 1..vn {
+            if self.lessthan.call(&self.vals[parent(i)].0, &self.vals[i].0) {
+                return Err(format!("heap property violated at index {}", i));
+            }
+        }
+        for (vi, (_, h)) in self.vals.iter().enumerate() {
+            match self.kmap.get(h) {
+                Some(&(ki, vi2)) if vi2 == vi && self.keys[ki].is_some() => {}
+                _ => return Err(format!("value {} is not linked to a live key", vi)),
+            }
+        }
+        let mut live = 0;
+        for (ki, key) in self.keys.iter().enumerate() {
+            let Some(key) = key else { continue };
+            live += 1;
+            if self.khash[ki] != self.autohash(key) {
+                return Err(format!("stored hash index of key {} is stale", ki));
+            }
+            let Some((_, h)) = self.probe_length(ki) else {
+                return Err(format!("key {} is not found by probing", ki));
+            };
+            let vi = self.kmap[&h].1;
+            if vi >= vn || self.vals[vi].1 != h {
+                return Err(format!("key {} points to a wrong value", ki));
+            }
+        }
+        if live != vn {
+            return Err(format!("{} live keys but {} values", live, vn));
+        }
+        Ok(())
+    } //debug_validate
+
     /// reserves additional capacity
     pub fn reserve(&mut self, additional: usize) {
         self.kmap.reserve(additional);
@@ -571,33 +2409,371 @@ impl<KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
     } //reserve
 
     /// clears HashHeap without changing capacity.  Also resets [RandomState]
-    /// for hasher.
+    /// for hasher, unless the HashHeap was created with a
+    /// [seed](HashHeap::with_seed), which is kept.
     pub fn clear(&mut self) {
         self.vals.clear();
         self.keys.clear();
+        self.khash.clear();
         self.kmap.clear();
-        self.autostate = RandomState::new();
+        self.passes.clear();
+        self.freekeys.clear();
+        if let HashState::Random(_) = self.autostate {
+            self.autostate = HashState::Random(RandomState::new());
+        }
     } //clear
 
+    /// returns statistics about the shape of the heap in O(log n) time
+    /// ```
+    /// # use hashheap::*;
+    ///   let hh:HashHeap<u32,u32> = (0..10).map(|i|(i,i)).collect();
+    ///   let stats = hh.shape_stats();
+    ///   assert_eq!(stats.depth, 4);
+    ///   assert_eq!(stats.leaves, 5);
+    ///   assert_eq!(stats.levels, vec![1,2,4,3]);
+    ///   assert_eq!(hh.key_depth(&0), Some(0)); // min-hashheap
+    /// ```
+    pub fn shape_stats(&self) -> ShapeStats {
+        ShapeStats::for_size(self.vals.len())
+    }
+
+    /// returns the level of the heap at which the entry with the given key
+    /// is currently found, with the top entry at level 0.  O(1).
+    pub fn key_depth(&self, key: &KT) -> Option<usize> {
+        if let (h, true) = self.findslot(key) {
+            Some(heap_depth(self.kmap[&h].1))
+        } else {
+            None
+        }
+    }
+
+    /// Performs up to `budget` steps of maintenance, and returns true once
+    /// there's nothing left to do.  A removed key leaves a *tombstone* in
+    /// the hash table for as long as another key's probe sequence passes
+    /// through it, and its slot in the internal array of keys is only
+    /// reused by later insertions.  Each step either moves one key into
+    /// the first tombstone of its probe sequence, which shortens lookups
+    /// of the key and eventually clears all tombstones, or, once there are
+    /// no tombstones, moves one key from the end of the array of keys into
+    /// an unused slot so the array can shrink.  Each step takes O(1) time,
+    /// so the work can be spread over idle moments, or between
+    /// latency-critical operations, instead of pausing for an O(n)
+    /// rebuild.  A [ConstHashHeap] never needs such maintenance since its
+    /// removals never leave tombstones.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<u32,u32>::new_minheap();
+    ///   hh.set_rehash(|h,c| h + c*c);
+    ///   hh.set_hash(|k| (*k as usize) % 8);  // many collisions
+    ///   for i in 0..64 { hh.insert(i, i); }
+    ///   for i in 0..48 { hh.remove(&i); }
+    ///   let mut calls = 1;
+    ///   while !hh.maintain(4) { calls += 1; }
+    ///   assert!(calls > 1);
+    ///   assert!(hh.maintain(4));
+    ///   assert_eq!(hh.pop(), Some((48,48)));
+    /// ```
+    pub fn maintain(&mut self, budget: usize) -> bool {
+        let mut steps = 0;
+        while steps < budget && self.kmap.len() > self.vals.len() {
+            if self.maintcursor >= self.vals.len() {
+                self.maintcursor = 0;
+            }
+            self.relocate(self.maintcursor);
+            self.maintcursor += 1;
+            steps += 1;
+        }
+        if self.kmap.len() > self.vals.len() {
+            return false;
+        }
+        // without tombstones, every unused key index is in freekeys
+        if !self.freekeys.is_sorted() {
+            self.freekeys.sort_unstable();
+        }
+        while steps < budget && self.keys.len() > self.vals.len() {
+            let last = self.keys.len() - 1;
+            let fi = self.freekeys.pop().unwrap(); // largest unused index
+            if fi != last {
+                let h = self.probe_from(self.keys[last].as_ref().unwrap(), self.khash[last]).0;
+                self.keys.swap(fi, last);
+                self.khash.swap(fi, last);
+                if let Some((ki, _)) = self.kmap.get_mut(&h) {
+                    *ki = fi;
+                }
+            }
+            self.keys.pop();
+            self.khash.pop();
+            steps += 1;
+        }
+        self.keys.len() == self.vals.len()
+    } //maintain
+
+    // moves the key of the entry at heap index vi into the first tombstone
+    // of its probe sequence, if there is one
+    fn relocate(&mut self, vi: usize) {
+        let h = self.vals[vi].1;
+        let ki = self.kmap[&h].0;
+        if self.keys[ki].is_none() {
+            return;
+        }
+        let h0 = self.khash[ki];
+        let (mut g, mut collisions) = (h0, 0);
+        while g != h {
+            if let Some((gi, _)) = self.kmap.get(&g) {
+                if self.keys[*gi].is_none() {
+                    break;
+                }
+            }
+            collisions += 1;
+            g = self.rehash_index(h0, collisions);
+        }
+        if g == h {
+            return;
+        }
+        let key = self.keys[ki].take().unwrap();
+        self.unlink_key(h);
+        let (h2, _, _) = self.probe_from(&key, h0);
+        let k2 = self.link_key(h2, h0, key);
+        self.kmap.insert(h2, (k2, vi));
+        self.vals[vi].1 = h2;
+    } //relocate
+
+    /// Rebuilds the hash table from the live entries in O(n) time,
+    /// discarding all tombstones and unused slots at once, as an
+    /// alternative to spreading the work over calls to
+    /// [maintain](HashHeap::maintain).  If a [RandomState] is given, keys
+    /// are rehashed with it; otherwise the current one is kept.  The order
+    /// of the entries in the heap does not change.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<u32,u32>::new_minheap();
+    ///   for i in 0..64 { hh.insert(i, i); }
+    ///   for i in 0..48 { hh.remove(&i); }
+    ///   hh.refresh(Some(std::collections::hash_map::RandomState::new()));
+    ///   assert!(hh.maintain(0));  // nothing left to do
+    ///   assert_eq!(hh.get(&50), Some(&50));
+    ///   assert_eq!(hh.pop(), Some((48,48)));
+    /// ```
+    pub fn refresh(&mut self, state: Option<RandomState>) {
+        let mut vals = core::mem::take(&mut self.vals);
+        let pairs: Vec<(KT, VT)> = vals
+            .drain(..)
+            .map(|(v, h)| (self.keys[self.kmap[&h].0].take().unwrap(), v))
+            .collect();
+        if let Some(state) = state {
+            self.autostate = HashState::Random(state);
+        }
+        self.keys.clear();
+        self.khash.clear();
+        self.kmap.clear();
+        self.passes.clear();
+        self.freekeys.clear();
+        self.maintcursor = 0;
+        self.heapify(pairs); // already in heap order
+    } //refresh
+
+    /// Purges all [tombstones](Self::tombstones) by rebuilding the hash
+    /// table with the current hash state: the same as `refresh(None)`.
+    /// Tombstones are normally removed as soon as no probe sequence passes
+    /// through them, but a custom rehash function with long probe
+    /// sequences can keep many of them alive.  O(n)
+    pub fn compact(&mut self) {
+        if self.tombstones() > 0 || !self.freekeys.is_empty() {
+            self.refresh(None);
+        }
+    }
+
+    /// [Compacts](Self::compact) the HashHeap and releases the capacity of
+    /// its internal tables beyond what its entries need.  O(n)
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<u32,u32>::with_capacity(1000, false);
+    ///   for i in 0..1000 { hh.insert(i, i); }
+    ///   hh.retain(|k,_| *k < 10);
+    ///   hh.shrink_to_fit();
+    ///   assert_eq!(hh.tombstones(), 0);
+    ///   assert!(hh.capacity() < 1000);
+    ///   assert_eq!(hh.pop(), Some((0,0)));
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.compact();
+        self.vals.shrink_to_fit();
+        self.keys.shrink_to_fit();
+        self.khash.shrink_to_fit();
+        self.kmap.shrink_to_fit();
+        self.passes.shrink_to_fit();
+        self.freekeys.shrink_to_fit();
+    } //shrink_to_fit
+
     /// returns true if the structure is a max-hashheap and false if it's a
     /// min-hashheap.
     pub fn is_max_hashheap(&self) -> bool {
         self.minmax
     }
 
-    /*
-    pub fn diagnostic(&self) {
-      if self.tc>0 {println!("total collisions: {}",self.tc);}
-    }
-    */
+    /// Returns statistics about the probe sequences of the keys, which
+    /// measure how well the hash and [rehash](Self::set_rehash) functions
+    /// spread the keys.  This operation runs in O(n) time.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut table = HashHeap::<u32,u32>::new_minheap();
+    ///   table.set_hash(|k| (*k as usize) % 4);   // a poor hash function
+    ///   for i in 0..8 { table.insert(i, i); }
+    ///   table.remove(&0);
+    ///   let stats = table.probe_stats();
+    ///   assert_eq!(stats.keys, 7);
+    ///   assert!(stats.collisions > 0 && stats.max_probe >= 4);
+    ///   assert_eq!(stats.tombstones, table.tombstones());
+    ///   assert!(stats.average_probe() > 1.0);
+    /// ```
+    pub fn probe_stats(&self) -> ProbeStats {
+        let mut stats = ProbeStats {
+            keys: self.vals.len(),
+            collisions: 0,
+            max_probe: 0,
+            tombstones: self.tombstones(),
+        };
+        for ki in 0..self.keys.len() {
+            if let Some((c, _)) = self.probe_length(ki) {
+                stats.collisions += c;
+                stats.max_probe = stats.max_probe.max(c);
+            }
+        }
+        stats
+    } //probe_stats
+
+    // number of collisions before the live key at ki is found by probing
+    // from its stored hash index, with the index where it's found, or None
+    // if it's not found within as many probes as there are entries in kmap
+    fn probe_length(&self, ki: usize) -> Option<(usize, usize)> {
+        self.keys[ki].as_ref()?;
+        let h0 = self.khash[ki];
+        let (mut h, mut collisions) = (h0, 0);
+        loop {
+            match self.kmap.get(&h) {
+                Some(&(ki2, _)) if ki2 == ki => return Some((collisions, h)),
+                Some(_) if collisions < self.kmap.len() => {
+                    collisions += 1;
+                    h = self.rehash_index(h0, collisions);
+                }
+                _ => return None,
+            }
+        }
+    } //probe_length
 } // impl HashHeap
 
+impl<KT: Hash + Eq, VT: PartialOrd + Copy + Into<f64>> HashHeap<KT, VT> {
+    /// Version of [priority_histogram](HashHeap::priority_histogram) for
+    /// numeric values that divides the range from the smallest to the
+    /// largest value into the given number of buckets of equal width.
+    /// Returns the lower edge of each bucket with the number of values in
+    /// it; the largest value is counted in the last bucket.  Returns an
+    /// empty vector if the structure is empty or `buckets` is zero.  O(n)
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut jobs = HashHeap::<u32,u32>::new_minheap();
+    ///   for (k,p) in [(1,0),(2,1),(3,4),(4,5),(5,8)] { jobs.insert(k,p); }
+    ///   assert_eq!(jobs.priority_histogram_auto(2), vec![(0.0,2),(4.0,3)]);
+    /// ```
+    pub fn priority_histogram_auto(&self, buckets: usize) -> Vec<(f64, usize)> {
+        let vals = || self.vals.iter().map(|(v, _)| (*v).into());
+        let lo = vals().fold(f64::INFINITY, f64::min);
+        let hi = vals().fold(f64::NEG_INFINITY, f64::max);
+        if buckets == 0 || lo > hi {
+            return Vec::new();
+        }
+        let width = (hi - lo) / buckets as f64;
+        let mut counts = vec![0; buckets];
+        for v in vals() {
+            let b = if width > 0.0 { ((v - lo) / width) as usize } else { 0 };
+            counts[b.min(buckets - 1)] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| (lo + width * i as f64, c))
+            .collect()
+    } //priority_histogram_auto
+} // impl numeric HashHeap
+
 //default
 impl<KT: Hash + Eq, VT: PartialOrd> Default for HashHeap<KT, VT> {
     fn default() -> Self {
         Self::new_maxheap()
     }
-} // impl default
+} // impl default
+
+/// Two HashHeaps are equal if they have the same keys with equal values,
+/// regardless of their internal layout, hash functions or whether they
+/// are min- or max-hashheaps.  Comparing them takes O(n) time.
+/// ```
+/// # use hashheap::*;
+///   let mut a = HashHeap::<&str,u32>::new_maxheap();
+///   let mut b = HashHeap::<&str,u32>::new_minheap();
+///   for (k,v) in [("x",1),("y",2),("z",3)] { a.insert(k,v); }
+///   for (k,v) in [("z",3),("y",2),("x",1)] { b.insert(k,v); }
+///   assert_eq!(a, b);
+///   b.modify(&"x", |v| *v = 4);
+///   assert_ne!(a, b);
+/// ```
+impl<KT: Hash + Eq, VT: PartialEq> PartialEq for HashHeap<KT, VT> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+impl<KT: Hash + Eq, VT: Eq> Eq for HashHeap<KT, VT> {}
+
+impl<KT: Hash + Eq, VT> HashHeap<KT, VT> {
+    // writes the heap as an indented tree, one entry per line in preorder,
+    // indented by two spaces per level below the top
+    fn write_tree<F>(&self, out: &mut dyn core::fmt::Write, entry: F) -> core::fmt::Result
+    where
+        F: Fn(&mut dyn core::fmt::Write, &KT, &VT) -> core::fmt::Result,
+    {
+        let pairs: Vec<(&KT, &VT)> = self.iter().collect();
+        let mut stack = if pairs.is_empty() { vec![] } else { vec![0] };
+        while let Some(i) = stack.pop() {
+            write!(out, "{:1$}", "", 2 * heap_depth(i))?;
+            entry(out, pairs[i].0, pairs[i].1)?;
+            writeln!(out)?;
+            for c in [right(i), left(i)] {
+                if c < pairs.len() {
+                    stack.push(c);
+                }
+            }
+        }
+        Ok(())
+    } //write_tree
+}
+
+impl<KT: Hash + Eq + core::fmt::Debug, VT: core::fmt::Debug> HashHeap<KT, VT> {
+    /// Returns the heap as an indented tree with one `key: value` pair per
+    /// line, using their [Debug](core::fmt::Debug) implementations.  Each
+    /// entry is followed by its subtrees, indented by two more spaces, so
+    /// that the top entry is on the first line.  The [Display](core::fmt::Display)
+    /// implementation prints the same tree with the Display implementations
+    /// of keys and values.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut hh = HashHeap::<&str,u32>::new_minheap();
+    ///   for (k,v) in [("a",1),("b",2),("c",3),("d",4)] { hh.insert(k,v); }
+    ///   assert_eq!(hh.format_tree(), "\"a\": 1\n  \"b\": 2\n    \"d\": 4\n  \"c\": 3\n");
+    ///   assert_eq!(hh.to_string(), "a: 1\n  b: 2\n    d: 4\n  c: 3\n");
+    /// ```
+    pub fn format_tree(&self) -> String {
+        let mut out = String::new();
+        let _ = self.write_tree(&mut out, |f, k, v| write!(f, "{:?}: {:?}", k, v));
+        out
+    }
+}
+
+impl<KT: Hash + Eq + core::fmt::Display, VT: core::fmt::Display> core::fmt::Display
+    for HashHeap<KT, VT>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.write_tree(f, |f, k, v| write!(f, "{}: {}", k, v))
+    }
+}
 
 /*
 use core::fmt::Debug;
@@ -629,8 +2805,40 @@ impl<KT: Hash + Eq + Clone, VT: PartialOrd + Clone> Clone for HashHeap<KT, VT> {
 } // impl clone
 */
 
+// The derived Clone copies the RandomState, which must not change while
+// the structure is not empty: kmap and the cached hash indices in khash
+// were computed with it.
+impl<KT: Hash + Eq + Clone, VT: Clone> HashHeap<KT, VT> {
+    /// Returns a copy of the HashHeap that hashes keys with the given
+    /// [RandomState] instead of the one of this structure, for users who
+    /// want the copy to be reseeded.  A plain [Clone::clone] keeps the same
+    /// hasher state.  The hash table of the copy is rebuilt while the heap
+    /// order of the entries is preserved.  This operation runs in O(n) time.
+    /// The comparison function and [InsertPolicy] are kept, as is a hash
+    /// function set with [HashHeap::set_hash], which makes the new state
+    /// irrelevant.
+    /// ```
+    /// # use hashheap::*;
+    /// # use std::collections::hash_map::RandomState;
+    ///   let mut hh = HashHeap::<&str,u32>::new_minheap();
+    ///   hh.insert("a",2); hh.insert("b",1);
+    ///   let copy = hh.clone_with_hasher(RandomState::new());
+    ///   assert_eq!(copy.get(&"a"), Some(&2));
+    ///   assert_eq!(copy.peek(), Some((&"b",&1)));
+    /// ```
+    pub fn clone_with_hasher(&self, state: RandomState) -> Self {
+        let mut hh = self.empty_like(self.vals.len());
+        hh.autostate = HashState::Random(state);
+        let pairs = self.vals.iter().filter_map(|(v, h)| {
+            self.keys[self.kmap[h].0].clone().map(|k| (k, v.clone()))
+        });
+        hh.heapify(pairs.collect()); // already in heap order
+        hh
+    } //clone_with_hasher
+} // impl clone_with_hasher
+
 /// indexed get
-impl<KT: Hash + Eq, VT: PartialOrd> core::ops::Index<&KT> for HashHeap<KT, VT> {
+impl<KT: Hash + Eq, VT> core::ops::Index<&KT> for HashHeap<KT, VT> {
     type Output = VT;
     fn index(&self, index: &KT) -> &Self::Output {
         self.get(index).expect("key not found")
@@ -645,6 +2853,35 @@ impl<KT: Hash + Eq, VT: PartialOrd> From<Vec<(KT, VT)>> for HashHeap<KT, VT> {
     }
 }
 
+/// Like the conversion from a vector, this always returns a max-hashheap.
+/// The pairs are cloned.
+impl<KT: Hash + Eq + Clone, VT: PartialOrd + Clone> From<&[(KT, VT)]> for HashHeap<KT, VT> {
+    fn from(pairs: &[(KT, VT)]) -> HashHeap<KT, VT> {
+        HashHeap::from_pairs(pairs.to_vec(), true)
+    }
+}
+
+/// Converts a BTreeMap into a max-hashheap in O(n) time with *heapify*.
+/// Because the keys of a BTreeMap are distinct, no key is ever replaced.
+/// Its entries are sorted by key rather than by value, so the order of the
+/// map does not save any comparisons when building the heap.
+/// ```
+/// # use hashheap::*;
+///   let mut scores = std::collections::BTreeMap::new();
+///   scores.insert("ann", 7);
+///   scores.insert("bob", 9);
+///   scores.insert("cat", 8);
+///   let mut hh = HashHeap::from(scores);
+///   assert_eq!(hh.pop(), Some(("bob",9)));
+///   let hh2 = HashHeap::from(&[("x",1),("y",2)][..]);
+///   assert_eq!(hh2.peek(), Some((&"y",&2)));
+/// ```
+impl<KT: Hash + Eq, VT: PartialOrd> From<std::collections::BTreeMap<KT, VT>> for HashHeap<KT, VT> {
+    fn from(map: std::collections::BTreeMap<KT, VT>) -> HashHeap<KT, VT> {
+        HashHeap::from_pairs(map.into_iter().collect(), true)
+    }
+}
+
 /// The implementation of this `From` trait always returns a min-hashheap.
 /// For a max-hashheap, call [Iterator::collect] followed by [HashHeap::from_pairs]
 impl<KT: Hash + Eq, VT: PartialOrd> FromIterator<(KT, VT)> for HashHeap<KT, VT> {
@@ -653,12 +2890,47 @@ impl<KT: Hash + Eq, VT: PartialOrd> FromIterator<(KT, VT)> for HashHeap<KT, VT>
     }
 }
 
+/// Adds the pairs of an iterator as [HashHeap::insert] would, replacing
+/// the values of existing keys.  When the batch is large compared to the
+/// current size, including when the HashHeap is empty, the pairs are
+/// appended without ordering them and the heap is rebuilt with a single
+/// O(n) *heapify* pass; otherwise they are inserted one at a time.
+/// ```
+/// # use hashheap::*;
+///   let mut hh = HashHeap::<u32,u32>::new_maxheap();
+///   hh.extend((0..100).map(|i|(i,i)));
+///   hh.extend(vec![(5,500), (200,200)]);
+///   assert_eq!(hh.len(), 101);
+///   assert_eq!(hh.pop(), Some((5,500)));
+///   assert_eq!(hh.pop(), Some((200,200)));
+/// ```
+impl<KT: Hash + Eq, VT> Extend<(KT, VT)> for HashHeap<KT, VT> {
+    fn extend<I: IntoIterator<Item = (KT, VT)>>(&mut self, iter: I) {
+        let batch: Vec<(KT, VT)> = iter.into_iter().collect();
+        let n = self.vals.len() + batch.len();
+        self.reserve(batch.len());
+        // m insertions cost up to m*log(n) comparisons, heapify about 2n
+        if batch.len() * heap_depth(n) > 2 * n {
+            for (k, v) in batch {
+                let h0 = self.autohash(&k);
+                self.heap_append(k, v, h0);
+            }
+            self.reheap();
+        } else {
+            for (k, v) in batch {
+                self.insert(k, v);
+            }
+        }
+    } //extend
+}
+
 ////// iterator implementations
 
 /// This iterator is returned by the [HashHeap::keys] function
 pub struct KeyIter<'a, KT> {
     keys: &'a [Option<KT>],
     index: usize,
+    remaining: usize, // number of live keys not yet returned
 }
 impl<'a, KT> Iterator for KeyIter<'a, KT> {
     type Item = &'a KT;
@@ -671,10 +2943,16 @@ impl<'a, KT> Iterator for KeyIter<'a, KT> {
             None
         } else {
             self.index += 1;
+            self.remaining -= 1;
             self.keys[self.index - 1].as_ref()
         }
     } //next
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
 } // keys iterator
+impl<'a, KT> ExactSizeIterator for KeyIter<'a, KT> {}
+impl<'a, KT> core::iter::FusedIterator for KeyIter<'a, KT> {}
 
 /// This iterator is returned by the [HashHeap::values] function
 pub struct ValIter<'a, VT> {
@@ -692,14 +2970,20 @@ impl<'a, VT> Iterator for ValIter<'a, VT> {
             Some(&self.vals[self.index - 1].0)
         }
     } //next
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.vals.len() - self.index;
+        (n, Some(n))
+    }
 } // vals iterator
+impl<'a, VT> ExactSizeIterator for ValIter<'a, VT> {}
+impl<'a, VT> core::iter::FusedIterator for ValIter<'a, VT> {}
 
 /// This iterator is returned by the [HashHeap::iter] function
 pub struct KeyValIter<'a, KT, VT> {
     hh: &'a HashHeap<KT, VT>,
     index: usize,
 }
-impl<'a, KT: Hash + Eq, VT: PartialOrd> Iterator for KeyValIter<'a, KT, VT> {
+impl<'a, KT: Hash + Eq, VT> Iterator for KeyValIter<'a, KT, VT> {
     type Item = (&'a KT, &'a VT);
     fn next(&mut self) -> Option<Self::Item> {
         let vn = self.hh.vals.len();
@@ -713,15 +2997,99 @@ impl<'a, KT: Hash + Eq, VT: PartialOrd> Iterator for KeyValIter<'a, KT, VT> {
         }
         None
     } //next
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // every value is linked to a live key
+        let n = self.hh.vals.len() - self.index;
+        (n, Some(n))
+    }
 } // key-val iterator
+impl<'a, KT: Hash + Eq, VT> ExactSizeIterator for KeyValIter<'a, KT, VT> {}
+impl<'a, KT: Hash + Eq, VT> core::iter::FusedIterator for KeyValIter<'a, KT, VT> {}
+
+/// This iterator is returned by the [HashHeap::equal_range] function
+pub struct EqualRange<'a, KT, VT> {
+    hh: &'a HashHeap<KT, VT>,
+    val: &'a VT,
+    stack: Vec<usize>,
+}
+impl<'a, KT: Hash + Eq, VT: PartialOrd> Iterator for EqualRange<'a, KT, VT> {
+    type Item = (&'a KT, &'a VT);
+    fn next(&mut self) -> Option<Self::Item> {
+        let vn = self.hh.vals.len();
+        while let Some(i) = self.stack.pop() {
+            let (v, iv) = &self.hh.vals[i];
+            if self.hh.lessthan.call(v, self.val) {
+                continue; // prune subtree
+            }
+            if left(i) < vn {
+                self.stack.push(left(i));
+            }
+            if right(i) < vn {
+                self.stack.push(right(i));
+            }
+            if v == self.val {
+                let (ki, _) = self.hh.kmap[iv];
+                if let Some(k) = &self.hh.keys[ki] {
+                    return Some((k, v));
+                }
+            }
+        }
+        None
+    } //next
+} // equal_range iterator
+
+/// This iterator is returned by the [HashHeap::values_in_range] function
+pub struct ValuesInRange<'a, KT, VT, R> {
+    hh: &'a HashHeap<KT, VT>,
+    range: R,
+    stack: Vec<usize>,
+}
+impl<'a, KT: Hash + Eq, VT: PartialOrd, R: core::ops::RangeBounds<VT>> Iterator
+    for ValuesInRange<'a, KT, VT, R>
+{
+    type Item = (&'a KT, &'a VT);
+    fn next(&mut self) -> Option<Self::Item> {
+        use core::ops::Bound::*;
+        let vn = self.hh.vals.len();
+        while let Some(i) = self.stack.pop() {
+            let (v, iv) = &self.hh.vals[i];
+            // the bound that v lies beyond, if any
+            let beyond = match (self.range.start_bound(), self.range.end_bound()) {
+                (Included(b), _) if v < b => Some(b),
+                (Excluded(b), _) if v <= b => Some(b),
+                (_, Included(b)) if v > b => Some(b),
+                (_, Excluded(b)) if v >= b => Some(b),
+                _ => None,
+            };
+            match beyond {
+                Some(b) if self.hh.lessthan.call(v, b) => continue, // prune subtree
+                _ => {}
+            }
+            if left(i) < vn {
+                self.stack.push(left(i));
+            }
+            if right(i) < vn {
+                self.stack.push(right(i));
+            }
+            if self.range.contains(v) {
+                let (ki, _) = self.hh.kmap[iv];
+                if let Some(k) = &self.hh.keys[ki] {
+                    return Some((k, v));
+                }
+            }
+        }
+        None
+    } //next
+} // values_in_range iterator
 
-impl<'a, KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
+impl<'a, KT: Hash + Eq, VT> HashHeap<KT, VT> {
     /// returns an iterator over the keys of the structure in no particular
     /// order
     pub fn keys(&'a self) -> KeyIter<'a, KT> {
         KeyIter {
             keys: &self.keys,
             index: 0,
+            remaining: self.vals.len(),
         }
     } //keys
 
@@ -744,6 +3112,68 @@ impl<'a, KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
         KeyValIter { hh: self, index: 0 }
     }
 
+    /// Returns an iterator over references to the `(key,value)` pairs in
+    /// order of priority, without changing the HashHeap, so that the top
+    /// of a live queue can be inspected.  The iterator keeps its own small
+    /// heap of the positions that may come next, starting with the root
+    /// and adding the two children of each entry returned.  The first k
+    /// entries therefore take O(k log k) time, and the whole iteration
+    /// O(n log n) time and O(n) extra space.  Entries of equal priority
+    /// may appear in any order.
+    /// ```
+    /// # use hashheap::*;
+    ///   let hh:HashHeap<u32,u32> = (0..20).map(|i|(i,(i*7)%20)).collect();
+    ///   let top:Vec<_> = hh.iter_sorted().take(3).map(|(_,v)|*v).collect();
+    ///   assert_eq!(top, vec![0,1,2]);
+    ///   assert_eq!(hh.iter_sorted().count(), 20);
+    ///   assert_eq!(hh.len(), 20);
+    /// ```
+    pub fn iter_sorted(&'a self) -> SortedIter<'a, KT, VT> {
+        let frontier = if self.vals.is_empty() { vec![] } else { vec![0] };
+        SortedIter {
+            hh: self,
+            frontier,
+            remaining: self.vals.len(),
+        }
+    }
+
+    /// Returns an iterator over the (at most) k highest-priority pairs in
+    /// order of priority, without changing the HashHeap.  Only O(k)
+    /// entries of the heap are visited, so this takes O(k log k) time
+    /// regardless of the size of the structure.  It's the same as
+    /// `iter_sorted().take(k)`.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut jobs = HashHeap::<u32,u32>::new_maxheap();
+    ///   for i in 0..100000 { jobs.insert(i, i % 1000); }
+    ///   let urgent:Vec<_> = jobs.top_k(3).map(|(_,u)|*u).collect();
+    ///   assert_eq!(urgent, vec![999,999,999]);
+    ///   assert_eq!(jobs.top_k(200000).count(), 100000);
+    /// ```
+    pub fn top_k(&'a self, k: usize) -> core::iter::Take<SortedIter<'a, KT, VT>> {
+        self.iter_sorted().take(k)
+    }
+
+    /// Returns the pair at position n (counting from 0) in order of
+    /// priority, without changing the HashHeap, so `nth_priority(0)` is the
+    /// same as [peek](HashHeap::peek).  The selection only visits the n+1
+    /// highest-priority entries and their children, keeping them in a
+    /// separate heap, so it takes O(n log n) time regardless of the size of
+    /// the structure.  Among entries with equal priority the order is
+    /// unspecified.  Returns None if there are no more than n entries.
+    /// ```
+    /// # use hashheap::*;
+    ///   let mut scores = HashHeap::<&str,u32>::new_maxheap();
+    ///   for (p,s) in [("a",70),("b",95),("c",80),("d",60)] { scores.insert(p,s); }
+    ///   assert_eq!(scores.nth_priority(0), Some((&"b",&95)));
+    ///   assert_eq!(scores.nth_priority(2), Some((&"a",&70)));
+    ///   assert_eq!(scores.nth_priority(4), None);
+    ///   assert_eq!(scores.len(), 4);
+    /// ```
+    pub fn nth_priority(&'a self, n: usize) -> Option<(&'a KT, &'a VT)> {
+        self.iter_sorted().nth(n)
+    }
+
     /// returns a consuming iterator over `(key,value)` in order of
     /// priority (via [Self::pop]).  The hashheap will be emptied by
     /// the iterator
@@ -754,7 +3184,7 @@ impl<'a, KT: Hash + Eq, VT: PartialOrd> HashHeap<KT, VT> {
 
 /// The IntoIterator for references is the same as calling [HashHeap::iter],
 /// and will therefore return references in **arbitrary order**.
-impl<'t, KT: Hash + Eq, VT: PartialOrd> IntoIterator for &'t HashHeap<KT, VT> {
+impl<'t, KT: Hash + Eq, VT> IntoIterator for &'t HashHeap<KT, VT> {
     type Item = (&'t KT, &'t VT);
     type IntoIter = KeyValIter<'t, KT, VT>;
 
@@ -770,35 +3200,263 @@ impl<'t, KT: Hash + Eq, VT: PartialOrd> IntoIterator for &'t HashHeap<KT, VT> {
 /// iterator is thus at least O(n*log n).
 /// In constrast, the non-consuming iterators all enumerate references
 /// in arbitrary order.
-pub struct IntoIter<KT, VT>(HashHeap<KT, VT>);
-impl<KT: Hash + Eq, VT: PartialOrd> Iterator for IntoIter<KT, VT> {
+///
+/// The iterator is also double-ended: [next_back](DoubleEndedIterator::next_back)
+/// returns the pair with the *lowest* priority, so that `rev()` returns
+/// the pairs in reverse sorted order.  A binary heap cannot remove its
+/// lowest-priority entry efficiently, so the first call to next_back sorts
+/// the remaining pairs in O(n log n) time, after which both ends take O(1)
+/// time.
+/// ```
+/// # use hashheap::*;
+///   let hh:HashHeap<&str,u32> = vec![("a",3),("b",1),("c",4),("d",2)].into();
+///   let mut iter = hh.into_iter();   // max-hashheap
+///   assert_eq!(iter.next(), Some(("c",4)));
+///   assert_eq!(iter.next_back(), Some(("b",1)));
+///   let rest:Vec<_> = iter.rev().map(|(_,v)|v).collect();
+///   assert_eq!(rest, vec![2,3]);
+/// ```
+pub struct IntoIter<KT, VT> {
+    hh: HashHeap<KT, VT>,
+    sorted: Option<std::collections::VecDeque<(KT, VT)>>, // after next_back
+}
+impl<KT: Hash + Eq, VT> Iterator for IntoIter<KT, VT> {
     type Item = (KT, VT);
     fn next(&mut self) -> Option<(KT, VT)> {
-        self.0.pop()
+        match &mut self.sorted {
+            Some(pairs) => pairs.pop_front(),
+            None => self.hh.pop(),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.sorted.as_ref().map_or(self.hh.len(), |p| p.len());
+        (n, Some(n))
     }
 } //impl IntoIter
+impl<KT: Hash + Eq, VT> DoubleEndedIterator for IntoIter<KT, VT> {
+    fn next_back(&mut self) -> Option<(KT, VT)> {
+        let hh = &mut self.hh;
+        self.sorted
+            .get_or_insert_with(|| hh.drain_sorted().collect())
+            .pop_back()
+    }
+}
+impl<KT: Hash + Eq, VT> ExactSizeIterator for IntoIter<KT, VT> {}
+impl<KT: Hash + Eq, VT> core::iter::FusedIterator for IntoIter<KT, VT> {}
 
 /// The consuming iterator is implemented by [IntoIter] and will return
 /// the owned values in **sorted order**
-impl<KT: Hash + Eq, VT: PartialOrd> IntoIterator for HashHeap<KT, VT> {
+impl<KT: Hash + Eq, VT> IntoIterator for HashHeap<KT, VT> {
     type Item = (KT, VT);
     type IntoIter = IntoIter<KT, VT>;
 
     fn into_iter(self) -> Self::IntoIter {
-        IntoIter(self)
+        IntoIter {
+            hh: self,
+            sorted: None,
+        }
     }
 } // consuming iterator
 
+/// Iterator returned by [HashHeap::into_keys]
+pub struct IntoKeys<KT> {
+    keys: std::vec::IntoIter<Option<KT>>,
+    remaining: usize,
+}
+impl<KT> Iterator for IntoKeys<KT> {
+    type Item = KT;
+    fn next(&mut self) -> Option<KT> {
+        let k = self.keys.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some(k)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<KT> ExactSizeIterator for IntoKeys<KT> {}
+impl<KT> core::iter::FusedIterator for IntoKeys<KT> {}
+
+/// Iterator returned by [HashHeap::into_values]
+pub struct IntoValues<KT, VT>(IntoIter<KT, VT>);
+impl<KT: Hash + Eq, VT> Iterator for IntoValues<KT, VT> {
+    type Item = VT;
+    fn next(&mut self) -> Option<VT> {
+        self.0.next().map(|(_, v)| v)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+impl<KT: Hash + Eq, VT> DoubleEndedIterator for IntoValues<KT, VT> {
+    fn next_back(&mut self) -> Option<VT> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+impl<KT: Hash + Eq, VT> ExactSizeIterator for IntoValues<KT, VT> {}
+impl<KT: Hash + Eq, VT> core::iter::FusedIterator for IntoValues<KT, VT> {}
+
+/// Iterator returned by [HashHeap::extract_if]
+pub struct ExtractIf<'a, KT: Hash + Eq, VT, F: FnMut(&KT, &VT) -> bool> {
+    hh: &'a mut HashHeap<KT, VT>,
+    pred: F,
+    index: usize, // next position in vals to test
+    removed: bool,
+}
+impl<'a, KT: Hash + Eq, VT, F: FnMut(&KT, &VT) -> bool> Iterator
+    for ExtractIf<'a, KT, VT, F>
+{
+    type Item = (KT, VT);
+    fn next(&mut self) -> Option<(KT, VT)> {
+        let hh = &mut *self.hh;
+        while self.index < hh.vals.len() {
+            let (v, h) = &hh.vals[self.index];
+            let (ki, _) = hh.kmap[h];
+            let h = *h;
+            if !(self.pred)(hh.keys[ki].as_ref().unwrap(), v) {
+                self.index += 1;
+                continue;
+            }
+            // the last entry, not yet tested, takes this position
+            hh.heapswap(self.index, hh.vals.len() - 1);
+            let (V, _) = hh.vals.pop().unwrap();
+            let K = hh.keys[ki].take().unwrap();
+            hh.unlink_key(h);
+            self.removed = true;
+            return Some((K, V));
+        }
+        None
+    }
+} //impl ExtractIf
+
+impl<'a, KT: Hash + Eq, VT, F: FnMut(&KT, &VT) -> bool> Drop
+    for ExtractIf<'a, KT, VT, F>
+{
+    fn drop(&mut self) {
+        if self.removed {
+            self.hh.reheap();
+        }
+    }
+}
+
+/// Iterator returned by [HashHeap::iter_sorted]
+pub struct SortedIter<'a, KT, VT> {
+    hh: &'a HashHeap<KT, VT>,
+    frontier: Vec<usize>, // heap of heap indices, best first
+    remaining: usize,
+}
+impl<'a, KT, VT> SortedIter<'a, KT, VT> {
+    // true if the entry at frontier position a has higher priority than b
+    fn better(&self, a: usize, b: usize) -> bool {
+        let vals = &self.hh.vals;
+        self.hh.lessthan.call(&vals[self.frontier[b]].0, &vals[self.frontier[a]].0)
+    }
+
+    fn push(&mut self, vi: usize) {
+        if vi >= self.hh.vals.len() {
+            return;
+        }
+        self.frontier.push(vi);
+        let mut i = self.frontier.len() - 1;
+        while i > 0 && self.better(i, parent(i)) {
+            self.frontier.swap(i, parent(i));
+            i = parent(i);
+        }
+    } //push
+
+    fn pop(&mut self) -> Option<usize> {
+        if self.frontier.is_empty() {
+            return None;
+        }
+        let top = self.frontier.swap_remove(0);
+        let (size, mut i) = (self.frontier.len(), 0);
+        loop {
+            let mut si = i;
+            for c in [left(i), right(i)] {
+                if c < size && self.better(c, si) {
+                    si = c;
+                }
+            }
+            if si == i {
+                break;
+            }
+            self.frontier.swap(i, si);
+            i = si;
+        }
+        Some(top)
+    } //pop
+}
+impl<'a, KT, VT> Iterator for SortedIter<'a, KT, VT> {
+    type Item = (&'a KT, &'a VT);
+    fn next(&mut self) -> Option<Self::Item> {
+        let vi = self.pop()?;
+        self.push(left(vi));
+        self.push(right(vi));
+        self.remaining -= 1;
+        let (v, h) = &self.hh.vals[vi];
+        let (ki, _) = self.hh.kmap[h];
+        self.hh.keys[ki].as_ref().map(|k| (k, v))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+} //impl SortedIter
+impl<'a, KT, VT> ExactSizeIterator for SortedIter<'a, KT, VT> {}
+impl<'a, KT, VT> core::iter::FusedIterator for SortedIter<'a, KT, VT> {}
+
+/// Iterator returned by [HashHeap::drain]
+pub struct Drain<'a, KT: Hash + Eq, VT>(&'a mut HashHeap<KT, VT>);
+impl<'a, KT: Hash + Eq, VT> Iterator for Drain<'a, KT, VT> {
+    type Item = (KT, VT);
+    fn next(&mut self) -> Option<(KT, VT)> {
+        self.0.pop_last()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+}
+impl<'a, KT: Hash + Eq, VT> ExactSizeIterator for Drain<'a, KT, VT> {}
+impl<'a, KT: Hash + Eq, VT> core::iter::FusedIterator for Drain<'a, KT, VT> {}
+impl<'a, KT: Hash + Eq, VT> Drop for Drain<'a, KT, VT> {
+    fn drop(&mut self) {
+        self.0.clear_tables();
+    }
+}
+
+/// Iterator returned by [HashHeap::drain_sorted]
+pub struct DrainSorted<'a, KT: Hash + Eq, VT>(&'a mut HashHeap<KT, VT>);
+impl<'a, KT: Hash + Eq, VT> Iterator for DrainSorted<'a, KT, VT> {
+    type Item = (KT, VT);
+    fn next(&mut self) -> Option<(KT, VT)> {
+        self.0.pop()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+}
+impl<'a, KT: Hash + Eq, VT> ExactSizeIterator for DrainSorted<'a, KT, VT> {}
+impl<'a, KT: Hash + Eq, VT> core::iter::FusedIterator for DrainSorted<'a, KT, VT> {}
+impl<'a, KT: Hash + Eq, VT> Drop for DrainSorted<'a, KT, VT> {
+    fn drop(&mut self) {
+        self.0.clear_tables();
+    }
+}
+
 /// Non-consuming iterator, but will empty the heap via pop()
 pub struct PriorityQueue<'a,KT,VT>(&'a mut HashHeap<KT,VT>);
-impl<'a,KT: Hash + Eq, VT: PartialOrd> Iterator
+impl<'a,KT: Hash + Eq, VT> Iterator
 for PriorityQueue<'a,KT,VT>
 {
   type Item = (KT,VT);
   fn next(&mut self) -> Option<Self::Item> {
     self.0.pop()
   }
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.0.len(), Some(self.0.len()))
+  }
 }
+impl<'a,KT: Hash + Eq, VT> ExactSizeIterator for PriorityQueue<'a,KT,VT> {}
+impl<'a,KT: Hash + Eq, VT> core::iter::FusedIterator for PriorityQueue<'a,KT,VT> {}
 
 //////////testing
 #[cfg(test)]
@@ -826,4 +3484,525 @@ mod tests {
             println!("consuming iterator key {} : val {}", key, val);
         }
     } //it_works
+
+    #[test]
+    fn const_drain_reuse() {
+        let mut hp = ConstHashHeap::<u32, u32, 8>::new(false);
+        for i in 0..8 {
+            hp.insert(i, 10 - i);
+        }
+        let mut total = 0;
+        for (k, v) in hp.drain() {
+            total += k + v;
+        }
+        assert_eq!(total, 80);
+        assert_eq!(hp.size(), 0);
+        assert!(hp.insert(3, 3));
+        assert!(hp.insert(1, 5));
+        {
+            let mut ds = hp.drain_sorted();
+            assert_eq!(ds.next(), Some((3, 3)));
+        } // dropped early
+        assert_eq!(hp.size(), 0);
+        assert_eq!(hp.get(&1), None);
+    } //const_drain_reuse
+
+    #[test]
+    fn iter_sorted_matches_pops() {
+        let mut hh = HashHeap::<u32, u32>::new_maxheap();
+        for i in 0..200 {
+            hh.insert(i, (i * 37) % 101);
+        }
+        let sorted: Vec<u32> = hh.iter_sorted().map(|(_, v)| *v).collect();
+        let popped: Vec<u32> = hh.clone().into_iter().map(|(_, v)| v).collect();
+        assert_eq!(sorted, popped);
+        assert_eq!(hh.len(), 200);
+    } //iter_sorted_matches_pops
+
+    #[test]
+    fn values_in_range_matches_filter() {
+        for maxheap in [true, false] {
+            let mut hh = HashHeap::<u32, u32>::with_capacity(16, maxheap);
+            for i in 0..300 {
+                hh.insert(i, (i * 37) % 101);
+            }
+            let mut found: Vec<u32> = hh.values_in_range(20..60).map(|(k, _)| *k).collect();
+            found.sort();
+            let expected: Vec<u32> = (0..300).filter(|i| (20..60).contains(&hh[i])).collect();
+            assert_eq!(found, expected);
+            assert_eq!(
+                hh.values_in_range(..=10).count(),
+                hh.values().filter(|v| **v <= 10).count()
+            );
+            assert_eq!(
+                hh.values_in_range(90..).count(),
+                hh.values().filter(|v| **v >= 90).count()
+            );
+        }
+    } //values_in_range_matches_filter
+
+    #[test]
+    fn validate_detects_unstable_hash() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let mut hh = HashHeap::<u32, u32>::new_maxheap();
+        for i in 0..100 {
+            hh.insert(i, i % 13);
+        }
+        for i in (0..100).step_by(3) {
+            hh.remove(&i);
+        }
+        assert_eq!(hh.debug_validate(), Ok(()));
+        let calls = AtomicUsize::new(0);
+        let mut bad = HashHeap::<u32, u32>::new_maxheap();
+        bad.set_hash_with(move |k| *k as usize + calls.fetch_add(1, Ordering::Relaxed));
+        bad.insert(1, 1);
+        bad.insert(2, 2);
+        assert!(bad.debug_validate().is_err());
+    } //validate_detects_unstable_hash
+
+    #[test]
+    fn iterators_report_exact_len() {
+        let mut hh = HashHeap::<u32, u32>::new_maxheap();
+        for i in 0..50 {
+            hh.insert(i, i % 7);
+        }
+        for i in (0..50).step_by(5) {
+            hh.remove(&i);
+        }
+        let mut keys = hh.keys();
+        keys.next();
+        assert_eq!(keys.len(), 39);
+        assert_eq!(hh.values().len(), 40);
+        assert_eq!(hh.iter().skip(10).len(), 30);
+        assert_eq!(hh.iter_sorted().len(), 40);
+        let mut stream = hh.clone();
+        assert_eq!(stream.priority_stream().len(), 40);
+        let mut into = hh.into_iter();
+        into.next();
+        assert_eq!(into.len(), 39);
+        assert_eq!(into.by_ref().count(), 39);
+        assert_eq!(into.next(), None);
+        let mut chh = ConstHashHeap::<u32, u32, 16>::new(true);
+        for i in 0..10 {
+            chh.insert(i, i);
+        }
+        assert_eq!(chh.iter().len(), 10);
+        assert_eq!(chh.priority_stream().len(), 10);
+    } //iterators_report_exact_len
+
+    #[test]
+    fn borrowed_lookups_with_custom_hash() {
+        let mut hh = HashHeap::<String, u32>::new_maxheap();
+        hh.set_hash(|k| k.len() % 3); // many collisions
+        for w in ["a", "bb", "ccc", "dddd", "eeeee"] {
+            hh.insert(w.to_string(), w.len() as u32);
+        }
+        assert_eq!(hh.get_borrowed("ccc"), Some(&3));
+        assert_eq!(hh.modify_borrowed("a", |v| *v = 9), Some(Moved::Up));
+        assert_eq!(hh.remove_borrowed("dddd"), Some(("dddd".to_string(), 4)));
+        assert!(!hh.contains_key_borrowed("dddd"));
+        assert!(hh.contains_key(&"eeeee".to_string()));
+        assert_eq!(hh.pop(), Some(("a".to_string(), 9)));
+    } //borrowed_lookups_with_custom_hash
+
+    #[test]
+    fn drain_keeps_capacity() {
+        let mut hh = HashHeap::<u32, u32>::with_capacity(64, false);
+        for i in 0..50 {
+            hh.insert(i, 100 - i);
+        }
+        let cap = hh.vals.capacity();
+        {
+            let mut d = hh.drain();
+            assert!(d.next().is_some());
+        } // dropped early
+        assert_eq!(hh.len(), 0);
+        assert_eq!(hh.get(&7), None);
+        assert!(hh.vals.capacity() >= cap);
+        for i in 0..50 {
+            hh.insert(i, i);
+        }
+        let sorted: Vec<_> = hh.drain_sorted().map(|(k, _)| k).collect();
+        assert_eq!(sorted, (0..50).collect::<Vec<_>>());
+        assert!(hh.vals.capacity() >= cap);
+        assert!(hh.insert(3, 3).is_none());
+    } //drain_keeps_capacity
+
+    #[test]
+    fn growable_rehash() {
+        let mut hp = GrowableHashHeap::<u32, u32>::new(true);
+        let cap0 = hp.capacity();
+        for i in 0..1000 {
+            hp.insert(i, i % 97);
+        }
+        assert!(hp.capacity() > cap0);
+        assert!(hp.load_factor() <= 0.75);
+        for i in (0..1000).step_by(2) {
+            assert_eq!(hp.remove(&i), Some((i, i % 97)));
+        }
+        hp.refresh();
+        assert_eq!(hp.len(), 500);
+        assert_eq!(hp.get(&501), Some(&(501 % 97)));
+        let mut prev = 97;
+        while let Some((k, v)) = hp.pop() {
+            assert!(v <= prev && k % 2 == 1);
+            prev = v;
+        }
+    } //growable_rehash
+
+    #[test]
+    fn const_odd_capacity() {
+        let mut hp = ConstHashHeap::<u32, u32, 100>::new(false);
+        for i in 0..100 {
+            assert!(hp.insert(i * 7, i));
+        }
+        for i in 0..100 {
+            assert_eq!(hp.get(&(i * 7)), Some(&i));
+        }
+        let hp2 = hp.resize::<150>();
+        for i in 0..100 {
+            assert_eq!(hp2.get(&(i * 7)), Some(&i));
+        }
+    } //const_odd_capacity
+
+    /// An operation drawn by [random_ops], for driving backends that don't
+    /// implement the testutils harness
+    #[derive(Clone, Copy, Debug)]
+    enum RandomOp {
+        Insert(u32, u32),
+        Push(u32, u32),
+        Modify(u32, u32),
+        Remove(u32),
+        Pop,
+        TopSwap(u32, u32),
+    }
+
+    #[cfg(feature = "test-utils")]
+    impl From<RandomOp> for testutils::Op<u32, u32> {
+        fn from(op: RandomOp) -> Self {
+            match op {
+                RandomOp::Insert(k, v) => testutils::Op::Insert(k, v),
+                RandomOp::Push(k, v) => testutils::Op::Push(k, v),
+                RandomOp::Modify(k, v) => testutils::Op::Modify(k, v),
+                RandomOp::Remove(k) => testutils::Op::Remove(k),
+                RandomOp::Pop => testutils::Op::Pop,
+                RandomOp::TopSwap(k, v) => testutils::Op::TopSwap(k, v),
+            }
+        }
+    }
+
+    /// `n` pseudo-random operations on keys below 64 and values below
+    /// `vals`.  Each operation is chosen uniformly from `mix`, one letter per
+    /// slot: `i`nsert, `u` push, `m`odify, `r`emove, `p`op, `s` top_swap.
+    fn random_ops(seed: u32, n: usize, vals: u32, mix: &str) -> Vec<RandomOp> {
+        let mix = mix.as_bytes();
+        let mut x = seed;
+        let mut ops = Vec::with_capacity(n);
+        for _ in 0..n {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345);
+            let (k, v) = ((x >> 8) % 64, (x >> 4) % vals);
+            ops.push(match mix[x as usize % mix.len()] {
+                b'i' => RandomOp::Insert(k, v),
+                b'u' => RandomOp::Push(k, v),
+                b'm' => RandomOp::Modify(k, v),
+                b'r' => RandomOp::Remove(k),
+                b'p' => RandomOp::Pop,
+                b's' => RandomOp::TopSwap(k, v),
+                c => panic!("unknown operation {:?}", c as char),
+            });
+        }
+        ops
+    } //random_ops
+
+    #[test]
+    fn ranked_matches_sorting() {
+        let mut rh = RankedHashHeap::<u32, u32>::new_maxheap();
+        for op in random_ops(7, 500, 50, "rmpii") {
+            match op {
+                RandomOp::Insert(k, v) => {
+                    rh.insert(k, v);
+                }
+                RandomOp::Modify(k, v) => {
+                    rh.modify(&k, |x| *x = v);
+                }
+                RandomOp::Remove(k) => {
+                    rh.remove(&k);
+                }
+                RandomOp::Pop => {
+                    rh.pop();
+                }
+                _ => unreachable!(),
+            }
+        }
+        let mut sorted: Vec<u32> = (0..64).filter_map(|k| rh.get(&k).copied()).collect();
+        sorted.sort_by(|a, b| b.cmp(a));
+        for (i, v) in sorted.iter().enumerate() {
+            assert_eq!(rh.select_kth(i).map(|p| *p.1), Some(*v));
+        }
+        for k in 0..64 {
+            if let Some(v) = rh.get(&k) {
+                let higher = sorted.iter().filter(|w| *w > v).count();
+                assert_eq!(rh.rank(&k), Some(higher));
+            }
+        }
+    } //ranked_matches_sorting
+
+    #[test]
+    fn minmax_matches_sorting() {
+        let mut mm = MinMaxHashHeap::<u32, u32>::new();
+        for (round, op) in random_ops(11, 2000, 100, "rmpuii").into_iter().enumerate() {
+            match op {
+                RandomOp::Insert(k, v) => {
+                    mm.insert(k, v);
+                }
+                RandomOp::Push(k, v) => {
+                    mm.push(k, v);
+                }
+                RandomOp::Modify(k, v) => {
+                    mm.modify(&k, |x| *x = v);
+                }
+                RandomOp::Remove(k) => {
+                    mm.remove(&k);
+                }
+                RandomOp::Pop if round % 2 == 0 => {
+                    mm.pop_min();
+                }
+                RandomOp::Pop => {
+                    mm.pop_max();
+                }
+                RandomOp::TopSwap(..) => unreachable!(),
+            }
+            let vals: Vec<u32> = mm.iter().map(|(_, v)| *v).collect();
+            assert_eq!(mm.peek_min().map(|p| *p.1), vals.iter().min().copied());
+            assert_eq!(mm.peek_max().map(|p| *p.1), vals.iter().max().copied());
+        }
+        let mut last = 0;
+        while let Some((_, v)) = mm.pop_min() {
+            assert!(v >= last);
+            last = v;
+        }
+    } //minmax_matches_sorting
+
+    #[test]
+    fn deferred_settles() {
+        let mut dh = DeferredHashHeap::<u32, u32>::new(false);
+        for i in 0..100 {
+            dh.insert(i, (i * 13) % 100);
+        }
+        dh.insert(5, 1000); // replace pending
+        assert_eq!(dh.pop(), Some((0, 0)));
+        dh.set_threshold(8);
+        for i in 100..110 {
+            dh.push(i, i % 7);
+        }
+        assert_eq!(dh.pending(), 1); // settled when the 9th arrived
+        dh.insert(10, 0); // replace settled entry
+        let mut prev = 0;
+        let mut count = 0;
+        while let Some((_, v)) = dh.pop() {
+            assert!(v >= prev);
+            prev = v;
+            count += 1;
+        }
+        assert_eq!((prev, count), (1000, 109));
+    } //deferred_settles
+
+    #[test]
+    fn relax_large_batch() {
+        let mut hh = HashHeap::<u32, u32>::new_minheap();
+        for i in 0..50 {
+            hh.insert(i, 1000 + i);
+        }
+        let changed = hh.relax_batch((0..200).map(|i| (i % 100, (i * 37) % 1500)));
+        let mut expected: Vec<u32> = (0..100)
+            .map(|k| {
+                let relaxed = [k, k + 100].iter().map(|i| (i * 37) % 1500).min().unwrap();
+                if k < 50 { relaxed.min(1000 + k) } else { relaxed }
+            })
+            .collect();
+        expected.sort();
+        assert!(changed > 50);
+        let popped: Vec<u32> = core::iter::from_fn(|| hh.pop().map(|p| p.1)).collect();
+        assert_eq!(popped, expected);
+    } //relax_large_batch
+
+    #[test]
+    fn churn_leaves_no_tombstones() {
+        let mut hh = HashHeap::<u32, u32>::new_maxheap();
+        hh.set_rehash(|h, c| h + c * c); // also with quadratic probing
+        let mut chh = ConstHashHeap::<u32, u32, 64>::new(false);
+        for i in 0..48 {
+            hh.insert(i, i);
+            chh.insert(i, i);
+        }
+        let fresh = chh.average_hashes();
+        for round in 1..200 {
+            for i in 0..16 {
+                let k = (round * 16 + i) % 48;
+                assert_eq!(hh.remove(&k).map(|p| p.0), Some(k));
+                assert_eq!(chh.remove(&k), Some((k, k)));
+                hh.push(k + 1000, k);
+                chh.insert(k + 1000, k);
+            }
+            for i in 0..16 {
+                let k = (round * 16 + i) % 48;
+                hh.remove(&(k + 1000));
+                chh.remove(&(k + 1000));
+                hh.push(k, k);
+                chh.insert(k, k);
+            }
+            hh.modify(&(round % 48), |v| *v += 1);
+            let (k, v) = hh.top_swap(5000, 0).unwrap();
+            assert_eq!(hh.remove(&5000), Some((5000, 0)));
+            hh.push(k, v);
+        }
+        assert_eq!(hh.len(), 48);
+        assert!(hh.kmap.len() < 96 && hh.keys.len() < 96);
+        while !hh.maintain(8) {}
+        assert_eq!((hh.kmap.len(), hh.keys.len()), (48, 48));
+        assert!(chh.average_hashes() <= fresh * 2.0);
+        for i in 0..48 {
+            assert!(hh.contains_key(&i));
+            assert_eq!(chh.get(&i), Some(&i));
+        }
+        hh.clear();
+        assert!(hh.passes.is_empty());
+    } //churn_leaves_no_tombstones
+
+    #[cfg(feature = "test-suite")]
+    #[test]
+    fn backends_conform() {
+        conformance::run_all(HashHeap::<u32, i32>::new_maxheap, true);
+        conformance::run_all(|| ConstHashHeap::<u32, i32, 67>::new(false), false);
+        conformance::run_all(|| GrowableHashHeap::<u32, i32>::new(true), true);
+        conformance::run_all(|| SegmentedHashHeap::<u32, i32>::with_segment_size(5, false), false);
+    } //backends_conform
+
+    #[cfg(feature = "test-utils")]
+    #[test]
+    fn const_matches_model() {
+        use testutils::DiffChecker;
+        let mut checker = DiffChecker::new(ConstHashHeap::<u32, u32, 48>::new(true), true);
+        for op in random_ops(11, 2000, 50, "rmpusii") {
+            checker.apply(op.into());
+        }
+        checker.check_all();
+    } //const_matches_model
+
+    #[test]
+    fn full_const_rejects_new_keys() {
+        let mut chh = ConstHashHeap::<u32, u32, 8>::new(false);
+        for i in 0..8 {
+            assert!(chh.insert(i, i));
+        }
+        assert!(!chh.insert(8, 8)); // must not probe forever
+        assert!(chh.set_at(9, 9).is_none());
+        assert!(chh.insert(3, 0));
+        assert_eq!(chh.peek(), Some((&0, &0)));
+        assert_eq!(chh.get(&8), None);
+        assert!(!chh.set_max_load(1.5));
+        assert!(chh.set_max_load(0.5));
+        chh.remove(&0);
+        assert!(chh.needs_resize() && !chh.insert(0, 0));
+    } //full_const_rejects_new_keys
+
+    #[test]
+    fn clone_keeps_hasher() {
+        let mut hh = HashHeap::<u32, u32>::new_maxheap();
+        hh.set_insert_policy(InsertPolicy::KeepBest);
+        for i in 0..200 {
+            hh.insert(i, i % 17);
+        }
+        for i in (0..200).step_by(3) {
+            hh.remove(&i);
+        }
+        let copy = hh.clone();
+        let reseeded = hh.clone_with_hasher(RandomState::new());
+        for i in 0..200 {
+            assert_eq!(copy.get(&i), hh.get(&i));
+            assert_eq!(reseeded.get(&i), hh.get(&i));
+        }
+        assert_eq!(reseeded.len(), hh.len());
+        assert_eq!(reseeded.peek().map(|p| *p.1), Some(16));
+        assert_eq!(reseeded.insert_policy(), InsertPolicy::KeepBest);
+        // the other structures that hash with a RandomState
+        let mut chh = ConstHashHeap::<u32, u32, 64>::new(true);
+        let mut ghh = GrowableHashHeap::<u32, u32>::new(true);
+        for i in 0..40 {
+            chh.insert(i, i);
+            ghh.insert(i, i);
+        }
+        let (chh2, ghh2) = (chh.clone(), ghh.clone());
+        for i in 0..40 {
+            assert_eq!(chh2.get(&i), Some(&i));
+            assert_eq!(ghh2.get(&i), Some(&i));
+        }
+    } //clone_keeps_hasher
+
+    #[test]
+    fn seeded_hashing_is_reproducible() {
+        let build = |seed| {
+            let mut hh = HashHeap::<u32, u32>::with_seed(seed, true);
+            for i in 0..100 {
+                hh.insert(i, i % 9);
+            }
+            hh
+        };
+        let (mut a, b, c) = (build(7), build(7), build(8));
+        assert_eq!(a.khash, b.khash);
+        assert_ne!(a.khash, c.khash);
+        a.clear();
+        for i in 0..100 {
+            a.insert(i, i % 9);
+        }
+        assert_eq!(a.khash, b.khash);
+    } //seeded_hashing_is_reproducible
+
+    #[test]
+    fn full_const_table_skips_generators() {
+        let mut chh = ConstHashHeap::<u32, u32, 2>::new(true);
+        chh.insert(1, 1);
+        chh.insert(2, 2);
+        let calls = std::cell::Cell::new(0);
+        let generate = || {
+            calls.set(calls.get() + 1);
+            3
+        };
+        assert_eq!(chh.and_generate(3, |_| generate()), None);
+        assert_eq!(chh.entry(3).or_insert_with(generate), None);
+        assert_eq!(calls.get(), 0);
+        assert!(chh.and_generate(2, |v| v.unwrap() + 1).is_some());
+        assert_eq!(chh.get(&2), Some(&3));
+    } //full_const_table_skips_generators
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn csv_round_trips_comment_like_keys() {
+        let mut hh = HashHeap::<String, String>::new_minheap();
+        for (k, v) in [
+            ("#tag", "1"),
+            ("x", "2"),
+            (" ", ""),
+            ("", " y "),
+            ("a", "#"),
+        ] {
+            hh.insert(k.to_string(), v.to_string());
+        }
+        let (mut csv, mut tsv) = (Vec::new(), Vec::new());
+        hh.to_csv(&mut csv).unwrap();
+        hh.to_tsv(&mut tsv).unwrap();
+        let from_csv = HashHeap::<String, String>::from_csv(&csv[..], false).unwrap();
+        let from_tsv = HashHeap::<String, String>::from_tsv(&tsv[..], false).unwrap();
+        assert_eq!(from_csv, hh);
+        assert_eq!(from_tsv, hh);
+    } //csv_round_trips_comment_like_keys
+
+    #[cfg(feature = "journal")]
+    #[test]
+    fn journal_comments_are_not_split() {
+        let log = "# a \"quoted\" note\n# one \" quote\nI,a,5\nI,\"b\n#c\",3\nR,a\n";
+        let hh = recover::<String, u32, _>(log.as_bytes(), true).unwrap();
+        assert_eq!(hh.len(), 1);
+        assert_eq!(hh.get(&"b\n#c".to_string()), Some(&3));
+    } //journal_comments_are_not_split
 } //tests module
@@ -0,0 +1,199 @@
+//! Interactive tool for inspecting the behavior of [HashHeap] and
+//! [ConstHashHeap], built with the `cli` feature:
+//! `cargo run --features cli`.  Keys are strings and values are numbers.
+//! Type `help` for the list of commands.
+
+use hashheap::*;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+commands:
+  new min|max [const]   start over with an empty min/max heap
+                        (const = ConstHashHeap with capacity 1024)
+  insert k v            insert or replace the value of key k
+  push k v              insert only if k is new
+  get k                 value of key k
+  modify k v            change the value of key k
+  remove k              remove key k
+  pop                   remove the top entry
+  peek                  show the top entry
+  list                  show all entries in heap-array order
+  tree                  show the heap as an indented tree
+  stats                 show size and other statistics
+  help                  show this message
+  quit                  exit";
+
+enum Backend {
+    Dynamic(Box<HashHeap<String, f64>>),
+    Const(Box<ConstHashHeap<String, f64>>),
+}
+use Backend::*;
+
+impl Backend {
+    fn new(maxheap: bool, constcap: bool) -> Backend {
+        if constcap {
+            Const(Box::new(ConstHashHeap::new(maxheap)))
+        } else {
+            Dynamic(Box::new(HashHeap::with_capacity(0, maxheap)))
+        }
+    }
+    fn len(&self) -> usize {
+        match self {
+            Dynamic(h) => h.len(),
+            Const(h) => h.size(),
+        }
+    }
+    fn entry_at(&self, i: usize) -> Option<(&String, &f64)> {
+        match self {
+            Dynamic(h) => h.get_entry_at(i),
+            Const(h) => h.get_entry_at(i),
+        }
+    }
+}
+
+fn show(p: Option<(&String, &f64)>) -> String {
+    p.map_or("none".to_string(), |(k, v)| format!("{} : {}", k, v))
+}
+
+fn print_tree(hp: &Backend, i: usize, depth: usize) {
+    if let Some(e) = hp.entry_at(i) {
+        println!("{}{}", "  ".repeat(depth), show(Some(e)));
+        print_tree(hp, 2 * i + 1, depth + 1);
+        print_tree(hp, 2 * i + 2, depth + 1);
+    }
+}
+
+// executes one command, returns false on quit
+fn execute(hp: &mut Backend, words: &[&str]) -> Result<bool, String> {
+    let key = |i: usize| -> Result<String, String> {
+        words.get(i).map(|s| s.to_string()).ok_or("missing key".to_string())
+    };
+    let val = |i: usize| -> Result<f64, String> {
+        words
+            .get(i)
+            .ok_or("missing value".to_string())?
+            .parse::<f64>()
+            .map_err(|e| e.to_string())
+    };
+    match words[0] {
+        "new" => {
+            let maxheap = match words.get(1) {
+                Some(&"max") => true,
+                Some(&"min") => false,
+                _ => return Err("expected min or max".to_string()),
+            };
+            *hp = Backend::new(maxheap, words.get(2) == Some(&"const"));
+        }
+        "insert" => {
+            let (k, v) = (key(1)?, val(2)?);
+            let ok = match hp {
+                Dynamic(h) => {
+                    h.insert(k, v);
+                    true
+                }
+                Const(h) => h.insert(k, v),
+            };
+            if !ok {
+                println!("capacity reached");
+            }
+        }
+        "push" => {
+            let (k, v) = (key(1)?, val(2)?);
+            let ok = match hp {
+                Dynamic(h) => h.push(k, v),
+                Const(h) => h.get(&k).is_none() && h.insert(k, v),
+            };
+            println!("{}", if ok { "pushed" } else { "not pushed" });
+        }
+        "get" => {
+            let k = key(1)?;
+            let v = match hp {
+                Dynamic(h) => h.get(&k),
+                Const(h) => h.get(&k),
+            };
+            println!("{}", v.map_or("none".to_string(), |v| v.to_string()));
+        }
+        "modify" => {
+            let (k, v) = (key(1)?, val(2)?);
+            let ok = match hp {
+                Dynamic(h) => h.modify(&k, |x| *x = v).is_some(),
+                Const(h) => h.modify(&k, |x| *x = v),
+            };
+            if !ok {
+                println!("key not found");
+            }
+        }
+        "remove" => {
+            let k = key(1)?;
+            let p = match hp {
+                Dynamic(h) => h.remove(&k),
+                Const(h) => h.remove(&k),
+            };
+            println!("{}", show(p.as_ref().map(|(k, v)| (k, v))));
+        }
+        "pop" => {
+            let p = match hp {
+                Dynamic(h) => h.pop(),
+                Const(h) => h.pop(),
+            };
+            println!("{}", show(p.as_ref().map(|(k, v)| (k, v))));
+        }
+        "peek" => println!("{}", show(hp.entry_at(0))),
+        "list" => {
+            for i in 0..hp.len() {
+                println!("{}: {}", i, show(hp.entry_at(i)));
+            }
+        }
+        "tree" => print_tree(hp, 0, 0),
+        "stats" => {
+            let stats = match hp {
+                Dynamic(h) => h.shape_stats(),
+                Const(h) => h.shape_stats(),
+            };
+            println!(
+                "size {}, depth {}, leaves {}, levels {:?}, top {}",
+                stats.len,
+                stats.depth,
+                stats.leaves,
+                stats.levels,
+                show(hp.entry_at(0))
+            );
+            match hp {
+                Dynamic(h) => println!("HashHeap, maxheap {}", h.is_max_hashheap()),
+                Const(h) => println!(
+                    "ConstHashHeap, load factor {}, average hashes {}",
+                    h.load_factor(),
+                    h.diagnostics(false)
+                ),
+            }
+        }
+        "help" => println!("{}", HELP),
+        "quit" | "exit" => return Ok(false),
+        w => return Err(format!("unknown command {}, type help", w)),
+    }
+    Ok(true)
+} //execute
+
+fn main() {
+    let mut hp = Backend::new(false, false);
+    println!("hashheap interactive tool: empty min-HashHeap, type help");
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        match execute(&mut hp, &words) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("error: {}", e),
+        }
+    } //loop
+} //main
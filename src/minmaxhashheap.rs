@@ -0,0 +1,220 @@
+//! This module contains [MinMaxHashHeap], a hashheap with access to both
+//! its lowest and its highest priority entries.  It is stored as a
+//! *min-max heap*, a binary heap whose levels alternate between min levels,
+//! starting with the root, and max levels: each entry on a min level is no
+//! greater than any of its descendants, and each entry on a max level no
+//! less.  The minimum is thus at the root and the maximum is one of its
+//! children, and both can be removed in O(log n) time.  A bounded queue
+//! can therefore evict its worst entry while still popping its best.
+//!
+//! As in a [SegmentedHashHeap](crate::SegmentedHashHeap), the keys are
+//! indexed by a Rust HashMap from keys to heap indices, into which keys
+//! are cloned.
+
+use crate::heap_depth;
+use core::hash::Hash;
+use std::collections::HashMap;
+
+fn left(i:usize) -> usize { 2*i+1 }
+fn parent(i:usize) -> usize { (i-1)/2 }
+fn is_min_level(i:usize) -> bool { heap_depth(i).is_multiple_of(2) }
+
+/// A double-ended hashheap: see the
+/// [module documentation](crate::minmaxhashheap).
+/// ```
+/// # use hashheap::*;
+///   let mut hh = MinMaxHashHeap::<&str,u32>::new();
+///   for (k,v) in [("a",5),("b",1),("c",9),("d",4)] { hh.insert(k,v); }
+///   assert_eq!(hh.peek_min(), Some((&"b",&1)));
+///   assert_eq!(hh.peek_max(), Some((&"c",&9)));
+///   hh.modify(&"a", |v| *v = 10);
+///   assert_eq!(hh.pop_max(), Some(("a",10)));
+///   assert_eq!(hh.pop_min(), Some(("b",1)));
+///   assert_eq!(hh.pop_max(), Some(("c",9)));
+///   assert_eq!(hh.len(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MinMaxHashHeap<KT,VT>
+{
+  vals : Vec<(KT,VT)>,
+  index : HashMap<KT,usize>, // key to heap index
+}
+impl<KT:Hash+Eq+Clone, VT:PartialOrd> MinMaxHashHeap<KT,VT> {
+
+  /// creates an empty structure
+  pub fn new() -> Self { Self::with_capacity(0) }
+
+  /// creates an empty structure that can hold `cap` entries without
+  /// reallocating
+  pub fn with_capacity(cap:usize) -> Self {
+    MinMaxHashHeap { vals : Vec::with_capacity(cap), index : HashMap::with_capacity(cap) }
+  }
+
+  fn less(&self, i:usize, j:usize) -> bool { self.vals[i].1 < self.vals[j].1 }
+
+  fn swap(&mut self, i:usize, j:usize) {
+    if i == j { return; }
+    self.vals.swap(i,j);
+    if let Some(p) = self.index.get_mut(&self.vals[i].0) { *p = i; }
+    if let Some(p) = self.index.get_mut(&self.vals[j].0) { *p = j; }
+  }
+
+  // moves the entry at i up through the grandparents on its min (or max)
+  // levels
+  fn push_up_levels(&mut self, mut i:usize, min:bool) -> usize {
+    while i > 2 {
+      let g = parent(parent(i));
+      let better = if min {self.less(i,g)} else {self.less(g,i)};
+      if !better { break; }
+      self.swap(i,g);
+      i = g;
+    }
+    i
+  }
+
+  fn push_up(&mut self, i:usize) -> usize {
+    if i == 0 { return 0; }
+    let p = parent(i);
+    let min = is_min_level(i);
+    if (min && self.less(p,i)) || (!min && self.less(i,p)) {
+      self.swap(i,p);
+      self.push_up_levels(p, !min)
+    }
+    else { self.push_up_levels(i, min) }
+  }//push_up
+
+  fn push_down(&mut self, mut i:usize) {
+    let min = is_min_level(i);
+    let n = self.vals.len();
+    // true if the entry at a should be above the entry at b
+    let above = |hh:&Self, a:usize, b:usize| if min {hh.less(a,b)} else {hh.less(b,a)};
+    while left(i) < n {
+      // the best of the children and grandchildren
+      let mut m = left(i);
+      for d in [left(i)+1, left(left(i)), left(left(i))+1,
+                left(left(i)+1), left(left(i)+1)+1] {
+        if d < n && above(self,d,m) { m = d; }
+      }
+      if !above(self,m,i) { break; }
+      self.swap(m,i);
+      if m <= left(i)+1 { break; }  // a child: done
+      if above(self,parent(m),m) { self.swap(m,parent(m)); }
+      i = m;
+    }
+  }//push_down
+
+  // restores the heap after the entry at i changed.  If it moves up past
+  // its parent, the parent's entry takes its place, and may have to move
+  // down into its subtree, so push_down is always called.
+  fn reposition(&mut self, i:usize) {
+    self.push_up(i);
+    self.push_down(i);
+  }
+
+  fn remove_at(&mut self, i:usize) -> (KT,VT) {
+    let last = self.vals.len()-1;
+    self.swap(i,last);
+    let pair = self.vals.pop().unwrap();
+    self.index.remove(&pair.0);
+    if i < self.vals.len() { self.reposition(i); }
+    pair
+  }
+
+  // heap index of the maximum
+  fn max_index(&self) -> Option<usize> {
+    match self.vals.len() {
+      0 => None,
+      1 => Some(0),
+      2 => Some(1),
+      _ => Some(if self.less(1,2) {2} else {1}),
+    }
+  }
+
+  /// Adds or changes a key-value pair, returning the replaced pair, if it
+  /// exists.  O(log n)
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    if let Some(&i) = self.index.get(&key) {
+      let old = core::mem::replace(&mut self.vals[i], (key,val));
+      self.reposition(i);
+      Some(old)
+    }
+    else {
+      self.push(key,val);
+      None
+    }
+  }//insert
+
+  /// Version of insert that does not replace an existing key, returning
+  /// false instead.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    if self.index.contains_key(&key) { return false; }
+    self.index.insert(key.clone(), self.vals.len());
+    self.vals.push((key,val));
+    self.push_up(self.vals.len()-1);
+    true
+  }
+
+  /// returns the value associated with the key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    self.index.get(key).map(|&i|&self.vals[i].1)
+  }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.index.contains_key(key) }
+
+  /// applies the closure to the value associated with the key, then
+  /// adjusts its position in the heap.  Returns false if the key was not
+  /// found.  O(log n)
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    match self.index.get(key) {
+      Some(&i) => {
+        f(&mut self.vals[i].1);
+        self.reposition(i);
+        true
+      },
+      None => false,
+    }
+  }//modify
+
+  /// removes and returns the pair with the given key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    let i = *self.index.get(key)?;
+    Some(self.remove_at(i))
+  }
+
+  /// returns the pair with the smallest value.  O(1)
+  pub fn peek_min(&self) -> Option<(&KT,&VT)> {
+    self.vals.first().map(|(k,v)|(k,v))
+  }
+
+  /// returns the pair with the largest value.  O(1)
+  pub fn peek_max(&self) -> Option<(&KT,&VT)> {
+    self.max_index().map(|i|{ let (k,v) = &self.vals[i]; (k,v) })
+  }
+
+  /// removes and returns the pair with the smallest value.  O(log n)
+  pub fn pop_min(&mut self) -> Option<(KT,VT)> {
+    if self.vals.is_empty() { None } else { Some(self.remove_at(0)) }
+  }
+
+  /// removes and returns the pair with the largest value.  O(log n)
+  pub fn pop_max(&mut self) -> Option<(KT,VT)> {
+    let i = self.max_index()?;
+    Some(self.remove_at(i))
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.vals.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.vals.is_empty() }
+
+  /// iterates over the entries in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ {
+    self.vals.iter().map(|(k,v)|(k,v))
+  }
+}//impl MinMaxHashHeap
+
+impl<KT:Hash+Eq+Clone, VT:PartialOrd> Default for MinMaxHashHeap<KT,VT> {
+  fn default() -> Self { Self::new() }
+}
@@ -0,0 +1,102 @@
+//! This module contains [PriorityCache], a cache of bounded size that
+//! retains the entries with the highest priority values.  It's built on a
+//! min-[HashHeap] so that the least valuable entry, which is the one to be
+//! evicted, is always at the top.
+
+use crate::HashHeap;
+use core::hash::Hash;
+
+/// A cache holding at most a fixed number of key-value pairs, where the
+/// values are priorities.  When a new key is inserted into a full cache,
+/// the entry with the lowest priority is evicted and returned.
+/// ```
+/// # use hashheap::*;
+///   let mut cache = PriorityCache::<&str,u32>::new(2);
+///   assert_eq!(cache.insert("a",5), None);
+///   assert_eq!(cache.insert("b",3), None);
+///   assert_eq!(cache.insert("c",4), Some(("b",3)));  // evicts lowest
+///   assert_eq!(cache.insert("d",1), Some(("d",1)));  // not valuable enough
+///   cache.get_bump(&"c", |p| *p += 10);
+///   assert_eq!(cache.insert("e",7), Some(("a",5)));
+///   assert!(cache.contains_key(&"c"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PriorityCache<KT,VT>
+{
+  heap : HashHeap<KT,VT>,
+  capacity : usize,
+}
+impl<KT:Hash+Eq, VT:PartialOrd> PriorityCache<KT,VT> {
+
+  /// creates a cache holding at most `capacity` entries.  A capacity of
+  /// zero is treated as one.
+  pub fn new(capacity:usize) -> Self {
+    let capacity = capacity.max(1);
+    PriorityCache {
+      heap : HashHeap::with_capacity(capacity+1, false),
+      capacity,
+    }
+  }
+
+  /// the maximum number of entries
+  pub fn capacity(&self) -> usize { self.capacity }
+
+  /// the current number of entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if the cache contains no entries
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// Inserts a key-value pair.  If the key already exists, its priority is
+  /// replaced and None is returned.  Otherwise, if the cache is full, the
+  /// entry with the lowest priority is evicted and returned; this may be
+  /// the given pair itself if its priority is lower than all others.
+  /// O(log n).
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    if self.heap.contains_key(&key) || self.heap.len() < self.capacity {
+      self.heap.insert(key,val);
+      None
+    }
+    else {
+      Some(self.heap.pushpop(key,val))
+    }
+  }//insert
+
+  /// returns the priority associated with the key, without changing it.
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    self.heap.get(key)
+  }
+
+  /// Changes the priority of an entry with the given closure, typically to
+  /// increase it when the entry is used, and returns the new priority.
+  /// None is returned if the key is not in the cache.  O(log n).
+  pub fn get_bump<F:FnOnce(&mut VT)>(&mut self, key:&KT, bump:F) -> Option<&VT> {
+    if self.heap.modify(key,bump).is_some() { self.heap.get(key) } else { None }
+  }
+
+  /// determines if the key is in the cache
+  pub fn contains_key(&self, key:&KT) -> bool {
+    self.heap.contains_key(key)
+  }
+
+  /// removes and returns the entry with the given key. O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.heap.remove(key)
+  }
+
+  /// returns the entry that would be evicted next, which has the lowest
+  /// priority.  O(1)
+  pub fn peek_victim(&self) -> Option<(&KT,&VT)> {
+    self.heap.peek()
+  }
+
+  /// removes and returns the entry with the lowest priority. O(log n)
+  pub fn evict(&mut self) -> Option<(KT,VT)> {
+    self.heap.pop()
+  }
+
+  /// returns an iterator over entries in no particular order
+  pub fn iter(&self) -> crate::KeyValIter<'_,KT,VT> {
+    self.heap.iter()
+  }
+}//impl PriorityCache
@@ -0,0 +1,145 @@
+//! This module contains the [PriorityMap] trait, the operations common to
+//! the keyed priority queues of this crate: [HashHeap], [ConstHashHeap],
+//! [GrowableHashHeap] and [SegmentedHashHeap].  Code that accepts any of them can be written
+//! against the trait instead of a concrete type.  The trait is object
+//! safe, so different structures can also be used as `dyn PriorityMap`.
+//! The closure given to [PriorityMap::modify] is therefore passed as a
+//! `&mut dyn FnMut`.
+//!
+//! The structures all have inherent methods of the same names, which are
+//! called instead of the trait methods unless the trait is named
+//! explicitly or the structure is used through a generic or `dyn` type.
+
+use crate::{ConstHashHeap, GrowableHashHeap, HashHeap, SegmentedHashHeap};
+use core::hash::Hash;
+
+/// A keyed priority queue: see the [module documentation](crate::prioritymap).
+/// ```
+/// # use hashheap::*;
+///   fn total<K>(pm:&mut dyn PriorityMap<K,u32>) -> u32 {
+///     let mut sum = 0;
+///     while let Some((_,v)) = pm.pop() { sum += v; }
+///     sum
+///   }
+///   let mut hh = HashHeap::<&str,u32>::new_maxheap();
+///   let mut chh = ConstHashHeap::<&str,u32,4>::new(true);
+///   let queues:[&mut dyn PriorityMap<&str,u32>; 2] = [&mut hh, &mut chh];
+///   for pm in queues {
+///     assert_eq!(pm.insert("a", 1), Ok(None));
+///     assert_eq!(pm.insert("a", 2), Ok(Some(1)));
+///     pm.insert("b", 5).unwrap();
+///     assert!(pm.modify(&"a", &mut |v| *v += 5));
+///     assert_eq!(pm.peek(), Some((&"a",&7)));
+///     assert_eq!(total(pm), 12);
+///     assert!(pm.is_empty());
+///   }
+/// ```
+pub trait PriorityMap<KT,VT> {
+  /// Adds a key-value pair, or replaces the value associated with an
+  /// existing key, returning the replaced value.  If the structure has
+  /// a fixed capacity that is full, the pair is returned as an error.
+  fn insert(&mut self, key:KT, val:VT) -> Result<Option<VT>,(KT,VT)>;
+
+  /// returns the value associated with the key
+  fn get(&self, key:&KT) -> Option<&VT>;
+
+  /// determines if the key exists
+  fn contains_key(&self, key:&KT) -> bool { self.get(key).is_some() }
+
+  /// Applies the closure to the value associated with the key, then
+  /// adjusts its position in the heap.  Returns false if the key was not
+  /// found.
+  fn modify(&mut self, key:&KT, f:&mut dyn FnMut(&mut VT)) -> bool;
+
+  /// removes and returns the pair with the given key
+  fn remove(&mut self, key:&KT) -> Option<(KT,VT)>;
+
+  /// returns the pair with the highest priority
+  fn peek(&self) -> Option<(&KT,&VT)>;
+
+  /// removes and returns the pair with the highest priority
+  fn pop(&mut self) -> Option<(KT,VT)>;
+
+  /// the number of pairs
+  fn len(&self) -> usize;
+
+  /// iterates over all pairs in no particular order
+  fn iter(&self) -> Box<dyn Iterator<Item=(&KT,&VT)> + '_>;
+
+  /// returns true if there are no pairs
+  fn is_empty(&self) -> bool { self.len() == 0 }
+}//trait PriorityMap
+
+impl<KT:Hash+Eq, VT:PartialOrd> PriorityMap<KT,VT> for HashHeap<KT,VT> {
+  fn insert(&mut self, key:KT, val:VT) -> Result<Option<VT>,(KT,VT)> {
+    Ok(HashHeap::insert(self,key,val).map(|(_,v)|v))
+  }
+  fn get(&self, key:&KT) -> Option<&VT> { HashHeap::get(self,key) }
+  fn contains_key(&self, key:&KT) -> bool { HashHeap::contains_key(self,key) }
+  fn modify(&mut self, key:&KT, f:&mut dyn FnMut(&mut VT)) -> bool {
+    HashHeap::modify(self,key,f).is_some()
+  }
+  fn remove(&mut self, key:&KT) -> Option<(KT,VT)> { HashHeap::remove(self,key) }
+  fn peek(&self) -> Option<(&KT,&VT)> { HashHeap::peek(self) }
+  fn pop(&mut self) -> Option<(KT,VT)> { HashHeap::pop(self) }
+  fn iter(&self) -> Box<dyn Iterator<Item=(&KT,&VT)> + '_> {
+    Box::new(HashHeap::iter(self))
+  }
+  fn len(&self) -> usize { HashHeap::len(self) }
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd, const CAP:usize> PriorityMap<KT,VT>
+  for ConstHashHeap<KT,VT,CAP>
+{
+  fn insert(&mut self, key:KT, val:VT) -> Result<Option<VT>,(KT,VT)> {
+    if self.needs_resize() && self.get(&key).is_none() { return Err((key,val)); }
+    Ok(self.find_and(key, |_|val).0)
+  }
+  fn get(&self, key:&KT) -> Option<&VT> { ConstHashHeap::get(self,key) }
+  fn modify(&mut self, key:&KT, f:&mut dyn FnMut(&mut VT)) -> bool {
+    ConstHashHeap::modify(self,key,f)
+  }
+  fn remove(&mut self, key:&KT) -> Option<(KT,VT)> { ConstHashHeap::remove(self,key) }
+  fn peek(&self) -> Option<(&KT,&VT)> { ConstHashHeap::peek(self) }
+  fn pop(&mut self) -> Option<(KT,VT)> { ConstHashHeap::pop(self) }
+  fn iter(&self) -> Box<dyn Iterator<Item=(&KT,&VT)> + '_> {
+    Box::new(ConstHashHeap::iter(self))
+  }
+  fn len(&self) -> usize { self.size() }
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd> PriorityMap<KT,VT> for GrowableHashHeap<KT,VT> {
+  fn insert(&mut self, key:KT, val:VT) -> Result<Option<VT>,(KT,VT)> {
+    Ok(GrowableHashHeap::insert(self,key,val).map(|(_,v)|v))
+  }
+  fn get(&self, key:&KT) -> Option<&VT> { GrowableHashHeap::get(self,key) }
+  fn contains_key(&self, key:&KT) -> bool { GrowableHashHeap::contains_key(self,key) }
+  fn modify(&mut self, key:&KT, f:&mut dyn FnMut(&mut VT)) -> bool {
+    GrowableHashHeap::modify(self,key,f)
+  }
+  fn remove(&mut self, key:&KT) -> Option<(KT,VT)> { GrowableHashHeap::remove(self,key) }
+  fn peek(&self) -> Option<(&KT,&VT)> { GrowableHashHeap::peek(self) }
+  fn pop(&mut self) -> Option<(KT,VT)> { GrowableHashHeap::pop(self) }
+  fn iter(&self) -> Box<dyn Iterator<Item=(&KT,&VT)> + '_> {
+    Box::new(GrowableHashHeap::iter(self))
+  }
+  fn len(&self) -> usize { GrowableHashHeap::len(self) }
+}
+
+impl<KT:Hash+Eq+Clone, VT:PartialOrd> PriorityMap<KT,VT> for SegmentedHashHeap<KT,VT> {
+  fn insert(&mut self, key:KT, val:VT) -> Result<Option<VT>,(KT,VT)> {
+    Ok(SegmentedHashHeap::insert(self,key,val).map(|(_,v)|v))
+  }
+  fn get(&self, key:&KT) -> Option<&VT> { SegmentedHashHeap::get(self,key) }
+  fn contains_key(&self, key:&KT) -> bool { SegmentedHashHeap::contains_key(self,key) }
+  fn modify(&mut self, key:&KT, f:&mut dyn FnMut(&mut VT)) -> bool {
+    SegmentedHashHeap::modify(self,key,f)
+  }
+  fn remove(&mut self, key:&KT) -> Option<(KT,VT)> { SegmentedHashHeap::remove(self,key) }
+  fn peek(&self) -> Option<(&KT,&VT)> { SegmentedHashHeap::peek(self) }
+  fn pop(&mut self) -> Option<(KT,VT)> { SegmentedHashHeap::pop(self) }
+  fn iter(&self) -> Box<dyn Iterator<Item=(&KT,&VT)> + '_> {
+    Box::new(SegmentedHashHeap::iter(self))
+  }
+  fn len(&self) -> usize { SegmentedHashHeap::len(self) }
+}
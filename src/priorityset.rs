@@ -0,0 +1,102 @@
+//! This module contains [PrioritySet], a priority queue of keys whose
+//! priorities are computed from the keys themselves by a function given
+//! when the structure is created, instead of being supplied as separate
+//! values.  When the data that a priority is derived from changes, for
+//! example a field of the key that is not part of its hash, or some
+//! external table read by the function, [PrioritySet::reprioritize]
+//! recomputes the priority of a key and repositions it.  A PrioritySet is
+//! a [HashHeap] from keys to their computed priorities.
+
+use crate::HashHeap;
+use core::hash::Hash;
+use core::fmt::Debug;
+
+/// A set of keys ordered by derived priority: see the
+/// [module documentation](crate::priorityset).
+/// ```
+/// # use hashheap::*;
+/// # use std::rc::Rc;
+/// # use std::cell::RefCell;
+///   let dist = Rc::new(RefCell::new(vec![5,2,9]));
+///   let d = dist.clone();
+///   let mut open = PrioritySet::new(move |v:&usize| d.borrow()[*v], false);
+///   for v in 0..3 { open.insert(v); }
+///   assert_eq!(open.peek(), Some(&1));
+///   dist.borrow_mut()[2] = 1;     // shorter path found
+///   open.reprioritize(&2);
+///   assert_eq!(open.pop(), Some(2));
+///   assert_eq!(open.priority(&0), Some(&5));
+/// ```
+pub struct PrioritySet<KT,PT>
+{
+  heap : HashHeap<KT,PT>,
+  priority : Box<dyn Fn(&KT) -> PT>,
+}
+impl<KT:Hash+Eq, PT:PartialOrd> PrioritySet<KT,PT> {
+
+  /// creates an empty set whose keys are prioritized by the given
+  /// function; true = maxheap, false = minheap
+  pub fn new<F>(priority:F, maxheap:bool) -> Self
+  where F: Fn(&KT) -> PT + 'static
+  {
+    PrioritySet { heap:HashHeap::with_capacity(0,maxheap), priority:Box::new(priority) }
+  }
+
+  /// Adds a key, computing its priority.  Returns false, without
+  /// recomputing the priority, if the key was already present.  O(log n)
+  pub fn insert(&mut self, key:KT) -> bool {
+    if self.heap.contains_key(&key) { return false; }
+    let p = (self.priority)(&key);
+    self.heap.push(key,p)
+  }
+
+  /// Recomputes the priority of the key and repositions it.  Returns
+  /// false if the key is not present.  O(log n)
+  pub fn reprioritize(&mut self, key:&KT) -> bool {
+    if !self.heap.contains_key(key) { return false; }
+    let p = (self.priority)(key);
+    self.heap.modify(key, |v| *v = p).is_some()
+  }
+
+  /// recomputes the priorities of all keys and restores the heap in O(n)
+  /// time, plus the cost of calling the priority function
+  pub fn reprioritize_all(&mut self) {
+    let priority = &self.priority;
+    self.heap.modify_all(|k,v| *v = priority(k));
+  }
+
+  /// returns the priority of the key as last computed.  O(1)
+  pub fn priority(&self, key:&KT) -> Option<&PT> { self.heap.get(key) }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// removes and returns the key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<KT> {
+    self.heap.remove(key).map(|(k,_)|k)
+  }
+
+  /// returns the key with the highest priority.  O(1)
+  pub fn peek(&self) -> Option<&KT> { self.heap.peek().map(|(k,_)|k) }
+
+  /// removes and returns the key with the highest priority.  O(log n)
+  pub fn pop(&mut self) -> Option<KT> { self.heap.pop().map(|(k,_)|k) }
+
+  /// the number of keys
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no keys
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// iterates over the keys with their priorities in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&PT)> + '_ { self.heap.iter() }
+
+  /// returns the underlying HashHeap from keys to priorities
+  pub fn into_hashheap(self) -> HashHeap<KT,PT> { self.heap }
+}//impl PrioritySet
+
+impl<KT:Hash+Eq+Debug, PT:PartialOrd+Debug> Debug for PrioritySet<KT,PT> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_map().entries(self.heap.iter()).finish()
+  }
+}
@@ -0,0 +1,286 @@
+//! This module contains [RankedHashHeap], a [HashHeap] augmented with a
+//! companion order-statistics tree so that the rank of a key, and the
+//! entry at a given rank, can be found in O(log n) time instead of the
+//! O(n) required by searching the heap.  The companion is a treap (a
+//! randomized balanced binary search tree) whose nodes record the sizes of
+//! their subtrees.  It holds clones of the keys and values, which is why
+//! this structure requires `KT:Clone` and `VT:Clone`.  Each entry is also
+//! tagged with a unique sequence number to distinguish equal values.
+//! Every mutating operation costs an additional expected O(log n) to keep
+//! the tree consistent with the heap.
+
+use crate::HashHeap;
+use core::hash::Hash;
+
+const NIL : usize = usize::MAX;
+
+#[derive(Clone, Debug)]
+struct OSNode<KT,VT>
+{
+  key : KT,
+  val : VT,
+  seq : u64,
+  pri : u64,   // random treap priority
+  size : usize,
+  left : usize,
+  right : usize,
+}
+
+// order-statistics treap, sorted in increasing order of (val,seq)
+#[derive(Clone, Debug)]
+struct OSTree<KT,VT>
+{
+  nodes : Vec<Option<OSNode<KT,VT>>>,
+  free : Vec<usize>,
+  root : usize,
+  rng : u64,
+}
+impl<KT, VT:PartialOrd> OSTree<KT,VT> {
+  fn new() -> Self {
+    OSTree { nodes:Vec::new(), free:Vec::new(), root:NIL, rng:0x9E3779B97F4A7C15 }
+  }
+
+  fn node(&self, i:usize) -> &OSNode<KT,VT> { self.nodes[i].as_ref().unwrap() }
+  fn node_mut(&mut self, i:usize) -> &mut OSNode<KT,VT> { self.nodes[i].as_mut().unwrap() }
+
+  fn size(&self, i:usize) -> usize {
+    if i==NIL {0} else {self.node(i).size}
+  }
+
+  fn update(&mut self, i:usize) {
+    let (l,r) = (self.node(i).left, self.node(i).right);
+    let s = 1 + self.size(l) + self.size(r);
+    self.node_mut(i).size = s;
+  }
+
+  // node i comes before (val,seq)
+  fn before(&self, i:usize, val:&VT, seq:u64) -> bool {
+    let n = self.node(i);
+    &n.val < val || (&n.val == val && n.seq < seq)
+  }
+
+  // splits tree t into nodes before (val,seq) and the rest
+  fn split(&mut self, t:usize, val:&VT, seq:u64) -> (usize,usize) {
+    if t==NIL { return (NIL,NIL); }
+    if self.before(t,val,seq) {
+      let (a,b) = self.split(self.node(t).right,val,seq);
+      self.node_mut(t).right = a;
+      self.update(t);
+      (t,b)
+    }
+    else {
+      let (a,b) = self.split(self.node(t).left,val,seq);
+      self.node_mut(t).left = b;
+      self.update(t);
+      (a,t)
+    }
+  }//split
+
+  // merges trees a and b, where all nodes of a come before those of b
+  fn merge(&mut self, a:usize, b:usize) -> usize {
+    if a==NIL { return b; }
+    if b==NIL { return a; }
+    if self.node(a).pri > self.node(b).pri {
+      let m = self.merge(self.node(a).right,b);
+      self.node_mut(a).right = m;
+      self.update(a);
+      a
+    }
+    else {
+      let m = self.merge(a,self.node(b).left);
+      self.node_mut(b).left = m;
+      self.update(b);
+      b
+    }
+  }//merge
+
+  fn random(&mut self) -> u64 { // xorshift
+    self.rng ^= self.rng << 13;
+    self.rng ^= self.rng >> 7;
+    self.rng ^= self.rng << 17;
+    self.rng
+  }
+
+  fn insert(&mut self, key:KT, val:VT, seq:u64) {
+    let (l,r) = self.split(self.root,&val,seq);
+    let pri = self.random();
+    let node = OSNode { key, val, seq, pri, size:1, left:NIL, right:NIL };
+    let i = match self.free.pop() {
+      Some(i) => { self.nodes[i] = Some(node); i },
+      None => { self.nodes.push(Some(node)); self.nodes.len()-1 },
+    };
+    let m = self.merge(l,i);
+    self.root = self.merge(m,r);
+  }//insert
+
+  fn remove(&mut self, val:&VT, seq:u64) {
+    let (l,r) = self.split(self.root,val,seq);
+    let (m,r2) = self.split(r,val,seq+1);
+    if m != NIL {
+      self.nodes[m] = None;
+      self.free.push(m);
+    }
+    self.root = self.merge(l,r2);
+  }//remove
+
+  // number of nodes with values less than val
+  fn count_less(&self, val:&VT) -> usize {
+    let (mut t, mut c) = (self.root, 0);
+    while t != NIL {
+      let n = self.node(t);
+      if &n.val < val { c += self.size(n.left)+1; t = n.right; }
+      else { t = n.left; }
+    }
+    c
+  }
+
+  // number of nodes with values greater than val
+  fn count_greater(&self, val:&VT) -> usize {
+    let (mut t, mut c) = (self.root, 0);
+    while t != NIL {
+      let n = self.node(t);
+      if val < &n.val { c += self.size(n.right)+1; t = n.left; }
+      else { t = n.right; }
+    }
+    c
+  }
+
+  // the node at position k in increasing order
+  fn select(&self, mut k:usize) -> Option<&OSNode<KT,VT>> {
+    let mut t = self.root;
+    while t != NIL {
+      let n = self.node(t);
+      let ls = self.size(n.left);
+      if k < ls { t = n.left; }
+      else if k == ls { return Some(n); }
+      else { k -= ls+1; t = n.right; }
+    }
+    None
+  }
+}//impl OSTree
+
+/// A [HashHeap] that also supports O(log n) rank and selection queries:
+/// see the [module documentation](crate::rankedhashheap).
+/// ```
+/// # use hashheap::*;
+///   let mut queue = RankedHashHeap::<&str,u32>::new_minheap();
+///   queue.insert("a",30);
+///   queue.insert("b",10);
+///   queue.insert("c",20);
+///   queue.insert("d",20);
+///   assert_eq!(queue.rank(&"b"), Some(0));
+///   assert_eq!(queue.rank(&"d"), Some(1));
+///   assert_eq!(queue.rank(&"a"), Some(3));
+///   assert_eq!(queue.select_kth(3), Some((&"a",&30)));
+///   queue.modify(&"a",|v|*v=5);
+///   assert_eq!(queue.rank(&"b"), Some(1));
+///   assert_eq!(queue.pop(), Some(("a",5)));
+///   assert_eq!(queue.select_kth(0), Some((&"b",&10)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RankedHashHeap<KT,VT>
+{
+  heap : HashHeap<KT,(VT,u64)>,
+  tree : OSTree<KT,VT>,
+  seq : u64,
+}
+impl<KT:Hash+Eq+Clone, VT:PartialOrd+Clone> RankedHashHeap<KT,VT> {
+
+  /// creates an empty RankedHashHeap; true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    RankedHashHeap {
+      heap : HashHeap::with_capacity(0,maxheap),
+      tree : OSTree::new(),
+      seq : 0,
+    }
+  }
+
+  /// creates an empty min-heap version
+  pub fn new_minheap() -> Self { Self::new(false) }
+
+  /// creates an empty max-heap version
+  pub fn new_maxheap() -> Self { Self::new(true) }
+
+  /// Add or change a key-value pair, returning the replaced pair, if
+  /// it exists.  O(log n)
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    self.seq += 1;
+    self.tree.insert(key.clone(),val.clone(),self.seq);
+    let old = self.heap.insert(key,(val,self.seq));
+    old.map(|(k,(v,s))| {
+      self.tree.remove(&v,s);
+      (k,v)
+    })
+  }//insert
+
+  /// returns the value associated with the key, if it exists.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    self.heap.get(key).map(|p|&p.0)
+  }
+
+  /// Applies the mutating closure to the value associated with the key,
+  /// then adjusts its position.  Returns false if the key was not found.
+  /// O(log n) plus the cost of the closure.
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    let (v,s) = match self.heap.get(key) {
+      Some((v,s)) => (v.clone(),*s),
+      None => { return false; },
+    };
+    self.tree.remove(&v,s);
+    self.heap.modify(key,|p|f(&mut p.0));
+    if let Some((nv,_)) = self.heap.get(key) {
+      self.tree.insert(key.clone(),nv.clone(),s);
+    }
+    true
+  }//modify
+
+  /// Removes and returns the key-value pair with the given key. O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.heap.remove(key).map(|(k,(v,s))| {
+      self.tree.remove(&v,s);
+      (k,v)
+    })
+  }
+
+  /// returns the highest-priority key-value pair. O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    self.heap.peek().map(|(k,p)|(k,&p.0))
+  }
+
+  /// removes and returns the highest-priority key-value pair. O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    self.heap.pop().map(|(k,(v,s))| {
+      self.tree.remove(&v,s);
+      (k,v)
+    })
+  }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// the number of key-value pairs
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// returns the number of entries with strictly higher priority than the
+  /// value associated with the key, or None if the key is not found.  The
+  /// top entry has rank zero.  Expected O(log n) time.
+  pub fn rank(&self, key:&KT) -> Option<usize> {
+    let (v,_) = self.heap.get(key)?;
+    Some(if self.heap.is_max_hashheap() {self.tree.count_greater(v)}
+         else {self.tree.count_less(v)})
+  }
+
+  /// returns the entry at position k (starting from zero) in order of
+  /// priority, so that `select_kth(0)` returns the same value as
+  /// [Self::peek].  Among entries with equal values, earlier insertions
+  /// come first for a minheap and last for a maxheap.  Expected O(log n).
+  pub fn select_kth(&self, k:usize) -> Option<(&KT,&VT)> {
+    let n = self.heap.len();
+    if k >= n { return None; }
+    let pos = if self.heap.is_max_hashheap() {n-1-k} else {k};
+    self.tree.select(pos).map(|nd|(&nd.key,&nd.val))
+  }
+}//impl RankedHashHeap
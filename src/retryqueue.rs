@@ -0,0 +1,128 @@
+//! This module contains [RetryQueue], which schedules keyed tasks for
+//! repeated attempts with exponential backoff.  The priority of each key
+//! is the time of its next attempt, and a failed attempt reschedules the
+//! key in place, in O(log n) time.
+
+use crate::HashHeap;
+use core::hash::Hash;
+use core::ops::Add;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A queue of keys scheduled for (re)attempts.  The time type `T` can be
+/// [std::time::Instant] or any other type that a [Duration] can be added
+/// to.  After [RetryQueue::poll_ready] hands out a key, the caller reports
+/// the outcome with [RetryQueue::succeed] or [RetryQueue::fail].  A failed
+/// key is rescheduled after a delay that doubles with each consecutive
+/// failure, starting from the base delay, up to the maximum delay.
+/// ```
+/// # use hashheap::*;
+/// # use std::time::{Duration,Instant};
+///   let t0 = Instant::now();
+///   let mut q = RetryQueue::<&str,Instant>::new(Duration::from_secs(1),
+///                                               Duration::from_secs(60));
+///   q.schedule("job", t0);
+///   let ready:Vec<_> = q.poll_ready(t0).collect();
+///   assert_eq!(ready, vec![("job",0)]);
+///   assert_eq!(q.fail("job", t0), t0 + Duration::from_secs(1));
+///   assert_eq!(q.poll_ready(t0).count(), 0);
+///   let t1 = t0 + Duration::from_secs(1);
+///   assert_eq!(q.poll_ready(t1).next(), Some(("job",1)));
+///   assert_eq!(q.fail("job", t1), t1 + Duration::from_secs(2));
+///   assert!(q.succeed(&"job"));
+///   assert!(q.is_empty());
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryQueue<KT,T>
+{
+  heap : HashHeap<KT,(T,u32)>,     // next attempt time, failures so far
+  inflight : HashMap<KT,u32>,      // keys handed out by poll_ready
+  base : Duration,
+  max : Duration,
+}
+impl<KT:Hash+Eq+Clone, T:Copy+PartialOrd+Add<Duration,Output=T>> RetryQueue<KT,T>
+{
+  /// creates an empty queue with the given base and maximum delays
+  pub fn new(base:Duration, max:Duration) -> Self {
+    RetryQueue {
+      heap : HashHeap::new_minheap(),
+      inflight : HashMap::new(),
+      base,
+      max,
+    }
+  }
+
+  /// schedules a first attempt for the key at the given time, resetting
+  /// its failure count if it's already in the queue.
+  pub fn schedule(&mut self, key:KT, at:T) {
+    self.inflight.remove(&key);
+    self.heap.insert(key,(at,0));
+  }
+
+  // delay after the given number of consecutive failures
+  fn delay(&self, failures:u32) -> Duration {
+    let factor = 1u32.checked_shl(failures.saturating_sub(1)).unwrap_or(u32::MAX);
+    self.base.checked_mul(factor).unwrap_or(self.max).min(self.max)
+  }
+
+  /// records a failed attempt and reschedules the key with exponential
+  /// backoff from the given time.  A key that's not known to the queue is
+  /// treated as having failed for the first time.  Returns the time of the
+  /// next attempt.  O(log n)
+  pub fn fail(&mut self, key:KT, now:T) -> T {
+    let failures = self.inflight.remove(&key)
+      .or_else(|| self.heap.get(&key).map(|p|p.1))
+      .unwrap_or(0) + 1;
+    let next = now + self.delay(failures);
+    self.heap.insert(key,(next,failures));
+    next
+  }//fail
+
+  /// records a successful attempt and removes the key from the queue.
+  /// Returns false if the key was unknown. O(log n)
+  pub fn succeed(&mut self, key:&KT) -> bool {
+    self.inflight.remove(key).is_some() || self.heap.remove(key).is_some()
+  }
+
+  /// returns an iterator over the keys due at or before the given time,
+  /// in order of their scheduled times, together with the number of times
+  /// each has failed.  The keys are taken out of the schedule and
+  /// remembered until [Self::succeed] or [Self::fail] is called.
+  pub fn poll_ready<'a>(&'a mut self, now:T) -> RetryReady<'a,KT,T> {
+    RetryReady { rq:self, now }
+  }
+
+  /// the time of the earliest scheduled attempt
+  pub fn next_due(&self) -> Option<&T> {
+    self.heap.peek().map(|(_,p)|&p.0)
+  }
+
+  /// the number of scheduled keys, not including those handed out
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if no keys are scheduled or awaiting an outcome
+  pub fn is_empty(&self) -> bool {
+    self.heap.len() == 0 && self.inflight.is_empty()
+  }
+}//impl RetryQueue
+
+/// Iterator returned by [RetryQueue::poll_ready]
+pub struct RetryReady<'a,KT,T>
+{
+  rq : &'a mut RetryQueue<KT,T>,
+  now : T,
+}
+impl<'a,KT:Hash+Eq+Clone, T:Copy+PartialOrd+Add<Duration,Output=T>> Iterator
+for RetryReady<'a,KT,T>
+{
+  type Item = (KT,u32);
+  fn next(&mut self) -> Option<Self::Item> {
+    match self.rq.heap.peek() {
+      Some((_,(t,_))) if *t <= self.now => {},
+      _ => { return None; },
+    }
+    let (key,(_,failures)) = self.rq.heap.pop()?;
+    self.rq.inflight.insert(key.clone(),failures);
+    Some((key,failures))
+  }
+}
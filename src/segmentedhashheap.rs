@@ -0,0 +1,237 @@
+//! This module contains [SegmentedHashHeap], a hashheap whose binary heap
+//! is stored in fixed-size segments instead of one contiguous vector.
+//! When a vector outgrows its capacity, all of its elements are copied
+//! into a new allocation of twice the size, which causes a pause of O(n)
+//! time exactly when the size crosses a power of two.  The segments of a
+//! SegmentedHashHeap are never reallocated: a new segment is added when the
+//! last one is full, and only the (much shorter) list of segments is ever
+//! copied.  Heap index i is found at offset `i % segment_size` of segment
+//! `i / segment_size`.
+//!
+//! The keys are indexed by a Rust HashMap from keys to heap indices, which
+//! still grows by rehashing.  A latency-sensitive application should
+//! therefore create the structure [with a capacity](SegmentedHashHeap::with_capacity)
+//! large enough for its expected size, which reserves the index up front
+//! without allocating all of the segments.  Keys are cloned into the index.
+
+use core::hash::Hash;
+use std::collections::HashMap;
+
+/// The default number of entries in each segment
+pub const DEFAULT_SEGMENT : usize = 1024;
+
+fn left(i:usize) -> usize { 2*i+1 }
+fn right(i:usize) -> usize { 2*i+2 }
+fn parent(i:usize) -> usize { (i-1)/2 }
+
+/// A hashheap with segmented storage: see the
+/// [module documentation](crate::segmentedhashheap).
+/// ```
+/// # use hashheap::*;
+///   let mut hh = SegmentedHashHeap::<u32,u32>::with_segment_size(4, false);
+///   for i in 0..10 { hh.insert(i, 100-i); }
+///   assert_eq!(hh.segments(), 3);
+///   hh.modify(&5, |v| *v = 1);
+///   assert_eq!(hh.pop(), Some((5,1)));
+///   assert_eq!(hh.pop(), Some((9,91)));
+///   assert_eq!(hh.get(&0), Some(&100));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SegmentedHashHeap<KT,VT>
+{
+  segs : Vec<Vec<(KT,VT)>>,  // each with capacity segsize
+  segsize : usize,
+  size : usize,
+  index : HashMap<KT,usize>, // key to heap index
+  maxheap : bool,
+}
+impl<KT:Hash+Eq+Clone, VT:PartialOrd> SegmentedHashHeap<KT,VT> {
+
+  /// creates an empty structure with the default segment size;
+  /// true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    Self::with_segment_size(DEFAULT_SEGMENT, maxheap)
+  }
+
+  /// creates an empty structure whose key index can hold `cap` keys
+  /// without growing.  Segments are still allocated only as needed.
+  pub fn with_capacity(cap:usize, maxheap:bool) -> Self {
+    let mut hh = Self::new(maxheap);
+    hh.index.reserve(cap);
+    hh
+  }
+
+  /// creates an empty structure with segments of the given number of
+  /// entries, which is at least one.
+  pub fn with_segment_size(segsize:usize, maxheap:bool) -> Self {
+    SegmentedHashHeap {
+      segs : Vec::new(),
+      segsize : segsize.max(1),
+      size : 0,
+      index : HashMap::new(),
+      maxheap,
+    }
+  }
+
+  fn at(&self, i:usize) -> &(KT,VT) { &self.segs[i/self.segsize][i%self.segsize] }
+
+  fn at_mut(&mut self, i:usize) -> &mut (KT,VT) {
+    &mut self.segs[i/self.segsize][i%self.segsize]
+  }
+
+  // true if the entry at i has lower priority than the entry at j
+  fn lower(&self, i:usize, j:usize) -> bool {
+    let (a,b) = (&self.at(i).1, &self.at(j).1);
+    if self.maxheap {a < b} else {b < a}
+  }
+
+  // swaps entries i and j, which may be in different segments
+  fn swap(&mut self, i:usize, j:usize) {
+    if i == j { return; }
+    let (si,sj) = (i/self.segsize, j/self.segsize);
+    let (oi,oj) = (i%self.segsize, j%self.segsize);
+    if si == sj { self.segs[si].swap(oi,oj); }
+    else {
+      let (lo,hi) = if si < sj {(si,sj)} else {(sj,si)};
+      let (first,rest) = self.segs.split_at_mut(hi);
+      let (a,b) = if si < sj {(&mut first[lo][oi], &mut rest[0][oj])}
+                  else {(&mut rest[0][oi], &mut first[lo][oj])};
+      core::mem::swap(a,b);
+    }
+    if let Some(p) = self.index.get_mut(&self.segs[si][oi].0) { *p = i; }
+    if let Some(p) = self.index.get_mut(&self.segs[sj][oj].0) { *p = j; }
+  }//swap
+
+  fn swapup(&mut self, mut i:usize) -> usize {
+    while i > 0 && self.lower(parent(i), i) {
+      self.swap(i, parent(i));
+      i = parent(i);
+    }
+    i
+  }
+
+  fn swapdown(&mut self, mut i:usize) -> usize {
+    loop {
+      let (lf,rt) = (left(i),right(i));
+      let mut si = i;
+      if lf < self.size && self.lower(si,lf) { si = lf; }
+      if rt < self.size && self.lower(si,rt) { si = rt; }
+      if si == i { return i; }
+      self.swap(i,si);
+      i = si;
+    }
+  }//swapdown
+
+  fn reposition(&mut self, i:usize) {
+    if self.swapup(i) == i { self.swapdown(i); }
+  }
+
+  // appends an entry at the end of the heap, adding a segment if needed
+  fn push_back(&mut self, key:KT, val:VT) {
+    if self.size.is_multiple_of(self.segsize) && self.segs.len() == self.size/self.segsize {
+      self.segs.push(Vec::with_capacity(self.segsize));
+    }
+    self.index.insert(key.clone(), self.size);
+    self.segs[self.size/self.segsize].push((key,val));
+    self.size += 1;
+  }
+
+  // removes the last entry of the heap.  One empty segment is kept as a
+  // spare, so that alternating pushes and pops at a segment boundary
+  // don't allocate every time.
+  fn pop_back(&mut self) -> (KT,VT) {
+    self.size -= 1;
+    let pair = self.segs[self.size/self.segsize].pop().unwrap();
+    if self.segs.len() > self.size.div_ceil(self.segsize) + 1 { self.segs.pop(); }
+    self.index.remove(&pair.0);
+    pair
+  }
+
+  /// Adds or changes a key-value pair, returning the replaced pair, if it
+  /// exists.  O(log n)
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    if let Some(&i) = self.index.get(&key) {
+      let old = core::mem::replace(self.at_mut(i), (key,val));
+      self.reposition(i);
+      Some(old)
+    }
+    else {
+      self.push_back(key,val);
+      self.swapup(self.size-1);
+      None
+    }
+  }//insert
+
+  /// Version of insert that does not replace an existing key, returning
+  /// false instead.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    if self.index.contains_key(&key) { return false; }
+    self.push_back(key,val);
+    self.swapup(self.size-1);
+    true
+  }
+
+  /// returns the value associated with the key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    self.index.get(key).map(|&i|&self.at(i).1)
+  }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.index.contains_key(key) }
+
+  /// applies the closure to the value associated with the key, then
+  /// adjusts its position in the heap.  Returns false if the key was not
+  /// found.  O(log n)
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    match self.index.get(key) {
+      Some(&i) => {
+        f(&mut self.at_mut(i).1);
+        self.reposition(i);
+        true
+      },
+      None => false,
+    }
+  }//modify
+
+  /// removes and returns the pair with the given key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    let i = *self.index.get(key)?;
+    self.swap(i, self.size-1);
+    let pair = self.pop_back();
+    if i < self.size { self.reposition(i); }
+    Some(pair)
+  }
+
+  /// returns the pair with the highest priority.  O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    if self.size == 0 { None }
+    else { let (k,v) = self.at(0); Some((k,v)) }
+  }
+
+  /// removes and returns the pair with the highest priority.  O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    if self.size == 0 { return None; }
+    self.swap(0, self.size-1);
+    let pair = self.pop_back();
+    self.swapdown(0);
+    Some(pair)
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.size }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.size == 0 }
+
+  /// the number of entries in each segment
+  pub fn segment_size(&self) -> usize { self.segsize }
+
+  /// the number of segments currently allocated
+  pub fn segments(&self) -> usize { self.segs.len() }
+
+  /// iterates over the entries in heap order, which is in no particular
+  /// order of priority except that the top entry comes first
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ {
+    self.segs.iter().flatten().map(|(k,v)|(k,v))
+  }
+}//impl SegmentedHashHeap
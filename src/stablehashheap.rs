@@ -0,0 +1,118 @@
+//! This module contains [StableHashHeap], a [HashHeap] that breaks ties
+//! between equal priorities in first-in, first-out order.  A binary heap
+//! returns entries of equal priority in an order that depends on the
+//! history of the heap, which makes schedulers that rely on it hard to
+//! test and to reason about.  Each entry of a StableHashHeap is tagged
+//! with the value of a counter that increases with every insertion, and
+//! the earlier of two entries with equal priorities is considered to have
+//! the higher priority.
+
+use crate::{HashHeap, UserFn};
+use core::hash::Hash;
+
+// a value tagged with its insertion number
+#[derive(Clone, Debug)]
+struct Stamped<VT> {
+  val : VT,
+  seq : u64,
+}
+
+// the comparators of a max- and a min-heap: among equal values, the one
+// inserted first is treated as greater (max) or smaller (min)
+fn fifo_max<VT:PartialOrd>(a:&Stamped<VT>, b:&Stamped<VT>) -> bool {
+  a.val < b.val || (a.val == b.val && a.seq > b.seq)
+}
+fn fifo_min<VT:PartialOrd>(a:&Stamped<VT>, b:&Stamped<VT>) -> bool {
+  b.val < a.val || (a.val == b.val && a.seq > b.seq)
+}
+
+/// A HashHeap with first-in, first-out order among equal priorities: see
+/// the [module documentation](crate::stablehashheap).
+/// ```
+/// # use hashheap::*;
+///   let mut jobs = StableHashHeap::<&str,u8>::new(true);
+///   for (job,p) in [("a",1),("b",5),("c",1),("d",5),("e",1)] {
+///     jobs.insert(job,p);
+///   }
+///   let order:Vec<_> = std::iter::from_fn(|| jobs.pop()).map(|p|p.0).collect();
+///   assert_eq!(order, vec!["b","d","a","c","e"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StableHashHeap<KT,VT>
+{
+  heap : HashHeap<KT,Stamped<VT>>,
+  counter : u64,
+}
+impl<KT:Hash+Eq, VT:PartialOrd> StableHashHeap<KT,VT> {
+
+  /// creates an empty structure; true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    let cmp = if maxheap {fifo_max} else {fifo_min};
+    StableHashHeap { heap : HashHeap::with_cmp(0,maxheap,UserFn::Ptr(cmp)), counter : 0 }
+  }
+
+  fn stamp(&mut self, val:VT) -> Stamped<VT> {
+    self.counter += 1;
+    Stamped { val, seq : self.counter }
+  }
+
+  /// Add or change a key-value pair, returning the replaced pair, if it
+  /// exists.  A replaced entry counts as newly inserted, so it goes behind
+  /// the other entries of equal priority.  O(log n)
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    let sv = self.stamp(val);
+    self.heap.insert(key,sv).map(|(k,s)|(k,s.val))
+  }
+
+  /// Version of insert that does not replace an existing key, returning
+  /// false instead.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    if self.heap.contains_key(&key) { return false; }
+    let sv = self.stamp(val);
+    self.heap.push(key,sv)
+  }
+
+  /// returns the value associated with the key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> { self.heap.get(key).map(|s|&s.val) }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// Applies the closure to the value associated with the key and
+  /// repositions it.  The entry keeps its place in the insertion order.
+  /// Returns false if the key was not found.  O(log n)
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    self.heap.modify(key, |s| f(&mut s.val)).is_some()
+  }
+
+  /// removes and returns the pair with the given key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.heap.remove(key).map(|(k,s)|(k,s.val))
+  }
+
+  /// returns the pair with the highest priority, the earliest inserted
+  /// among equals.  O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    self.heap.peek().map(|(k,s)|(k,&s.val))
+  }
+
+  /// removes and returns the pair with the highest priority, the earliest
+  /// inserted among equals.  O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    self.heap.pop().map(|(k,s)|(k,s.val))
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.is_empty() }
+
+  /// returns true if the structure is a max-hashheap
+  pub fn is_max_hashheap(&self) -> bool { self.heap.is_max_hashheap() }
+
+  /// iterates over the entries in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ {
+    self.heap.iter().map(|(k,s)|(k,&s.val))
+  }
+}//impl StableHashHeap
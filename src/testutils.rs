@@ -0,0 +1,466 @@
+//! Utilities for testing code built on hashheaps, enabled by the
+//! `test-utils` feature.  [ModelHeap] is a deliberately simple reference
+//! implementation of a keyed priority queue: an unsorted vector that is
+//! searched linearly.  A [DiffChecker] applies each operation to both a
+//! model and a structure under test, such as a [HashHeap] with custom
+//! hash, rehash or comparison functions, and panics as soon as their
+//! observable behavior differs.  The structure's heap property is also
+//! checked after every operation.
+//!
+//! Entries with equal priority may be popped in any order, so `peek` and
+//! `pop` are only required to return *some* entry of highest priority.
+//!
+//! Operation sequences can also be given as data, as vectors of [Op], so
+//! that fuzzers and property tests can generate them.  [apply_ops] runs
+//! such a sequence on a [HashHeap], and [DiffChecker::apply] on both a
+//! model and a structure under test.  Any interleaving of these operations
+//! is supported.
+
+use crate::{CHHEntry, ConstHashHeap, HashHeap};
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// Reference model of a keyed priority queue.  Every operation other than
+/// insertion takes O(n) time.
+#[derive(Clone, Debug)]
+pub struct ModelHeap<KT,VT>
+{
+  entries : Vec<(KT,VT)>,
+  lessthan : fn(&VT,&VT) -> bool,  // lower priority
+}
+impl<KT:Eq, VT:PartialOrd> ModelHeap<KT,VT> {
+  /// creates an empty model; true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    Self::with_cmp(if maxheap {|a,b|a<b} else {|a,b|b<a})
+  }
+
+  /// creates an empty model where `cmp(a,b)` means that a has lower
+  /// priority than b, as in [HashHeap::set_cmp]
+  pub fn with_cmp(cmp:fn(&VT,&VT) -> bool) -> Self {
+    ModelHeap { entries:Vec::new(), lessthan:cmp }
+  }
+
+  fn position(&self, key:&KT) -> Option<usize> {
+    self.entries.iter().position(|(k,_)|k==key)
+  }
+
+  /// adds or replaces a pair, returning the replaced pair
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    match self.position(&key) {
+      Some(i) => Some(core::mem::replace(&mut self.entries[i], (key,val))),
+      None => { self.entries.push((key,val)); None },
+    }
+  }
+
+  /// adds a pair only if the key is new
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    if self.position(&key).is_some() { return false; }
+    self.entries.push((key,val));
+    true
+  }
+
+  /// the value associated with the key
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    self.position(key).map(|i|&self.entries[i].1)
+  }
+
+  /// applies the function to the value associated with the key
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    self.position(key).map(|i|f(&mut self.entries[i].1)).is_some()
+  }
+
+  /// removes and returns the pair with the given key
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.position(key).map(|i|self.entries.swap_remove(i))
+  }
+
+  /// returns a pair with the highest priority; if there are ties, any
+  /// one of them
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    let mut best:Option<&(KT,VT)> = None;
+    for e in self.entries.iter() {
+      if best.is_none_or(|b|(self.lessthan)(&b.1,&e.1)) { best = Some(e); }
+    }
+    best.map(|(k,v)|(k,v))
+  }
+
+  /// determines if the two values have the same priority
+  pub fn same_priority(&self, a:&VT, b:&VT) -> bool {
+    !(self.lessthan)(a,b) && !(self.lessthan)(b,a)
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.entries.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+  /// iterates over the entries in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ {
+    self.entries.iter().map(|(k,v)|(k,v))
+  }
+}//impl ModelHeap
+
+/// An operation on a keyed priority queue, for driving a structure with
+/// generated sequences of operations
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<KT,VT> {
+  /// [HashHeap::insert]
+  Insert(KT,VT),
+  /// [HashHeap::push]
+  Push(KT,VT),
+  /// [HashHeap::modify], replacing the value associated with the key
+  Modify(KT,VT),
+  /// [HashHeap::remove]
+  Remove(KT),
+  /// [HashHeap::pop]
+  Pop,
+  /// [HashHeap::top_swap]
+  TopSwap(KT,VT),
+}
+
+/// The result of applying an [Op]: `Flag` for [Op::Push] and [Op::Modify],
+/// `Pair` for the others
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpResult<KT,VT> {
+  Flag(bool),
+  Pair(Option<(KT,VT)>),
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd> Op<KT,VT> {
+  /// applies the operation to a HashHeap
+  pub fn apply(self, hh:&mut HashHeap<KT,VT>) -> OpResult<KT,VT> {
+    match self {
+      Op::Insert(k,v) => OpResult::Pair(hh.insert(k,v)),
+      Op::Push(k,v) => OpResult::Flag(hh.push(k,v)),
+      Op::Modify(k,v) => OpResult::Flag(hh.modify(&k,|x|*x=v).is_some()),
+      Op::Remove(k) => OpResult::Pair(hh.remove(&k)),
+      Op::Pop => OpResult::Pair(hh.pop()),
+      Op::TopSwap(k,v) => OpResult::Pair(hh.top_swap(k,v)),
+    }
+  }
+}
+
+/// applies a sequence of operations to a HashHeap, returning their results
+/// ```
+/// # use hashheap::*;
+/// # use hashheap::testutils::*;
+///   let mut hh = HashHeap::<char,u32>::new_maxheap();
+///   let results = apply_ops(&mut hh, vec![
+///     Op::Insert('a',1), Op::Push('b',5), Op::Push('b',0),
+///     Op::Modify('a',9), Op::Pop, Op::TopSwap('c',3),
+///   ]);
+///   assert_eq!(results[2], OpResult::Flag(false));
+///   assert_eq!(results[4], OpResult::Pair(Some(('a',9))));
+///   assert_eq!(results[5], OpResult::Pair(Some(('b',5))));
+///   assert_eq!(hh.len(), 1);
+/// ```
+pub fn apply_ops<KT,VT,I>(hh:&mut HashHeap<KT,VT>, ops:I) -> Vec<OpResult<KT,VT>>
+where KT:Hash+Eq, VT:PartialOrd, I:IntoIterator<Item=Op<KT,VT>>
+{
+  ops.into_iter().map(|op|op.apply(hh)).collect()
+}
+
+/// The operations of a structure that can be checked against a
+/// [ModelHeap].  Implemented for [HashHeap] and [ConstHashHeap].
+pub trait CheckTarget<KT,VT> {
+  /// adds or replaces a pair, returning the replaced pair, or the given
+  /// pair as an error if it was rejected (because a fixed capacity is full)
+  fn check_insert(&mut self, key:KT, val:VT) -> Result<Option<(KT,VT)>,(KT,VT)>;
+  /// adds a pair only if the key is new, returning true if added
+  fn check_push(&mut self, key:KT, val:VT) -> bool;
+  fn check_get(&self, key:&KT) -> Option<&VT>;
+  fn check_modify(&mut self, key:&KT, f:&mut dyn FnMut(&mut VT)) -> bool;
+  fn check_remove(&mut self, key:&KT) -> Option<(KT,VT)>;
+  fn check_pop(&mut self) -> Option<(KT,VT)>;
+  /// replaces the top entry as in [HashHeap::top_swap]
+  fn check_top_swap(&mut self, key:KT, val:VT) -> Option<(KT,VT)>;
+  fn check_peek(&self) -> Option<(&KT,&VT)>;
+  fn check_len(&self) -> usize;
+  /// the entry at a position of the internal heap array
+  fn check_entry_at(&self, i:usize) -> Option<(&KT,&VT)>;
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd> CheckTarget<KT,VT> for HashHeap<KT,VT> {
+  fn check_insert(&mut self, key:KT, val:VT) -> Result<Option<(KT,VT)>,(KT,VT)> {
+    Ok(self.insert(key,val))
+  }
+  fn check_push(&mut self, key:KT, val:VT) -> bool { self.push(key,val) }
+  fn check_get(&self, key:&KT) -> Option<&VT> { self.get(key) }
+  fn check_modify(&mut self, key:&KT, f:&mut dyn FnMut(&mut VT)) -> bool {
+    self.modify(key,f).is_some()
+  }
+  fn check_remove(&mut self, key:&KT) -> Option<(KT,VT)> { self.remove(key) }
+  fn check_pop(&mut self) -> Option<(KT,VT)> { self.pop() }
+  fn check_top_swap(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    self.top_swap(key,val)
+  }
+  fn check_peek(&self) -> Option<(&KT,&VT)> { self.peek() }
+  fn check_len(&self) -> usize { self.len() }
+  fn check_entry_at(&self, i:usize) -> Option<(&KT,&VT)> { self.get_entry_at(i) }
+}
+
+impl<KT:Hash+Eq+Clone, VT:PartialOrd, const CAP:usize> CheckTarget<KT,VT>
+  for ConstHashHeap<KT,VT,CAP>
+{
+  fn check_insert(&mut self, key:KT, val:VT) -> Result<Option<(KT,VT)>,(KT,VT)> {
+    if self.needs_resize() && self.get(&key).is_none() { return Err((key,val)); }
+    match self.entry(key) {
+      CHHEntry::Occupied(mut e) => Ok(Some((e.key().clone(), e.insert(val)))),
+      CHHEntry::Vacant(e) => { e.insert(val); Ok(None) },
+    }
+  }
+  fn check_push(&mut self, key:KT, val:VT) -> bool {
+    self.get(&key).is_none() && self.check_insert(key,val).is_ok()
+  }
+  fn check_get(&self, key:&KT) -> Option<&VT> { self.get(key) }
+  fn check_modify(&mut self, key:&KT, f:&mut dyn FnMut(&mut VT)) -> bool {
+    self.modify(key,f)
+  }
+  fn check_remove(&mut self, key:&KT) -> Option<(KT,VT)> { self.remove(key) }
+  fn check_pop(&mut self) -> Option<(KT,VT)> { self.pop() }
+  // there's no top_swap for ConstHashHeap: emulated by insert and pop
+  fn check_top_swap(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    if self.get(&key).is_some() { self.insert(key,val); self.pop() }
+    else if self.size() == 0 { self.insert(key,val); None }
+    else { let top = self.pop(); self.insert(key,val); top }
+  }
+  fn check_peek(&self) -> Option<(&KT,&VT)> { self.peek() }
+  fn check_len(&self) -> usize { self.size() }
+  fn check_entry_at(&self, i:usize) -> Option<(&KT,&VT)> { self.get_entry_at(i) }
+}
+
+/// Applies the same operations to a [ModelHeap] and a structure under
+/// test, panicking with a description of the first difference found.
+/// Keys and values are cloned so that both sides receive their own copies.
+/// ```
+/// # use hashheap::*;
+/// # use hashheap::testutils::*;
+///   let mut hh = HashHeap::<u32,i32>::new_minheap();
+///   hh.set_hash(|k|(*k as usize) % 4);  // many collisions
+///   let mut checker = DiffChecker::new(hh, false);
+///   for i in 0..20 { checker.insert(i, (i as i32 * 7) % 11).unwrap(); }
+///   checker.modify(&3, |v|*v -= 100);
+///   checker.remove(&5);
+///   while checker.pop().is_some() {}
+///   checker.check_all();
+/// ```
+#[derive(Clone, Debug)]
+pub struct DiffChecker<KT,VT,H>
+{
+  model : ModelHeap<KT,VT>,
+  target : H,
+  ops : usize,  // number of operations applied
+}
+impl<KT,VT,H> DiffChecker<KT,VT,H>
+where KT:Eq+Clone+Debug, VT:PartialOrd+Clone+Debug, H:CheckTarget<KT,VT>
+{
+  /// checks the given structure, which should be empty, against a model
+  /// with the default ordering; true = maxheap, false = minheap
+  pub fn new(target:H, maxheap:bool) -> Self {
+    Self::with_model(target, ModelHeap::new(maxheap))
+  }
+
+  /// checks the given structure against a model with the same comparison
+  /// function, as set by [HashHeap::set_cmp]
+  pub fn with_cmp(target:H, cmp:fn(&VT,&VT) -> bool) -> Self {
+    Self::with_model(target, ModelHeap::with_cmp(cmp))
+  }
+
+  fn with_model(target:H, model:ModelHeap<KT,VT>) -> Self {
+    let mut checker = DiffChecker { model, target, ops:0 };
+    checker.check_all();
+    checker
+  }
+
+  /// the structure under test
+  pub fn target(&self) -> &H { &self.target }
+
+  /// the reference model
+  pub fn model(&self) -> &ModelHeap<KT,VT> { &self.model }
+
+  /// returns the structure under test
+  pub fn into_target(self) -> H { self.target }
+
+  fn fail(&self, what:&str) -> ! {
+    panic!("DiffChecker: after {} operations: {}", self.ops, what)
+  }
+
+  /// Inserts into both, which must return equal replaced pairs.  If the
+  /// target rejects the pair because it's full, the model is left
+  /// unchanged and the pair is returned as an error.
+  pub fn insert(&mut self, key:KT, val:VT) -> Result<Option<(KT,VT)>,(KT,VT)> {
+    self.ops += 1;
+    let found = self.target.check_insert(key.clone(), val.clone());
+    if let Ok(replaced) = &found {
+      let expected = self.model.insert(key.clone(), val.clone());
+      if *replaced != expected {
+        self.fail(&format!("insert({:?},{:?}) returned {:?}, expected {:?}",
+                           key, val, replaced, expected));
+      }
+    }
+    self.check_invariants();
+    found
+  }
+
+  /// pushes into both, which must agree on whether the key is new
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    self.ops += 1;
+    let existed = self.model.get(&key).is_some();
+    let pushed = self.target.check_push(key.clone(), val.clone());
+    if pushed == existed {
+      self.fail(&format!("push({:?},{:?}) returned {}", key, val, pushed));
+    }
+    if pushed { self.model.push(key,val); }
+    self.check_invariants();
+    pushed
+  }
+
+  /// looks up the key in both, which must return equal values
+  pub fn get(&mut self, key:&KT) -> Option<VT> {
+    self.ops += 1;
+    let (expected, found) = (self.model.get(key), self.target.check_get(key));
+    if expected != found {
+      self.fail(&format!("get({:?}) returned {:?}, expected {:?}", key, found, expected));
+    }
+    found.cloned()
+  }
+
+  /// applies the same modification to both
+  pub fn modify<F:FnMut(&mut VT)>(&mut self, key:&KT, mut f:F) -> bool {
+    self.ops += 1;
+    let expected = self.model.modify(key, &mut f);
+    let found = self.target.check_modify(key, &mut f);
+    if expected != found {
+      self.fail(&format!("modify({:?}) returned {}, expected {}", key, found, expected));
+    }
+    self.check_invariants();
+    found
+  }
+
+  /// removes the key from both, which must return equal pairs
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.ops += 1;
+    let expected = self.model.remove(key);
+    let found = self.target.check_remove(key);
+    if expected != found {
+      self.fail(&format!("remove({:?}) returned {:?}, expected {:?}", key, found, expected));
+    }
+    self.check_invariants();
+    found
+  }
+
+  /// Pops from the target, which must return a pair of the highest
+  /// priority found in the model.  The same pair is then removed from
+  /// the model.
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    self.ops += 1;
+    let best = self.model.peek().map(|(_,v)|v.clone());
+    let found = self.target.check_pop();
+    match (&found, &best) {
+      (None, None) => {},
+      (Some((k,v)), Some(b)) if self.model.get(k) == Some(v)
+                                && self.model.same_priority(v,b) => {
+        self.model.remove(k);
+      },
+      _ => self.fail(&format!("pop returned {:?}, expected priority {:?}", found, best)),
+    }
+    self.check_invariants();
+    found
+  }
+
+  /// Replaces the top entry of both as in [HashHeap::top_swap].  The target
+  /// must return a pair of the highest priority found in the model,
+  /// after the replacement if the key already exists.
+  pub fn top_swap(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    self.ops += 1;
+    let exists = self.model.get(&key).is_some();
+    if exists { self.model.insert(key.clone(), val.clone()); }
+    let best = self.model.peek().map(|(_,v)|v.clone());
+    let found = self.target.check_top_swap(key.clone(), val.clone());
+    match (&found, &best) {
+      (None, None) => { self.model.push(key,val); },
+      (Some((k,v)), Some(b)) if self.model.get(k) == Some(v)
+                                && self.model.same_priority(v,b) => {
+        self.model.remove(k);
+        if !exists { self.model.push(key,val); }
+      },
+      _ => self.fail(&format!("top_swap returned {:?}, expected priority {:?}", found, best)),
+    }
+    self.check_invariants();
+    found
+  }//top_swap
+
+  /// Applies the operation to both the model and the target, returning
+  /// the target's result.  An insertion rejected by a full target
+  /// returns `Pair(None)`.
+  /// ```
+  /// # use hashheap::*;
+  /// # use hashheap::testutils::*;
+  ///   let mut checker = DiffChecker::new(HashHeap::<u8,u8>::new_minheap(), false);
+  ///   let mut x:u32 = 1;
+  ///   for _ in 0..1000 {
+  ///     x = x.wrapping_mul(1103515245).wrapping_add(12345);
+  ///     let (k, v) = ((x>>8) as u8 % 32, (x>>16) as u8);
+  ///     let op = match x % 6 {
+  ///       0 => Op::Insert(k,v), 1 => Op::Push(k,v), 2 => Op::Modify(k,v),
+  ///       3 => Op::Remove(k), 4 => Op::Pop, _ => Op::TopSwap(k,v),
+  ///     };
+  ///     checker.apply(op);
+  ///   }
+  ///   checker.check_all();
+  /// ```
+  pub fn apply(&mut self, op:Op<KT,VT>) -> OpResult<KT,VT> {
+    match op {
+      Op::Insert(k,v) => OpResult::Pair(self.insert(k,v).unwrap_or(None)),
+      Op::Push(k,v) => OpResult::Flag(self.push(k,v)),
+      Op::Modify(k,v) => OpResult::Flag(self.modify(&k,|x|*x=v.clone())),
+      Op::Remove(k) => OpResult::Pair(self.remove(&k)),
+      Op::Pop => OpResult::Pair(self.pop()),
+      Op::TopSwap(k,v) => OpResult::Pair(self.top_swap(k,v)),
+    }
+  }//apply
+
+  /// Peeks at both; the target must return a pair of the highest priority
+  /// found in the model.
+  pub fn peek(&mut self) -> Option<(KT,VT)> {
+    self.ops += 1;
+    let found = self.target.check_peek().map(|(k,v)|(k.clone(),v.clone()));
+    match (&found, self.model.peek()) {
+      (None, None) => {},
+      (Some((k,v)), Some((_,b))) if self.model.get(k) == Some(v)
+                                    && self.model.same_priority(v,b) => {},
+      _ => self.fail(&format!("peek returned {:?}, expected priority {:?}",
+                              found, self.model.peek().map(|p|p.1))),
+    }
+    found
+  }
+
+  // checks sizes and the heap property of the target: O(n)
+  fn check_invariants(&self) {
+    let n = self.target.check_len();
+    if n != self.model.len() {
+      self.fail(&format!("size is {}, expected {}", n, self.model.len()));
+    }
+    for i in 1..n {
+      let child = self.target.check_entry_at(i).map(|p|p.1);
+      let parent = self.target.check_entry_at((i-1)/2).map(|p|p.1);
+      match (parent, child) {
+        (Some(p), Some(c)) if !(self.model.lessthan)(p,c) => {},
+        _ => self.fail(&format!("heap property violated at index {}", i)),
+      }
+    }
+  }//check_invariants
+
+  /// checks that every entry of the model is found in the target, in
+  /// addition to sizes and the heap property.  O(n) for a HashHeap.
+  pub fn check_all(&mut self) {
+    self.check_invariants();
+    for (k,v) in self.model.iter() {
+      let found = self.target.check_get(k);
+      if found != Some(v) {
+        self.fail(&format!("key {:?} has value {:?}, expected {:?}", k, found, v));
+      }
+    }
+  }//check_all
+}//impl DiffChecker
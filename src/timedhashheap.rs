@@ -0,0 +1,127 @@
+//! This module contains [TimedHashHeap], a [HashHeap] that records when
+//! each entry was inserted and when it was last changed, for monitoring
+//! how long entries wait in a queue.  The times are kept as [WithMeta]
+//! payloads, so they don't affect priorities.
+
+use crate::{HashHeap, WithMeta};
+use core::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// The times recorded for each entry of a [TimedHashHeap]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryTimes {
+  /// when the key was inserted
+  pub inserted : Instant,
+  /// when the value was last inserted or modified
+  pub updated : Instant,
+}
+
+/// A HashHeap with insertion and modification times: see the
+/// [module documentation](crate::timedhashheap).
+/// ```
+/// # use hashheap::*;
+///   let mut queue = TimedHashHeap::<&str,u32>::new(false);
+///   queue.insert("a", 3);
+///   queue.insert("b", 1);
+///   std::thread::sleep(std::time::Duration::from_millis(2));
+///   queue.modify(&"a", |v| *v = 2);
+///   let waited = queue.top_age().unwrap();  // "b" has waited longest
+///   assert!(waited >= std::time::Duration::from_millis(2));
+///   let a = queue.times(&"a").unwrap();
+///   assert!(a.updated > a.inserted);
+///   assert_eq!(queue.pop().map(|p|p.0), Some("b"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TimedHashHeap<KT,VT>
+{
+  heap : HashHeap<KT,WithMeta<VT,EntryTimes>>,
+}
+impl<KT:Hash+Eq, VT:PartialOrd> TimedHashHeap<KT,VT> {
+
+  /// creates an empty structure; true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    TimedHashHeap { heap : HashHeap::with_capacity(0,maxheap) }
+  }
+
+  /// Add or change a key-value pair, returning the replaced pair, if it
+  /// exists.  The insertion time of an existing key is kept.
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    let now = Instant::now();
+    let inserted = self.heap.get_meta(&key).map_or(now, |t|t.inserted);
+    self.heap.insert_with_meta(key, val, EntryTimes { inserted, updated:now })
+      .map(|(k,v,_)|(k,v))
+  }
+
+  /// Version of insert that does not replace an existing key, returning
+  /// false instead.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    let now = Instant::now();
+    self.heap.push(key, WithMeta::new(val, EntryTimes { inserted:now, updated:now }))
+  }
+
+  /// returns the value associated with the key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    self.heap.get(key).map(|w|&w.val)
+  }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// modifies the value associated with the key and records the time of
+  /// modification: see [HashHeap::modify]
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    let now = Instant::now();
+    self.heap.modify(key, |w| { f(&mut w.val); w.meta.updated = now; }).is_some()
+  }
+
+  /// removes and returns the pair with the given key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.heap.remove(key).map(|(k,w)|(k,w.val))
+  }
+
+  /// returns the entry with the highest priority.  O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> {
+    self.heap.peek().map(|(k,w)|(k,&w.val))
+  }
+
+  /// removes and returns the entry with the highest priority.  O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    self.heap.pop().map(|(k,w)|(k,w.val))
+  }
+
+  /// returns the times recorded for the key.  O(1)
+  pub fn times(&self, key:&KT) -> Option<EntryTimes> {
+    self.heap.get_meta(key).copied()
+  }
+
+  /// when the key was inserted
+  pub fn inserted_at(&self, key:&KT) -> Option<Instant> {
+    self.times(key).map(|t|t.inserted)
+  }
+
+  /// when the value associated with the key was last inserted or modified
+  pub fn updated_at(&self, key:&KT) -> Option<Instant> {
+    self.times(key).map(|t|t.updated)
+  }
+
+  /// how long ago the key was inserted
+  pub fn entry_age(&self, key:&KT) -> Option<Duration> {
+    self.inserted_at(key).map(|t|t.elapsed())
+  }
+
+  /// how long ago the highest-priority entry was inserted.  O(1)
+  pub fn top_age(&self) -> Option<Duration> {
+    self.heap.peek_with_meta().map(|(_,_,t)|t.inserted.elapsed())
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// iterates over the entries and their times in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT,&EntryTimes)> + '_ {
+    self.heap.iter().map(|(k,w)|(k,&w.val,&w.meta))
+  }
+}//impl TimedHashHeap
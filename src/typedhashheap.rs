@@ -0,0 +1,141 @@
+//! This module makes the polarity of a hashheap part of its type.  A
+//! [HashHeap] is a min- or a max-heap depending on how it was created,
+//! which is easy to get wrong: `From<Vec<_>>` creates a max-hashheap while
+//! [FromIterator] creates a min-hashheap.  The aliases [MinHashHeap] and
+//! [MaxHashHeap] of [TypedHashHeap] take a marker type, [Min] or [Max],
+//! whose comparison function is fixed at compile time, so that every
+//! constructor and conversion agrees with the type.
+//!
+//! A TypedHashHeap dereferences to the underlying HashHeap for all
+//! operations that don't change it, such as peek, get and the iterators.
+//! Operations that change it are provided directly, so that the ordering
+//! cannot be replaced.
+
+use crate::{HashHeap, UserFn};
+use core::hash::Hash;
+use core::marker::PhantomData;
+
+/// The polarity of a [TypedHashHeap]: [Min] or [Max]
+pub trait Polarity {
+  /// true for a max-hashheap
+  const MAXHEAP : bool;
+  /// true if a has lower priority than b
+  fn lessthan<VT:PartialOrd>(a:&VT, b:&VT) -> bool;
+}
+
+/// marker for min-hashheaps, whose smallest value is at the top
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Min;
+impl Polarity for Min {
+  const MAXHEAP : bool = false;
+  fn lessthan<VT:PartialOrd>(a:&VT, b:&VT) -> bool { b < a }
+}
+
+/// marker for max-hashheaps, whose largest value is at the top
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Max;
+impl Polarity for Max {
+  const MAXHEAP : bool = true;
+  fn lessthan<VT:PartialOrd>(a:&VT, b:&VT) -> bool { a < b }
+}
+
+/// A HashHeap whose polarity is given by a type: see the
+/// [module documentation](crate::typedhashheap).
+/// ```
+/// # use hashheap::*;
+///   let mut hh:MinHashHeap<&str,u32> = vec![("a",3),("b",1)].into();
+///   hh.insert("c", 2);
+///   assert_eq!(hh.peek(), Some((&"b",&1)));   // through Deref
+///   let mx:MaxHashHeap<u32,u32> = (0..10).map(|i|(i,i)).collect();
+///   assert_eq!(mx.peek(), Some((&9,&9)));
+///   let plain:HashHeap<u32,u32> = mx.into_hashheap();
+///   assert!(plain.is_max_hashheap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TypedHashHeap<KT,VT,P:Polarity>
+{
+  heap : HashHeap<KT,VT>,
+  polarity : PhantomData<P>,
+}
+
+/// a min-hashheap by type
+pub type MinHashHeap<KT,VT> = TypedHashHeap<KT,VT,Min>;
+/// a max-hashheap by type
+pub type MaxHashHeap<KT,VT> = TypedHashHeap<KT,VT,Max>;
+
+impl<KT:Hash+Eq, VT:PartialOrd, P:Polarity> TypedHashHeap<KT,VT,P> {
+
+  /// creates an empty structure with the default capacity
+  pub fn new() -> Self { Self::with_capacity(0) }
+
+  /// creates an empty structure with the given capacity
+  pub fn with_capacity(cap:usize) -> Self {
+    TypedHashHeap {
+      heap : HashHeap::with_cmp(cap, P::MAXHEAP, UserFn::Ptr(P::lessthan::<VT>)),
+      polarity : PhantomData,
+    }
+  }
+
+  /// builds the structure from pairs in O(n) time with heapify; later
+  /// pairs replace earlier ones with the same key
+  pub fn from_pairs(pairs:Vec<(KT,VT)>) -> Self {
+    let mut hh = Self::with_capacity(pairs.len());
+    hh.heap.heapify(pairs);
+    hh
+  }
+
+  /// see [HashHeap::insert]
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> { self.heap.insert(key,val) }
+
+  /// see [HashHeap::push]
+  pub fn push(&mut self, key:KT, val:VT) -> bool { self.heap.push(key,val) }
+
+  /// see [HashHeap::modify]
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> Option<crate::Moved> {
+    self.heap.modify(key,f)
+  }
+
+  /// see [HashHeap::remove]
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> { self.heap.remove(key) }
+
+  /// see [HashHeap::pop]
+  pub fn pop(&mut self) -> Option<(KT,VT)> { self.heap.pop() }
+
+  /// see [HashHeap::retain]
+  pub fn retain<F:FnMut(&KT,&VT)->bool>(&mut self, pred:F) { self.heap.retain(pred) }
+
+  /// see [HashHeap::clear]
+  pub fn clear(&mut self) { self.heap.clear() }
+
+  /// returns the underlying HashHeap
+  pub fn into_hashheap(self) -> HashHeap<KT,VT> { self.heap }
+}//impl TypedHashHeap
+
+impl<KT:Hash+Eq, VT:PartialOrd, P:Polarity> Default for TypedHashHeap<KT,VT,P> {
+  fn default() -> Self { Self::new() }
+}
+
+impl<KT,VT,P:Polarity> core::ops::Deref for TypedHashHeap<KT,VT,P> {
+  type Target = HashHeap<KT,VT>;
+  fn deref(&self) -> &HashHeap<KT,VT> { &self.heap }
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd, P:Polarity> From<Vec<(KT,VT)>> for TypedHashHeap<KT,VT,P> {
+  fn from(pairs:Vec<(KT,VT)>) -> Self { Self::from_pairs(pairs) }
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd, P:Polarity> FromIterator<(KT,VT)> for TypedHashHeap<KT,VT,P> {
+  fn from_iter<T:IntoIterator<Item=(KT,VT)>>(iter:T) -> Self {
+    Self::from_pairs(iter.into_iter().collect())
+  }
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd, P:Polarity> Extend<(KT,VT)> for TypedHashHeap<KT,VT,P> {
+  fn extend<I:IntoIterator<Item=(KT,VT)>>(&mut self, iter:I) { self.heap.extend(iter) }
+}
+
+impl<KT:Hash+Eq, VT:PartialOrd, P:Polarity> IntoIterator for TypedHashHeap<KT,VT,P> {
+  type Item = (KT,VT);
+  type IntoIter = crate::IntoIter<KT,VT>;
+  fn into_iter(self) -> Self::IntoIter { self.heap.into_iter() }
+}
@@ -0,0 +1,159 @@
+//! A secondary index of values for [HashHeap], enabled by the `valindex`
+//! feature.  [HashHeap::contains_val] searches the heap, pruning only the
+//! subtrees whose roots have lower priority than the value sought, which
+//! takes O(n) time in the worst case.  A [ValueIndexedHashHeap] also keeps
+//! a [BTreeMap] from each value to the set of keys that have it, so that
+//! [contains_val](ValueIndexedHashHeap::contains_val),
+//! [count_val](ValueIndexedHashHeap::count_val) and
+//! [keys_with_value](ValueIndexedHashHeap::keys_with_value) take O(log n)
+//! time.  The price is a clone of every key and value and an additional
+//! O(log n) update of the index with every change.  Values must be totally
+//! ordered ([Ord]).
+
+use crate::HashHeap;
+use core::hash::Hash;
+use std::collections::{BTreeMap, HashSet};
+
+/// A HashHeap with an index of its values: see the
+/// [module documentation](crate::valueindex).
+/// ```
+/// # use hashheap::*;
+///   let mut jobs = ValueIndexedHashHeap::<&str,u32>::new(false);
+///   jobs.insert("a", 3);
+///   jobs.insert("b", 5);
+///   jobs.insert("c", 3);
+///   assert!(jobs.contains_val(&5));
+///   assert_eq!(jobs.count_val(&3), 2);
+///   jobs.modify(&"a", |v| *v = 5);
+///   let mut fives:Vec<_> = jobs.keys_with_value(&5).collect();
+///   fives.sort();
+///   assert_eq!(fives, vec![&"a",&"b"]);
+///   assert_eq!(jobs.pop(), Some(("c",3)));
+///   assert!(!jobs.contains_val(&3));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ValueIndexedHashHeap<KT,VT>
+{
+  heap : HashHeap<KT,VT>,
+  index : BTreeMap<VT,HashSet<KT>>,
+}
+impl<KT:Hash+Eq+Clone, VT:Ord+Clone> ValueIndexedHashHeap<KT,VT> {
+
+  /// creates an empty structure; true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    Self::from_hashheap(HashHeap::with_capacity(0,maxheap))
+  }
+
+  /// indexes the values of an existing HashHeap in O(n log n) time
+  pub fn from_hashheap(heap:HashHeap<KT,VT>) -> Self {
+    let mut index:BTreeMap<VT,HashSet<KT>> = BTreeMap::new();
+    for (k,v) in heap.iter() {
+      index.entry(v.clone()).or_default().insert(k.clone());
+    }
+    ValueIndexedHashHeap { heap, index }
+  }
+
+  fn index_remove(&mut self, key:&KT, val:&VT) {
+    if let Some(keys) = self.index.get_mut(val) {
+      keys.remove(key);
+      if keys.is_empty() { self.index.remove(val); }
+    }
+  }
+
+  fn index_add(&mut self, key:&KT, val:&VT) {
+    self.index.entry(val.clone()).or_default().insert(key.clone());
+  }
+
+  /// Add or change a key-value pair, returning the replaced pair, if it
+  /// exists.  O(log n)
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    self.index_add(&key,&val);
+    let old = self.heap.insert(key,val);
+    if let Some((k,v)) = &old {
+      if self.heap.get(k) != Some(v) { self.index_remove(k,v); }
+    }
+    old
+  }
+
+  /// Version of insert that does not replace an existing key, returning
+  /// false instead.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    if self.heap.contains_key(&key) { return false; }
+    self.index_add(&key,&val);
+    self.heap.push(key,val)
+  }
+
+  /// returns the value associated with the key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> { self.heap.get(key) }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// Applies the closure to the value associated with the key and
+  /// repositions it.  Returns false if the key was not found.  O(log n)
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    let old = match self.heap.get(key) {
+      Some(v) => v.clone(),
+      None => { return false; },
+    };
+    self.heap.modify(key,f);
+    let new = self.heap[key].clone();
+    if new != old {
+      self.index_remove(key,&old);
+      self.index_add(key,&new);
+    }
+    true
+  }//modify
+
+  /// removes and returns the pair with the given key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    let (k,v) = self.heap.remove(key)?;
+    self.index_remove(&k,&v);
+    Some((k,v))
+  }
+
+  /// returns the pair with the highest priority.  O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> { self.heap.peek() }
+
+  /// removes and returns the pair with the highest priority.  O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    let (k,v) = self.heap.pop()?;
+    self.index_remove(&k,&v);
+    Some((k,v))
+  }
+
+  /// removes all entries
+  pub fn clear(&mut self) {
+    self.heap.clear();
+    self.index.clear();
+  }
+
+  /// determines if some key has the given value.  O(log n)
+  pub fn contains_val(&self, val:&VT) -> bool { self.index.contains_key(val) }
+
+  /// the number of keys with the given value.  O(log n)
+  pub fn count_val(&self, val:&VT) -> usize {
+    self.index.get(val).map_or(0, |keys|keys.len())
+  }
+
+  /// iterates over the keys with the given value, in no particular order.
+  /// O(log n) to start.
+  pub fn keys_with_value<'a>(&'a self, val:&VT) -> impl Iterator<Item=&'a KT> + 'a {
+    self.index.get(val).into_iter().flatten()
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.is_empty() }
+
+  /// iterates over the entries in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ { self.heap.iter() }
+
+  /// the underlying HashHeap, for operations that don't change it
+  pub fn hashheap(&self) -> &HashHeap<KT,VT> { &self.heap }
+
+  /// drops the index and returns the HashHeap
+  pub fn into_hashheap(self) -> HashHeap<KT,VT> { self.heap }
+}//impl ValueIndexedHashHeap
@@ -0,0 +1,127 @@
+//! This module contains [WatchedHashHeap], a [HashHeap] that notifies
+//! watchers whenever the identity of its highest-priority entry changes:
+//! when a new best entry is inserted, when the top entry is popped or
+//! removed, or when a modification moves an entry into or out of the top
+//! position.  Each watcher is a [std::sync::mpsc::Receiver] returned by
+//! [WatchedHashHeap::watch_top], which receives the key of the new top
+//! entry, or None when the structure becomes empty.  A dispatcher thread
+//! can thus block on the receiver instead of polling `peek`, for example
+//! to detect opportunities for preemption.  Changes to the value of the
+//! top entry that leave it at the top are not reported.
+
+use crate::HashHeap;
+use core::hash::Hash;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// A HashHeap with notification of changes to the top entry: see the
+/// [module documentation](crate::watchhashheap).
+/// ```
+/// # use hashheap::*;
+///   let mut jobs = WatchedHashHeap::<&str,u32>::new(true);
+///   let rx = jobs.watch_top();
+///   jobs.insert("low", 1);
+///   jobs.insert("high", 9);
+///   jobs.insert("mid", 5);       // not reported: "high" is still on top
+///   jobs.modify(&"mid", |p| *p = 10);
+///   jobs.pop();
+///   let changes:Vec<_> = rx.try_iter().collect();
+///   assert_eq!(changes, vec![Some("low"), Some("high"), Some("mid"), Some("high")]);
+/// ```
+#[derive(Debug)]
+pub struct WatchedHashHeap<KT,VT>
+{
+  heap : HashHeap<KT,VT>,
+  top : Option<KT>,   // key of the top entry as last reported
+  watchers : Vec<Sender<Option<KT>>>,
+}
+impl<KT:Hash+Eq+Clone, VT:PartialOrd> WatchedHashHeap<KT,VT> {
+
+  /// creates an empty structure; true = maxheap, false = minheap
+  pub fn new(maxheap:bool) -> Self {
+    Self::from_hashheap(HashHeap::with_capacity(0,maxheap))
+  }
+
+  /// wraps an existing HashHeap
+  pub fn from_hashheap(heap:HashHeap<KT,VT>) -> Self {
+    let top = heap.peek().map(|(k,_)|k.clone());
+    WatchedHashHeap { heap, top, watchers:Vec::new() }
+  }
+
+  /// Returns a receiver of the key of each new top entry, or None when
+  /// the structure becomes empty.  Dropping the receiver unsubscribes it.
+  pub fn watch_top(&mut self) -> Receiver<Option<KT>> {
+    let (tx,rx) = channel();
+    self.watchers.push(tx);
+    rx
+  }
+
+  /// the number of receivers currently watching
+  pub fn watchers(&self) -> usize { self.watchers.len() }
+
+  // notifies watchers if the top key changed since it was last reported
+  fn check_top(&mut self) {
+    let top = self.heap.peek().map(|(k,_)|k);
+    if top == self.top.as_ref() { return; }
+    self.top = top.cloned();
+    let top = &self.top;
+    self.watchers.retain(|tx| tx.send(top.clone()).is_ok());
+  }//check_top
+
+  /// Add or change a key-value pair, returning the replaced pair, if it
+  /// exists: see [HashHeap::insert]
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    let replaced = self.heap.insert(key,val);
+    self.check_top();
+    replaced
+  }
+
+  /// Version of insert that does not replace an existing key, returning
+  /// false instead.
+  pub fn push(&mut self, key:KT, val:VT) -> bool {
+    let added = self.heap.push(key,val);
+    if added { self.check_top(); }
+    added
+  }
+
+  /// returns the value associated with the key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> { self.heap.get(key) }
+
+  /// determines if the key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool { self.heap.contains_key(key) }
+
+  /// modifies the value associated with the key: see [HashHeap::modify]
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    let found = self.heap.modify(key,f).is_some();
+    if found { self.check_top(); }
+    found
+  }
+
+  /// removes and returns the pair with the given key.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    let removed = self.heap.remove(key);
+    if removed.is_some() { self.check_top(); }
+    removed
+  }
+
+  /// returns the entry with the highest priority.  O(1)
+  pub fn peek(&self) -> Option<(&KT,&VT)> { self.heap.peek() }
+
+  /// removes and returns the entry with the highest priority.  O(log n)
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    let popped = self.heap.pop();
+    if popped.is_some() { self.check_top(); }
+    popped
+  }
+
+  /// the number of entries
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// iterates over the entries in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ { self.heap.iter() }
+
+  /// returns the underlying HashHeap; the watchers are disconnected
+  pub fn into_hashheap(self) -> HashHeap<KT,VT> { self.heap }
+}//impl WatchedHashHeap
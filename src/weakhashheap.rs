@@ -0,0 +1,156 @@
+//! This module contains [WeakHashHeap], a [HashHeap] whose entries can
+//! *expire* when the objects they refer to no longer exist.  Liveness is
+//! determined by a function on keys supplied at construction.  For the
+//! common case of keys that are weak references to [Rc] objects, the
+//! [WeakKey] type and the [WeakHashHeap::new_weak] constructor are
+//! provided.  Expired entries are skipped and purged lazily by
+//! [WeakHashHeap::peek] and [WeakHashHeap::pop], or all at once by
+//! [WeakHashHeap::purge].
+
+use crate::HashHeap;
+use core::hash::{Hash, Hasher};
+use std::rc::{Rc, Weak};
+
+/// A weak reference to an [Rc] that can be used as a key.  Two WeakKeys
+/// are equal if and only if they point to the same allocation, and the
+/// hash is computed from the address.  The address cannot be reused while
+/// a WeakKey exists, because weak references keep the allocation alive
+/// (but not the object inside it).
+#[derive(Debug)]
+pub struct WeakKey<T>(Weak<T>);
+impl<T> WeakKey<T> {
+  /// creates a weak key referring to the given object
+  pub fn new(rc:&Rc<T>) -> Self { WeakKey(Rc::downgrade(rc)) }
+  /// returns a strong reference to the object, if it still exists
+  pub fn upgrade(&self) -> Option<Rc<T>> { self.0.upgrade() }
+  /// determines if the object still exists
+  pub fn is_alive(&self) -> bool { self.0.strong_count() > 0 }
+}
+impl<T> Clone for WeakKey<T> {
+  fn clone(&self) -> Self { WeakKey(self.0.clone()) }
+}
+impl<T> PartialEq for WeakKey<T> {
+  fn eq(&self, other:&Self) -> bool { Weak::ptr_eq(&self.0,&other.0) }
+}
+impl<T> Eq for WeakKey<T> {}
+impl<T> Hash for WeakKey<T> {
+  fn hash<H:Hasher>(&self, state:&mut H) {
+    (Weak::as_ptr(&self.0) as *const () as usize).hash(state);
+  }
+}
+
+/// A [HashHeap] that skips and purges entries whose keys are no longer
+/// alive: see the [module documentation](crate::weakhashheap).
+/// ```
+/// # use hashheap::*;
+/// # use std::rc::Rc;
+///   let job1 = Rc::new("job1");
+///   let job2 = Rc::new("job2");
+///   let mut hp = WeakHashHeap::<WeakKey<&str>,u32>::new_weak(false);
+///   hp.insert(WeakKey::new(&job1), 1);
+///   hp.insert(WeakKey::new(&job2), 2);
+///   drop(job1);  // cancelled
+///   let (key,pri) = hp.pop().unwrap();
+///   assert_eq!(*key.upgrade().unwrap(), "job2");
+///   assert_eq!(hp.len(), 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct WeakHashHeap<KT,VT>
+{
+  heap : HashHeap<KT,VT>,
+  alive : fn(&KT) -> bool,
+}
+impl<KT:Hash+Eq, VT:PartialOrd> WeakHashHeap<KT,VT> {
+
+  /// creates an empty structure that uses the given function to determine
+  /// if a key is still alive; true = maxheap, false = minheap
+  pub fn with_liveness(maxheap:bool, alive:fn(&KT) -> bool) -> Self {
+    WeakHashHeap {
+      heap : HashHeap::with_capacity(0,maxheap),
+      alive,
+    }
+  }
+
+  /// Add or change a key-value pair, returning the replaced pair, if
+  /// it exists.
+  pub fn insert(&mut self, key:KT, val:VT) -> Option<(KT,VT)> {
+    self.heap.insert(key,val)
+  }
+
+  /// returns the value associated with a live key.  O(1)
+  pub fn get(&self, key:&KT) -> Option<&VT> {
+    if (self.alive)(key) { self.heap.get(key) } else { None }
+  }
+
+  /// determines if a live key exists.  O(1)
+  pub fn contains_key(&self, key:&KT) -> bool {
+    (self.alive)(key) && self.heap.contains_key(key)
+  }
+
+  /// modifies the value associated with a live key: see [HashHeap::modify]
+  pub fn modify<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    (self.alive)(key) && self.heap.modify(key,f).is_some()
+  }
+
+  /// removes and returns the pair with the given key, if it exists,
+  /// whether or not it's alive.  O(log n)
+  pub fn remove(&mut self, key:&KT) -> Option<(KT,VT)> {
+    self.heap.remove(key)
+  }
+
+  // removes expired entries from the top of the heap
+  fn purge_top(&mut self) {
+    while let Some((k,_)) = self.heap.peek() {
+      if (self.alive)(k) { break; }
+      self.heap.pop();
+    }
+  }
+
+  /// returns the live entry with the highest priority, after purging any
+  /// expired entries with higher priority.  Amortized O(log n).
+  pub fn peek(&mut self) -> Option<(&KT,&VT)> {
+    self.purge_top();
+    self.heap.peek()
+  }
+
+  /// removes and returns the live entry with the highest priority, after
+  /// purging any expired entries with higher priority.  Amortized O(log n).
+  pub fn pop(&mut self) -> Option<(KT,VT)> {
+    self.purge_top();
+    self.heap.pop()
+  }
+
+  /// removes all expired entries, returning the number removed.  This
+  /// operation takes O(n + d log n) time where d is the number removed.
+  pub fn purge(&mut self) -> usize {
+    let alive = self.alive;
+    let hp = &self.heap;
+    let dead:Vec<usize> = hp.vals.iter()
+      .filter(|(_,h)| hp.keys[hp.kmap[h].0].as_ref().is_some_and(|k|!alive(k)))
+      .map(|(_,h)|*h).collect();
+    for h in dead.iter() {
+      self.heap.remove_slot(*h);
+    }
+    dead.len()
+  }//purge
+
+  /// the number of entries, including expired entries not yet purged
+  pub fn len(&self) -> usize { self.heap.len() }
+
+  /// returns true if there are no entries, live or expired
+  pub fn is_empty(&self) -> bool { self.heap.len() == 0 }
+
+  /// returns an iterator over the live entries in no particular order
+  pub fn iter(&self) -> impl Iterator<Item=(&KT,&VT)> + '_ {
+    let alive = self.alive;
+    self.heap.iter().filter(move |(k,_)|alive(k))
+  }
+}//impl WeakHashHeap
+
+impl<T, VT:PartialOrd> WeakHashHeap<WeakKey<T>,VT> {
+  /// creates an empty structure with [WeakKey] keys, which expire when
+  /// the objects they refer to are dropped; true = maxheap, false = minheap
+  pub fn new_weak(maxheap:bool) -> Self {
+    Self::with_liveness(maxheap, |k|k.is_alive())
+  }
+}
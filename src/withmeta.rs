@@ -0,0 +1,157 @@
+//! This module allows an auxiliary payload, or *metadata*, to be attached
+//! to each entry of a [HashHeap] without affecting its priority.  A value
+//! of type [WithMeta] pairs a priority with a payload, and is compared
+//! only by its priority.  A `HashHeap<KT,WithMeta<VT,A>>` is therefore
+//! ordered exactly as a `HashHeap<KT,VT>` would be, and has additional
+//! methods such as [HashHeap::insert_with_meta] and [HashHeap::get_meta].
+//! When the payload is a version number, [HashHeap::update_if_newer]
+//! ignores updates older than the stored version.
+//!
+//! The alias [PayloadHashHeap] names such a structure by its three types,
+//! the key, the priority and the payload, which itself need not be
+//! comparable.
+
+use crate::HashHeap;
+use core::cmp::Ordering;
+use core::hash::Hash;
+
+/// A priority value paired with an auxiliary payload that's never
+/// compared: see the [module documentation](crate::withmeta).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WithMeta<VT,A> {
+  pub val : VT,
+  pub meta : A,
+}
+impl<VT,A> WithMeta<VT,A> {
+  pub fn new(val:VT, meta:A) -> Self { WithMeta { val, meta } }
+  /// separates the value and the payload
+  pub fn into_parts(self) -> (VT,A) { (self.val, self.meta) }
+}
+impl<VT:PartialEq,A> PartialEq for WithMeta<VT,A> {
+  fn eq(&self, other:&Self) -> bool { self.val == other.val }
+}
+impl<VT:PartialOrd,A> PartialOrd for WithMeta<VT,A> {
+  fn partial_cmp(&self, other:&Self) -> Option<Ordering> {
+    self.val.partial_cmp(&other.val)
+  }
+}
+
+/// A HashHeap from keys of type KT to priorities of type PT, each with a
+/// payload of type A that is never compared.
+/// ```
+/// # use hashheap::*;
+///   struct Task { cmd:String, retries:u32 }   // not comparable
+///   let mut queue = PayloadHashHeap::<u32,u8,Task>::new_maxheap();
+///   queue.insert_with_meta(1, 3, Task{cmd:"backup".into(), retries:0});
+///   queue.insert_with_meta(2, 7, Task{cmd:"deploy".into(), retries:0});
+///   queue.payload_mut(&2).unwrap().retries += 1;    // no reheap
+///   queue.modify_priority(&1, |p| *p = 9);
+///   let (k,p,task) = queue.pop_with_meta().unwrap();
+///   assert_eq!((k,p,task.cmd.as_str()), (1,9,"backup"));
+///   assert_eq!(queue.get_meta(&2).map(|t|t.retries), Some(1));
+/// ```
+pub type PayloadHashHeap<KT,PT,A> = HashHeap<KT,WithMeta<PT,A>>;
+
+impl<KT:Hash+Eq, VT:PartialOrd, A> HashHeap<KT,WithMeta<VT,A>> {
+  /// Add or change a key-value pair with the given payload, returning the
+  /// replaced triple, if it exists.  Same complexity as [HashHeap::insert].
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut jobs = HashHeap::<u32,WithMeta<u8,String>>::new_maxheap();
+  ///   jobs.insert_with_meta(1, 5, "backup".to_string());
+  ///   jobs.insert_with_meta(2, 9, "deploy".to_string());
+  ///   assert_eq!(jobs.get_meta(&1).unwrap(), "backup");
+  ///   jobs.modify_meta(&1, |m| m.push('!'));
+  ///   assert_eq!(jobs.pop_with_meta(), Some((2, 9, "deploy".to_string())));
+  ///   assert_eq!(jobs.peek_with_meta(), Some((&1, &5, &"backup!".to_string())));
+  /// ```
+  pub fn insert_with_meta(&mut self, key:KT, val:VT, meta:A) -> Option<(KT,VT,A)> {
+    self.insert(key, WithMeta::new(val,meta)).map(|(k,w)|(k,w.val,w.meta))
+  }
+
+  /// returns the payload associated with the key.  O(1)
+  pub fn get_meta(&self, key:&KT) -> Option<&A> {
+    self.get(key).map(|w|&w.meta)
+  }
+
+  /// returns the value and payload associated with the key.  O(1)
+  pub fn get_with_meta(&self, key:&KT) -> Option<(&VT,&A)> {
+    self.get(key).map(|w|(&w.val,&w.meta))
+  }
+
+  /// Changes the payload associated with the key, returning false if the
+  /// key was not found.  Since the payload does not affect the priority,
+  /// the heap is not adjusted and this operation runs in O(1) time.
+  pub fn modify_meta<F:FnOnce(&mut A)>(&mut self, key:&KT, f:F) -> bool {
+    if let (h,true) = self.findslot(key) {
+      let (_,vi) = self.kmap[&h];
+      f(&mut self.vals[vi].0.meta);
+      true
+    }
+    else { false }
+  }
+
+  /// returns a mutable reference to the payload associated with the key.
+  /// Since the payload does not affect the priority, the heap doesn't
+  /// need to be adjusted afterwards.  O(1)
+  pub fn payload_mut(&mut self, key:&KT) -> Option<&mut A> {
+    let (h,found) = self.findslot(key);
+    if !found { return None; }
+    let (_,vi) = self.kmap[&h];
+    Some(&mut self.vals[vi].0.meta)
+  }
+
+  /// Applies the closure to the priority associated with the key, leaving
+  /// the payload unchanged, and repositions the entry.  Returns false if
+  /// the key was not found.  O(log n)
+  pub fn modify_priority<F:FnOnce(&mut VT)>(&mut self, key:&KT, f:F) -> bool {
+    self.modify(key, |w| f(&mut w.val)).is_some()
+  }
+
+  /// returns the highest-priority entry with its payload.  O(1)
+  pub fn peek_with_meta(&self) -> Option<(&KT,&VT,&A)> {
+    self.peek().map(|(k,w)|(k,&w.val,&w.meta))
+  }
+
+  /// removes and returns the highest-priority entry with its payload.
+  /// O(log n)
+  pub fn pop_with_meta(&mut self) -> Option<(KT,VT,A)> {
+    self.pop().map(|(k,w)|(k,w.val,w.meta))
+  }
+
+  /// removes and returns the entry with the given key, with its payload.
+  /// O(log n)
+  pub fn remove_with_meta(&mut self, key:&KT) -> Option<(KT,VT,A)> {
+    self.remove(key).map(|(k,w)|(k,w.val,w.meta))
+  }
+}//impl HashHeap with metadata
+
+impl<KT:Hash+Eq, VT:PartialOrd, N:PartialOrd> HashHeap<KT,WithMeta<VT,N>> {
+  /// Treats the payload as a version number (or timestamp) and applies
+  /// the update only if the given version is greater than the one stored
+  /// with the key, or if the key is new.  Returns true if the update was
+  /// applied.  This guards against stale updates that are re-delivered
+  /// out of order.  O(log n).
+  /// ```
+  /// # use hashheap::*;
+  ///   let mut hh = HashHeap::<&str,WithMeta<u32,u64>>::new_minheap();
+  ///   assert!(hh.update_if_newer("a", 1, 50));
+  ///   assert!(hh.update_if_newer("a", 3, 20));
+  ///   assert!(!hh.update_if_newer("a", 2, 10));  // stale
+  ///   assert_eq!(hh.get_with_meta(&"a"), Some((&20,&3)));
+  /// ```
+  pub fn update_if_newer(&mut self, key:KT, version:N, val:VT) -> bool {
+    match self.findslot(&key) {
+      (h,true) => {
+        let (_,vi) = self.kmap[&h];
+        if self.vals[vi].0.meta < version {
+          self.vals[vi].0 = WithMeta::new(val,version);
+          self.reposition(vi);
+          true
+        }
+        else { false }
+      },
+      _ => self.push(key, WithMeta::new(val,version)),
+    }
+  }//update_if_newer
+}